@@ -0,0 +1,115 @@
+use std::{hint::black_box, time::Duration};
+
+use bevy::{
+    asset::AssetPlugin,
+    ecs::system::SystemState,
+    math::{primitives::*, Isometry3d, Quat, Vec3},
+    prelude::*,
+};
+use bevy_prototype_sdf::{dim3::Dim3, ExecutableSdfs, Sdf, Sdf3d, SdfPlugin, SdfProcessed};
+use criterion::{criterion_group, criterion_main, Criterion};
+use sdf_peck::{
+    core::{capsule_contacts, sphere_contacts, ScaledIsometry3d},
+    march_edge,
+};
+
+const ASSETS: [&str; 3] = ["sphere_stage.sdf3d", "csg_subtract.sdf3d", "terrain.sdf3d"];
+
+#[derive(Resource, Default)]
+struct ProcessedSdfs(Vec<AssetId<Sdf3d>>);
+
+/// Loads the test assets and steps the app until all of them are processed.
+fn load_assets() -> (App, Vec<Handle<Sdf3d>>) {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: "tests/assets".into(),
+            ..default()
+        },
+        SdfPlugin,
+    ))
+    .init_resource::<ProcessedSdfs>()
+    .add_observer(
+        |trigger: On<SdfProcessed>, mut processed: ResMut<ProcessedSdfs>| {
+            processed.0.push(AssetId::from(trigger.event().0));
+        },
+    );
+    app.finish();
+    app.cleanup();
+
+    let handles = ASSETS
+        .map(|path| app.world().resource::<AssetServer>().load(path))
+        .to_vec();
+    for _ in 0..1000 {
+        app.update();
+        let processed = &app.world().resource::<ProcessedSdfs>().0;
+        if handles
+            .iter()
+            .all(|handle| processed.contains(&handle.id()))
+        {
+            return (app, handles);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    panic!("Timed out loading benchmark assets");
+}
+
+fn collision(c: &mut Criterion) {
+    let (mut app, handles) = load_assets();
+    let mut state = SystemState::<ExecutableSdfs<Dim3>>::new(app.world_mut());
+    let sdfs = state.get(app.world());
+
+    for (path, handle) in ASSETS.iter().zip(handles.iter()) {
+        let (_, sdf) = sdfs.get(handle.id()).unwrap();
+        let aabb = sdf.aabb(Isometry3d::IDENTITY);
+        let top = Vec3::new(0., aabb.max.y, 0.);
+
+        let mut group = c.benchmark_group(*path);
+
+        let sphere_iso = Isometry3d::from_translation(top + Vec3::Y * 0.45);
+        group.bench_function("sphere_contacts", |b| {
+            b.iter(|| {
+                sphere_contacts(
+                    Sphere::new(0.5),
+                    black_box(sphere_iso),
+                    &sdf,
+                    ScaledIsometry3d::new(Isometry3d::IDENTITY, 1.),
+                    0.1,
+                )
+            })
+        });
+
+        let capsule_iso = Isometry3d::new(top + Vec3::Y * 0.45, Quat::from_rotation_z(1.2));
+        group.bench_function("capsule_contacts", |b| {
+            b.iter(|| {
+                capsule_contacts(
+                    Capsule3d::new(0.4, 1.),
+                    black_box(capsule_iso),
+                    &sdf,
+                    ScaledIsometry3d::new(Isometry3d::IDENTITY, 1.),
+                    0.1,
+                )
+            })
+        });
+
+        let start = Vec3::new(0.3, aabb.max.y + 5., 0.2);
+        group.bench_function("march_edge", |b| {
+            b.iter(|| march_edge(&sdf, black_box(start), Vec3::NEG_Y, 0.001, 100.))
+        });
+
+        let rotation = Quat::from_euler(EulerRot::XYZ, 0.3, 0.7, 0.1);
+        group.bench_function("aabb", |b| {
+            b.iter(|| sdf.aabb(Isometry3d::from_rotation(black_box(rotation))))
+        });
+        #[cfg(feature = "tight-aabb")]
+        group.bench_function("tight_aabb", |b| {
+            b.iter(|| sdf_peck::bench::tight_aabb(&sdf, aabb, black_box(rotation)))
+        });
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, collision);
+criterion_main!(benches);
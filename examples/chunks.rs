@@ -0,0 +1,103 @@
+//! Restricts a huge SDF collider to the chunk around the player, so the broad phase only pairs
+//! it with bodies nearby and contacts only sample the surface in that chunk.
+
+use avian3d::prelude::*;
+use bevy::{math::bounding::Aabb3d, prelude::*};
+use bevy_prototype_sdf::SdfPlugin;
+use sdf_peck::{SdfCollider, SdfCollisionPlugin};
+
+/// Size of a chunk in the local space of the planet
+const CHUNK_SIZE: f32 = 20.;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            SdfPlugin,
+            PhysicsPlugins::default(),
+            SdfCollisionPlugin::<()>::default(),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(FixedUpdate, (move_player, follow_player_chunk).chain())
+        .run();
+}
+
+#[derive(Component)]
+struct Planet;
+
+#[derive(Component)]
+struct Player;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    loader: Res<AssetServer>,
+) {
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_xyz(3., 5., 0.).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    commands.spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(loader.load("planet.sdf3d"))
+            .with_region(Some(Aabb3d::new(Vec3::Y * 1000., Vec3::splat(CHUNK_SIZE)))),
+        Transform::from_xyz(0., -1000., 0.),
+        Planet,
+    ));
+
+    commands
+        .spawn((
+            RigidBody::Dynamic,
+            Mesh3d(meshes.add(Sphere::new(0.5).mesh().ico(3).unwrap())),
+            MeshMaterial3d(materials.add(Color::srgb(0.4, 0.85, 1.))),
+            SdfCollider::sphere(0.5),
+            Transform::from_xyz(0., 3., 0.),
+            Player,
+        ))
+        .with_child((
+            Camera3d::default(),
+            Transform::from_xyz(0., 4., 10.).looking_at(Vec3::ZERO, Vec3::Y),
+        ));
+}
+
+fn move_player(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut players: Query<&mut LinearVelocity, With<Player>>,
+) {
+    let mut input = Vec3::ZERO;
+    for (key, direction) in [
+        (KeyCode::KeyW, Vec3::NEG_Z),
+        (KeyCode::KeyS, Vec3::Z),
+        (KeyCode::KeyA, Vec3::NEG_X),
+        (KeyCode::KeyD, Vec3::X),
+    ] {
+        if keys.pressed(key) {
+            input += direction;
+        }
+    }
+    for mut velocity in players.iter_mut() {
+        velocity.x = input.x * 5.;
+        velocity.z = input.z * 5.;
+    }
+}
+
+/// Moves the region of the planet to the chunk the player is in, only when it changes chunk.
+fn follow_player_chunk(
+    players: Query<&Position, With<Player>>,
+    mut planets: Query<(&Position, &Rotation, &mut SdfCollider), With<Planet>>,
+) {
+    let Ok(player) = players.single() else {
+        return;
+    };
+    for (pos, rot, mut collider) in planets.iter_mut() {
+        let local = rot.0.inverse() * (player.0 - pos.0) / collider.uniform_scale();
+        let chunk = (local / CHUNK_SIZE).round() * CHUNK_SIZE;
+        // Chunks overlap by half their size, so the player never stands on a border
+        let region = Aabb3d::new(chunk, Vec3::splat(CHUNK_SIZE));
+        if collider.region() != Some(region) {
+            collider.set_region(Some(region));
+        }
+    }
+}
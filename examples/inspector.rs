@@ -0,0 +1,148 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_prototype_sdf::SdfPlugin;
+use sdf_peck::{SdfCollider, SdfCollisionPlugin, SdfSpatialQuery};
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            SdfPlugin,
+            PhysicsPlugins::default(),
+            SdfCollisionPlugin::<()>::default(),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (move_probe, inspect).chain())
+        .run();
+}
+
+#[derive(Component)]
+struct Probe;
+
+#[derive(Component)]
+struct Readout;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    loader: Res<AssetServer>,
+) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0., 6., 10.).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    commands.spawn((
+        DirectionalLight::default(),
+        Transform::from_xyz(3., 5., 0.).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    let material = materials.add(Color::srgba(0.8, 0.8, 0.8, 0.6));
+    commands.spawn((
+        RigidBody::Static,
+        SdfCollider::sphere(1.),
+        Mesh3d(meshes.add(Sphere::new(1.).mesh().ico(4).unwrap())),
+        MeshMaterial3d(material.clone()),
+        Transform::from_xyz(-3., 1., 0.),
+    ));
+    commands.spawn((
+        RigidBody::Static,
+        SdfCollider::capsule(0.5, 2.),
+        Mesh3d(meshes.add(Capsule3d::new(0.5, 2.).mesh())),
+        MeshMaterial3d(material),
+        Transform::from_xyz(0., 1.5, 0.),
+    ));
+    commands.spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(loader.load("inspector_target.sdf3d")),
+        Transform::from_xyz(3., 1., 0.),
+    ));
+
+    commands.spawn((Probe, Transform::from_xyz(0., 1., 3.)));
+    commands.spawn((
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        },
+        Readout,
+    ));
+}
+
+fn move_probe(
+    mut probe: Single<&mut Transform, With<Probe>>,
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    let axis = |neg, pos| (input.pressed(pos) as i32 - input.pressed(neg) as i32) as f32;
+    let movement = Vec3::new(
+        axis(KeyCode::KeyA, KeyCode::KeyD),
+        axis(KeyCode::KeyQ, KeyCode::KeyE),
+        axis(KeyCode::KeyW, KeyCode::KeyS),
+    );
+    probe.translation += movement * time.delta_secs() * 2.;
+
+    let yaw = axis(KeyCode::ArrowRight, KeyCode::ArrowLeft) * time.delta_secs();
+    let pitch = axis(KeyCode::ArrowDown, KeyCode::ArrowUp) * time.delta_secs();
+    probe.rotate_y(yaw);
+    probe.rotate_local_x(pitch);
+}
+
+fn inspect(
+    mut gizmos: Gizmos,
+    probe: Single<&Transform, With<Probe>>,
+    mut readout: Single<&mut Text, With<Readout>>,
+    spatial_query: SpatialQuery<SdfCollider>,
+    sdf_query: SdfSpatialQuery,
+) {
+    let filter = SpatialQueryFilter::DEFAULT;
+    let origin = probe.translation;
+    let direction = probe.forward();
+
+    gizmos.sphere(origin, 0.05, Color::WHITE);
+    let mut lines = vec![format!("Probe: {origin:.2}")];
+
+    if let Some(projection) = spatial_query.project_point(origin, true, &filter) {
+        let distance = origin.distance(projection.point);
+        let signed = if projection.is_inside {
+            -distance
+        } else {
+            distance
+        };
+        gizmos.line(origin, projection.point, Color::srgb(0.5, 1., 0.5));
+        lines.push(format!(
+            "Closest: {} at {:.2}, distance {signed:.3}",
+            projection.entity, projection.point
+        ));
+    }
+
+    let contained = spatial_query.point_intersections(origin, &filter);
+    lines.push(format!("Inside: {contained:?}"));
+
+    match sdf_query.cast_ray_detailed(origin, direction, 20., true, &filter) {
+        Some((entity, hit)) => {
+            gizmos.line(origin, hit.point, Color::srgb(1., 0.5, 0.5));
+            gizmos.arrow(
+                hit.point,
+                hit.point + hit.normal * 0.3,
+                Color::srgb(1., 0.5, 0.5),
+            );
+            lines.push(format!(
+                "Ray: {entity} at {:.3}, normal {:.2}, {} iterations{}",
+                hit.distance,
+                hit.normal,
+                hit.iterations,
+                if hit.inside { ", started inside" } else { "" },
+            ));
+        }
+        None => {
+            gizmos.line(origin, origin + direction * 20., Color::WHITE);
+            lines.push("Ray: no hit".into());
+        }
+    }
+
+    readout.0 = lines.join("\n");
+}
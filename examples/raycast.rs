@@ -7,9 +7,9 @@ use bevy::{
 };
 use bevy_march::{
     MarcherConeTexture, MarcherMainTextures, MarcherMaterial, MarcherScale, MarcherSettings,
-    RayMarcherPlugin, RenderedSdf,
+    RayMarcherPlugin,
 };
-use sdf_peck::{ColliderShape, SdfCollider, SdfCollisionPlugin};
+use sdf_peck::{SdfCollisionPlugin, SdfObject, SdfObjectPlugin, SdfSpatialQuery};
 
 fn main() {
     let mut app = App::new();
@@ -21,6 +21,7 @@ fn main() {
         RayMarcherPlugin::<SdfMaterial>::new(march_shader),
         PhysicsPlugins::default(),
         SdfCollisionPlugin::<()>::default(),
+        SdfObjectPlugin::<SdfMaterial>::default(),
     ))
     .add_systems(Startup, setup)
     .add_systems(FixedUpdate, cast_ray)
@@ -62,15 +63,13 @@ fn setup(
         Transform::from_xyz(3., 5., 0.).looking_at(Vec3::ZERO, Vec3::Y),
     ));
 
-    let sdf = loader.load("query_target.sdf3d");
     commands.spawn((
         RigidBody::Static,
-        SdfCollider::sdf(sdf.clone()),
+        SdfObject::new(
+            loader.load("query_target.sdf3d"),
+            materials.add(SdfMaterial { color: Vec3::ONE }),
+        ),
         Transform::from_translation(CENTER),
-        RenderedSdf {
-            sdf,
-            material: materials.add(SdfMaterial { color: Vec3::ONE }),
-        },
     ));
 }
 
@@ -100,31 +99,27 @@ fn move_camera(
 fn cast_ray(
     mut gizmos: Gizmos,
     camera: Single<&Transform, With<Camera3d>>,
-    spatial_query: SpatialQuery<SdfCollider>,
+    spatial_query: SdfSpatialQuery,
 ) {
     let origin = camera.translation;
-    // let direction = camera.forward();
-    // let max_dist = 5.;
-    // gizmos.line(origin, origin + direction * max_dist, Color::WHITE);
-    let hits = spatial_query.shape_intersections(
-        &ColliderShape::Sphere(Sphere::new(1.3)),
+    let direction = camera.forward();
+    let max_dist = 5.;
+    gizmos.line(origin, origin + direction * max_dist, Color::WHITE);
+
+    let Some(hit) = spatial_query.sphere_cast(
         origin,
-        Quat::default(),
+        direction,
+        0.2,
+        max_dist,
         &SpatialQueryFilter::DEFAULT,
-    );
-
-    if hits.is_empty() {
+    ) else {
         return;
-    }
+    };
 
-    gizmos.sphere(origin, 1.3, Color::srgb(1., 0.5, 0.5));
-
-    // let hit_pos = origin + direction * hit.distance;
-    // gizmos.sphere(hit_pos, 0.2, Color::srgb(1., 0.5, 0.5));
-
-    // gizmos.arrow(
-    //     hit_pos + hit.point2,
-    //     hit_pos + hit.point2 + hit.normal2 * 0.1,
-    //     Color::srgb(1., 0.5, 0.5),
-    // );
+    gizmos.sphere(hit.center, 0.2, Color::srgb(1., 0.5, 0.5));
+    gizmos.arrow(
+        hit.point,
+        hit.point + hit.normal * 0.1,
+        Color::srgb(1., 0.5, 0.5),
+    );
 }
@@ -1,31 +1,105 @@
 use std::ops::Deref;
 
-use bevy::math::Vec3;
+use bevy::math::{Isometry3d, Vec3};
 use bevy_math::Vec3A;
 
+use crate::primitives::ScaledIsometry3d;
+
 // Rework contacts, see Jondolf's example:
 // https://discord.com/channels/691052431525675048/1124043933886976171/1398707094252945408
-#[derive(Debug)]
+/// A contact between two shapes, with the normal pointing from the first to the second.
+///
+/// More data may be added over time, so contacts can only be created by this crate.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
 pub struct Contact {
     pub point: Vec3,
     pub anchor1: Vec3,
     pub anchor2: Vec3,
     pub normal: Vec3,
     pub penetration: f32,
+    pub(crate) local_point1: Vec3,
+    pub(crate) local_point2: Vec3,
+}
+
+impl Contact {
+    /// A contact without known placements of the shapes, using the anchors as local points.
+    pub(crate) fn new(
+        point: Vec3,
+        anchor1: Vec3,
+        anchor2: Vec3,
+        normal: Vec3,
+        penetration: f32,
+    ) -> Self {
+        Self {
+            point,
+            anchor1,
+            anchor2,
+            normal,
+            penetration,
+            local_point1: anchor1,
+            local_point2: anchor2,
+        }
+    }
+
+    /// The contact point in the local space of the first shape, saving a transform for decals and
+    /// deformation.
+    ///
+    /// The scale of SDFs is undone, while analytic shapes like spheres are placed unscaled and
+    /// scaled themselves.
+    pub fn local_point1(&self) -> Vec3 {
+        self.local_point1
+    }
+
+    /// The contact point in the local space of the second shape, with its scale undone.
+    pub fn local_point2(&self) -> Vec3 {
+        self.local_point2
+    }
+
+    /// Moves the contact into the space `iso` places the shapes in, keeping the local points.
+    pub(crate) fn transformed(self, iso: Isometry3d) -> Self {
+        Self {
+            point: iso.transform_point(self.point).into(),
+            anchor1: iso.rotation * self.anchor1,
+            anchor2: iso.rotation * self.anchor2,
+            normal: iso.rotation * self.normal,
+            ..self
+        }
+    }
 }
 
-pub struct Manifolds<'a, T: From<Contact>>(pub(crate) &'a mut Vec<T>);
+pub struct Manifolds<'a, T: From<Contact>> {
+    pub(crate) contacts: &'a mut Vec<T>,
+    filter: Option<&'a dyn Fn(&mut Contact) -> bool>,
+}
+
+impl<'a, T: From<Contact>> Manifolds<'a, T> {
+    pub(crate) fn new(contacts: &'a mut Vec<T>) -> Self {
+        Self {
+            contacts,
+            filter: None,
+        }
+    }
+
+    /// Passes every contact through `filter` before it's added, dropping it if that returns false.
+    pub(crate) fn with_filter(mut self, filter: &'a dyn Fn(&mut Contact) -> bool) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+}
 
 impl<T: From<Contact>> Deref for Manifolds<'_, T> {
     type Target = Vec<T>;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        self.contacts
     }
 }
 
 pub(crate) struct ManifoldAdder<'a, T: From<Contact>> {
     manifolds: Manifolds<'a, T>,
     flipped: bool,
+    // Placements of the shapes that contact points are made local to
+    frames: Option<(ScaledIsometry3d, ScaledIsometry3d)>,
 }
 
 impl<'a, T: From<Contact>> ManifoldAdder<'a, T> {
@@ -33,6 +107,7 @@ impl<'a, T: From<Contact>> ManifoldAdder<'a, T> {
         Self {
             manifolds,
             flipped: false,
+            frames: None,
         }
     }
 
@@ -40,9 +115,35 @@ impl<'a, T: From<Contact>> ManifoldAdder<'a, T> {
         Self {
             manifolds,
             flipped: true,
+            frames: None,
         }
     }
 
+    /// An adder into the same manifolds, for shapes made of parts that each add their contacts.
+    pub fn reborrow(&mut self) -> ManifoldAdder<'_, T> {
+        ManifoldAdder {
+            manifolds: Manifolds {
+                contacts: &mut *self.manifolds.contacts,
+                filter: self.manifolds.filter,
+            },
+            flipped: self.flipped,
+            frames: self.frames,
+        }
+    }
+
+    /// Sets the placements of the shapes, in the order the contacts are generated in.
+    ///
+    /// Only the first call counts, so shapes that delegate to another pair of shapes keep their
+    /// own local spaces.
+    pub fn set_frames(
+        &mut self,
+        frame_a: impl Into<ScaledIsometry3d>,
+        frame_b: impl Into<ScaledIsometry3d>,
+    ) {
+        self.frames
+            .get_or_insert_with(|| (frame_a.into(), frame_b.into()));
+    }
+
     pub fn push(
         &mut self,
         point: Vec3A,
@@ -51,15 +152,24 @@ impl<'a, T: From<Contact>> ManifoldAdder<'a, T> {
         normal: Vec3A,
         penetration: f32,
     ) {
-        self.manifolds.0.push(
-            Contact {
-                point: point.into(),
-                anchor1: if self.flipped { anchor_b } else { anchor_a }.into(),
-                anchor2: if self.flipped { anchor_a } else { anchor_b }.into(),
-                normal: if self.flipped { -normal } else { normal }.into(),
-                penetration,
+        let (frame_a, frame_b) = self
+            .frames
+            .unwrap_or((ScaledIsometry3d::IDENTITY, ScaledIsometry3d::IDENTITY));
+        let (local_a, local_b) = (frame_a.local_point(point), frame_b.local_point(point));
+        let mut contact = Contact {
+            point: point.into(),
+            anchor1: if self.flipped { anchor_b } else { anchor_a }.into(),
+            anchor2: if self.flipped { anchor_a } else { anchor_b }.into(),
+            normal: if self.flipped { -normal } else { normal }.into(),
+            penetration,
+            local_point1: if self.flipped { local_b } else { local_a },
+            local_point2: if self.flipped { local_a } else { local_b },
+        };
+        if let Some(filter) = self.manifolds.filter {
+            if !filter(&mut contact) {
+                return;
             }
-            .into(),
-        );
+        }
+        self.manifolds.contacts.push(contact.into());
     }
 }
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use bevy_prototype_sdf::SdfProcessed;
+
+use crate::{primitives::LocalSdf, SdfCollider, SdfColliderKind, SdfContext};
+
+/// Projections needed for surfaces of SDFs that aren't exact distance fields
+const PROJECTION_ITERATIONS: usize = 4;
+
+/// A point on the surface of an SDF collider to attach joints to, found with
+/// [`SdfSpatialQuery::surface_anchor`](crate::SdfSpatialQuery::surface_anchor).
+///
+/// As a component, the anchor is projected onto the surface again when the collider's SDF asset
+/// is reloaded. Systems that create joints from it can react to `Changed<SdfSurfaceAnchor>`.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SdfSurfaceAnchor {
+    pub collider: Entity,
+    /// Anchor relative to the position and rotation of the collider, the local anchor of a joint
+    /// if the collider is on the body itself
+    pub local_anchor: Vec3,
+    /// Surface normal at the anchor, relative to the rotation of the collider
+    pub local_normal: Vec3,
+}
+
+impl SdfSurfaceAnchor {
+    /// Projects a point relative to the collider onto its surface.
+    pub(crate) fn project(
+        collider_entity: Entity,
+        collider: &SdfCollider,
+        sdf: &impl LocalSdf,
+        local_point: Vec3,
+    ) -> Self {
+        let mut sdf_point = local_point / collider.scale;
+        for _ in 0..PROJECTION_ITERATIONS {
+            let gradient = sdf.gradient(sdf_point).normalize_or(Vec3::Y);
+            sdf_point -= gradient * sdf.distance(sdf_point);
+        }
+        Self {
+            collider: collider_entity,
+            local_anchor: sdf_point * collider.scale,
+            local_normal: sdf.gradient(sdf_point).normalize_or(Vec3::Y),
+        }
+    }
+}
+
+pub(crate) fn reproject_surface_anchors(
+    trigger: On<SdfProcessed>,
+    mut anchors: Query<&mut SdfSurfaceAnchor>,
+    colliders: Query<&SdfCollider>,
+    context: SdfContext,
+) {
+    let SdfProcessed(id) = trigger.event();
+    let id = AssetId::from(*id);
+    for mut anchor in anchors.iter_mut() {
+        let Ok(collider) = colliders.get(anchor.collider) else {
+            continue;
+        };
+        if !matches!(collider.collider(), SdfColliderKind::Arbitrary(handle) if handle.id() == id) {
+            continue;
+        }
+        let Some(sdf) = collider.full_sdf(&context) else {
+            continue;
+        };
+        let reprojected =
+            SdfSurfaceAnchor::project(anchor.collider, collider, &sdf, anchor.local_anchor);
+        anchor.set_if_neq(reprojected);
+    }
+}
@@ -1,30 +1,72 @@
+use std::{cell::RefCell, f32::consts::TAU};
+
 use avian3d::{
     collision::collider::{PairContext, SingleContext},
     prelude::*,
 };
-use bevy::prelude::*;
-use bevy_math::bounding::{Bounded3d, BoundingVolume};
-use bevy_prototype_sdf::{dim3::Dim3, ExecutableSdfs};
+use bevy::{math::FloatPow, prelude::*};
+use bevy_math::{
+    bounding::{Bounded3d, BoundingVolume},
+    ops,
+};
+use bevy_prototype_sdf::ExecutableSdf3d;
 
 use crate::{
     adder::{Contact, ManifoldAdder, Manifolds},
+    backend::SdfSource,
     collider::SdfColliderKind,
-    primitives::{Collider, ScaledIsometry3d},
-    SdfCollider,
+    compliance,
+    contact_cache::PairNormals,
+    context::{SdfContext, UnsupportedPairs},
+    diagnostics::{CountingSdf, SdfEvaluations},
+    motion::SurfaceMotion,
+    one_way,
+    primitives::{
+        capsule_sdf_collisions, Collider, LocalSdf, Parameterized, ScaledIsometry3d,
+        SegmentWarmStart, Shelled, SmoothedNormals, WithMarchQuality,
+    },
+    slope, SdfCollider,
 };
 
+#[cfg(feature = "tight-aabb")]
+use crate::primitives::tight_aabb;
 use avian3d::prelude::{AnyCollider, ContactManifold, ScalableCollider};
 use bevy::math::Vec3;
 
+// Most pairs keep the same number of manifolds every step, so the point buffers of the previous
+// step's manifolds are reused instead of allocating one per contact
+thread_local! {
+    static POINT_BUFFERS: RefCell<Vec<Vec<ContactPoint>>> = const { RefCell::new(Vec::new()) };
+}
+
+const MAX_POOLED_POINT_BUFFERS: usize = 256;
+
+fn recycle_manifolds(contacts: &mut Vec<ContactManifold>) {
+    POINT_BUFFERS.with_borrow_mut(|buffers| {
+        for manifold in contacts.drain(..) {
+            if buffers.len() >= MAX_POOLED_POINT_BUFFERS {
+                break;
+            }
+            let mut points = manifold.points;
+            points.clear();
+            buffers.push(points);
+        }
+    });
+}
+
 impl From<Contact> for ContactManifold {
     fn from(value: Contact) -> Self {
+        let mut points = POINT_BUFFERS
+            .with_borrow_mut(|buffers| buffers.pop())
+            .unwrap_or_default();
+        points.push(ContactPoint::new(
+            value.anchor1,
+            value.anchor2,
+            value.point,
+            value.penetration,
+        ));
         Self {
-            points: vec![ContactPoint::new(
-                value.anchor1,
-                value.anchor2,
-                value.point,
-                value.penetration,
-            )],
+            points,
             normal: value.normal,
             friction: 0.,
             restitution: 0.,
@@ -35,28 +77,112 @@ impl From<Contact> for ContactManifold {
 
 impl ComputeMassProperties3d for SdfCollider {
     fn mass(&self, density: f32) -> f32 {
+        if let Some(properties) = self.mass_properties {
+            return properties.volume * self.scale.cubed() * density;
+        }
         match self.collider {
-            SdfColliderKind::Sphere(sphere) => sphere.mass(density),
-            SdfColliderKind::Capsule(capsule) => capsule.mass(density),
-            _ => density,
+            SdfColliderKind::Sphere(mut sphere) => {
+                sphere.radius *= self.scale;
+                sphere.mass(density)
+            }
+            SdfColliderKind::Capsule(mut capsule) => {
+                capsule.radius *= self.scale;
+                capsule.half_length *= self.scale;
+                capsule.mass(density)
+            }
+            SdfColliderKind::Ellipsoid(mut ellipsoid) => {
+                ellipsoid.half_size *= self.scale;
+                ellipsoid.volume() * density
+            }
+            SdfColliderKind::SphereCluster(ref cluster) => {
+                Sphere::new(cluster.radius * self.scale).mass(density) * cluster.len() as f32
+            }
+            SdfColliderKind::SweptSphere(swept) => {
+                Sphere::new(swept.radius * self.scale).mass(density)
+            }
+            SdfColliderKind::Polyline(ref polyline) => {
+                // The capsules overlap at the joints, so their caps only count once
+                Capsule3d::new(polyline.radius * self.scale, polyline.length() * self.scale)
+                    .mass(density)
+            }
+            // Segments have no volume, and SDFs can't be estimated without evaluating them
+            _ => self.scale.cubed() * density,
         }
     }
 
     fn unit_principal_angular_inertia(&self) -> Vec3 {
-        match self.collider {
+        if let Some(properties) = self.mass_properties {
+            return properties.unit_principal_angular_inertia * self.scale.squared();
+        }
+        let unscaled = match self.collider {
             SdfColliderKind::Sphere(sphere) => sphere.unit_principal_angular_inertia(),
             SdfColliderKind::Capsule(capsule) => capsule.unit_principal_angular_inertia(),
+            SdfColliderKind::Ellipsoid(ellipsoid) => {
+                let sq = ellipsoid.half_size * ellipsoid.half_size;
+                Vec3::new(sq.y + sq.z, sq.x + sq.z, sq.x + sq.y) * 0.2
+            }
+            SdfColliderKind::SphereCluster(ref cluster) => {
+                // Only the diagonal of the inertia tensor around the center of mass is used
+                let com = cluster.center_of_mass();
+                let spread = cluster
+                    .centers()
+                    .map(|c| (c - com) * (c - com))
+                    .sum::<Vec3>()
+                    / cluster.len().max(1) as f32;
+                Sphere::new(cluster.radius).unit_principal_angular_inertia()
+                    + Vec3::new(
+                        spread.y + spread.z,
+                        spread.x + spread.z,
+                        spread.x + spread.y,
+                    )
+            }
+            SdfColliderKind::SweptSphere(swept) => {
+                Sphere::new(swept.radius).unit_principal_angular_inertia()
+            }
+            SdfColliderKind::Segment(segment) => {
+                // The diagonal of the inertia tensor of a thin rod around its middle
+                let line = segment.vertices[1] - segment.vertices[0];
+                let dir = line.normalize_or_zero();
+                (Vec3::ONE - dir * dir) * line.length_squared() / 12.
+            }
+            SdfColliderKind::Polyline(ref polyline) => {
+                // Every segment as a thin rod around the center of mass, weighted by its length
+                let com = polyline.center_of_mass();
+                let rods = polyline
+                    .segments()
+                    .map(|(a, b)| {
+                        let line = b - a;
+                        let dir = line.normalize_or_zero();
+                        let offset = (a + b) * 0.5 - com;
+                        let sq = offset * offset;
+                        ((Vec3::ONE - dir * dir) * line.length_squared() / 12.
+                            + Vec3::new(sq.y + sq.z, sq.x + sq.z, sq.x + sq.y))
+                            * line.length()
+                    })
+                    .sum::<Vec3>()
+                    / polyline.length().max(f32::EPSILON);
+                Sphere::new(polyline.radius).unit_principal_angular_inertia() + rods
+            }
             _ => Sphere::new(1.).unit_principal_angular_inertia(),
-        }
+        };
+        unscaled * self.scale * self.scale
     }
 
     fn center_of_mass(&self) -> Vec3 {
-        Vec3::ZERO
+        if let Some(properties) = self.mass_properties {
+            return properties.center_of_mass * self.scale;
+        }
+        match &self.collider {
+            SdfColliderKind::SphereCluster(cluster) => cluster.center_of_mass() * self.scale,
+            SdfColliderKind::Segment(segment) => segment.center() * self.scale,
+            SdfColliderKind::Polyline(polyline) => polyline.center_of_mass() * self.scale,
+            _ => Vec3::ZERO,
+        }
     }
 }
 
 impl AnyCollider for SdfCollider {
-    type Context = ExecutableSdfs<'static, Dim3>;
+    type Context = SdfContext<'static, 'static>;
 
     fn aabb_with_context(
         &self,
@@ -64,36 +190,7 @@ impl AnyCollider for SdfCollider {
         rotation: impl Into<Rotation>,
         context: SingleContext<Self::Context>,
     ) -> ColliderAabb {
-        let iso = Isometry3d::new(position, *rotation.into());
-        let aabb = match &self.collider {
-            &SdfColliderKind::Sphere(mut s) => {
-                s.radius *= self.scale;
-                s.aabb_3d(iso)
-            }
-            &SdfColliderKind::Capsule(mut c) => {
-                c.radius *= self.scale;
-                c.half_length *= self.scale;
-                c.aabb_3d(iso)
-            }
-            SdfColliderKind::Arbitrary(handle) => {
-                let Some((_, sdf)) = context.get(handle.id()) else {
-                    eprintln!("Failed to get SDF!");
-                    return ColliderAabb::INVALID;
-                };
-
-                let fake_iso = Isometry3d::new(Vec3A::ZERO, iso.rotation);
-
-                let mut aabb = sdf.aabb(fake_iso);
-                aabb.min *= self.scale;
-                aabb.max *= self.scale;
-                aabb.translate_by(iso.translation);
-                aabb
-            }
-        };
-        ColliderAabb {
-            min: aabb.min.into(),
-            max: aabb.max.into(),
-        }
+        self.world_aabb(Isometry3d::new(position, *rotation.into()), &context)
     }
 
     fn contact_manifolds_with_context(
@@ -107,14 +204,117 @@ impl AnyCollider for SdfCollider {
         contacts: &mut Vec<ContactManifold>,
         context: PairContext<Self::Context>,
     ) {
-        contacts.clear();
-        let manifolds = Manifolds(contacts);
-
+        // Stay in Vec3A from here on, converting only where avian reads the contacts
         let iso1 = Isometry3d::new(position1, *rotation1.into());
         let iso2 = Isometry3d::new(position2, *rotation2.into());
+        self.isometry_contact_manifolds(other, iso1, iso2, pred_dist, contacts, context);
+    }
+}
+
+impl SdfCollider {
+    fn isometry_contact_manifolds(
+        &self,
+        other: &Self,
+        mut iso1: Isometry3d,
+        mut iso2: Isometry3d,
+        pred_dist: f32,
+        contacts: &mut Vec<ContactManifold>,
+        context: PairContext<SdfContext<'static, 'static>>,
+    ) {
+        if context.query_only_pair(context.entity1, context.entity2) {
+            recycle_manifolds(contacts);
+            return;
+        }
+        let narrow_phase = context.narrow_phase();
+        let pred_dist = narrow_phase
+            .prediction_distance
+            .for_pair(self, other, pred_dist);
+
+        // Swept spheres and segments collide as capsules, with anchors moved back to the body
+        if let Some((capsule, iso)) = self.line_capsule(iso1) {
+            let offset = Vec3::from(iso.translation - iso1.translation);
+            self.with_shape(capsule)
+                .isometry_contact_manifolds(other, iso, iso2, pred_dist, contacts, context);
+            for point in contacts.iter_mut().flat_map(|m| m.points.iter_mut()) {
+                point.anchor1 += offset;
+            }
+            return;
+        }
+        if let Some((capsule, iso)) = other.line_capsule(iso2) {
+            let offset = Vec3::from(iso.translation - iso2.translation);
+            self.isometry_contact_manifolds(
+                &other.with_shape(capsule),
+                iso1,
+                iso,
+                pred_dist,
+                contacts,
+                context,
+            );
+            for point in contacts.iter_mut().flat_map(|m| m.points.iter_mut()) {
+                point.anchor2 += offset;
+            }
+            return;
+        }
+
+        let placeholder1 = context.placeholder(self).map(|s| self.with_shape(s));
+        let placeholder2 = context.placeholder(other).map(|s| other.with_shape(s));
+        if placeholder1.is_some() || placeholder2.is_some() {
+            return placeholder1
+                .as_ref()
+                .unwrap_or(self)
+                .isometry_contact_manifolds(
+                    placeholder2.as_ref().unwrap_or(other),
+                    iso1,
+                    iso2,
+                    pred_dist,
+                    contacts,
+                    context,
+                );
+        }
+
+        if !contacts.is_empty()
+            && !self.reloaded
+            && !other.reloaded
+            && context.skip_distant_pair(
+                context.entity1,
+                context.entity2,
+                iso1.translation,
+                iso2.translation,
+            )
+        {
+            return;
+        }
+
+        recycle_manifolds(contacts);
+        let (entity1, entity2) = (context.entity1, context.entity2);
+        let filter = |contact: &mut Contact| {
+            narrow_phase
+                .contact_filters
+                .apply(entity1, entity2, contact)
+        };
+        let mut manifolds = Manifolds::new(&mut *contacts);
+        if !narrow_phase.contact_filters.is_empty() {
+            manifolds = manifolds.with_filter(&filter);
+        }
+
+        let current = (iso1, iso2);
+        let prediction1 = self.kinematic_prediction(context.entity1, iso1, pred_dist, &context);
+        let prediction2 = other.kinematic_prediction(context.entity2, iso2, pred_dist, &context);
+        if let Some((motion, dt)) = prediction1 {
+            iso1 = motion.advance(iso1, dt);
+        }
+        if let Some((motion, dt)) = prediction2 {
+            iso2 = motion.advance(iso2, dt);
+        }
+        if context.separated_by_patches(self, iso1, other, current.1.translation, pred_dist)
+            || context.separated_by_patches(other, iso2, self, current.0.translation, pred_dist)
+        {
+            return;
+        }
 
         let scale1 = self.scale;
         let scale2 = other.scale;
+        let mut evaluations = SdfEvaluations::default();
         match (&self.collider, &other.collider) {
             (SdfColliderKind::Sphere(mut s1), SdfColliderKind::Sphere(mut s2)) => {
                 s1.radius *= scale1;
@@ -147,13 +347,18 @@ impl AnyCollider for SdfCollider {
                 );
             }
 
-            (&SdfColliderKind::Sphere(mut s), SdfColliderKind::Arbitrary(handle)) => {
-                let Some((_, sdf)) = context.get(handle.id()) else {
+            (
+                &SdfColliderKind::Sphere(mut s),
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+            ) => {
+                let Some(sdf) = other.sdf_source(&context) else {
                     return;
                 };
 
                 s.radius *= scale1;
 
+                let sdf = collider_sdf(&sdf, other, context.entity2, &context);
+
                 s.get_collisions(
                     iso1,
                     &sdf,
@@ -164,14 +369,21 @@ impl AnyCollider for SdfCollider {
                     ManifoldAdder::normal(manifolds),
                     pred_dist,
                 );
+
+                evaluations = sdf.evaluations();
             }
-            (SdfColliderKind::Arbitrary(handle), &SdfColliderKind::Sphere(mut s)) => {
-                let Some((_, sdf)) = context.get(handle.id()) else {
+            (
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+                &SdfColliderKind::Sphere(mut s),
+            ) => {
+                let Some(sdf) = self.sdf_source(&context) else {
                     return;
                 };
 
                 s.radius *= scale2;
 
+                let sdf = collider_sdf(&sdf, self, context.entity1, &context);
+
                 s.get_collisions(
                     iso2,
                     &sdf,
@@ -182,17 +394,25 @@ impl AnyCollider for SdfCollider {
                     ManifoldAdder::flipped(manifolds),
                     pred_dist,
                 );
+
+                evaluations = sdf.evaluations();
             }
 
-            (&SdfColliderKind::Capsule(mut c), SdfColliderKind::Arbitrary(handle)) => {
-                let Some((_, sdf)) = context.get(handle.id()) else {
+            (
+                &SdfColliderKind::Capsule(mut c),
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+            ) => {
+                let Some(sdf) = other.sdf_source(&context) else {
                     return;
                 };
 
                 c.radius *= scale1;
                 c.half_length *= scale1;
 
-                c.get_collisions(
+                let sdf = collider_sdf(&sdf, other, context.entity2, &context);
+
+                persistent_capsule_sdf_collisions(
+                    &c,
                     iso1,
                     &sdf,
                     ScaledIsometry3d {
@@ -201,16 +421,95 @@ impl AnyCollider for SdfCollider {
                     },
                     ManifoldAdder::normal(manifolds),
                     pred_dist,
+                    (context.entity1, context.entity2),
+                    self.reloaded || other.reloaded,
+                    &context,
                 );
+
+                evaluations = sdf.evaluations();
             }
-            (SdfColliderKind::Arbitrary(handle), &SdfColliderKind::Capsule(mut c)) => {
-                let Some((_, sdf)) = context.get(handle.id()) else {
+            (
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+                &SdfColliderKind::Capsule(mut c),
+            ) => {
+                let Some(sdf) = self.sdf_source(&context) else {
                     return;
                 };
 
                 c.radius *= scale2;
                 c.half_length *= scale2;
 
+                let sdf = collider_sdf(&sdf, self, context.entity1, &context);
+
+                persistent_capsule_sdf_collisions(
+                    &c,
+                    iso2,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                    (context.entity1, context.entity2),
+                    self.reloaded || other.reloaded,
+                    &context,
+                );
+
+                evaluations = sdf.evaluations();
+            }
+
+            (&SdfColliderKind::Sphere(mut s), SdfColliderKind::Ellipsoid(e)) => {
+                s.radius *= scale1;
+                let sdf = CountingSdf::new(*e);
+                s.get_collisions(
+                    iso1,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::Ellipsoid(e), &SdfColliderKind::Sphere(mut s)) => {
+                s.radius *= scale2;
+                let sdf = CountingSdf::new(*e);
+                s.get_collisions(
+                    iso2,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+
+            (&SdfColliderKind::Capsule(mut c), SdfColliderKind::Ellipsoid(e)) => {
+                c.radius *= scale1;
+                c.half_length *= scale1;
+                let sdf = CountingSdf::new(*e);
+                c.get_collisions(
+                    iso1,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::Ellipsoid(e), &SdfColliderKind::Capsule(mut c)) => {
+                c.radius *= scale2;
+                c.half_length *= scale2;
+                let sdf = CountingSdf::new(*e);
                 c.get_collisions(
                     iso2,
                     &sdf,
@@ -221,21 +520,789 @@ impl AnyCollider for SdfCollider {
                     ManifoldAdder::flipped(manifolds),
                     pred_dist,
                 );
+                evaluations = sdf.evaluations();
             }
 
-            (t1, t2) => warn!(
-                "Unsupported collision: {:?} vs {:?} ({} vs {})",
-                t1, t2, context.entity1, context.entity2
-            ),
-        }
-    }
-}
+            (SdfColliderKind::Ellipsoid(e1), SdfColliderKind::Ellipsoid(e2)) => {
+                let sdf = CountingSdf::new(*e2);
+                e1.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (
+                SdfColliderKind::Ellipsoid(e),
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+            ) => {
+                let Some(sdf) = other.sdf_source(&context) else {
+                    return;
+                };
 
-impl ScalableCollider for SdfCollider {
-    fn scale(&self) -> Vec3 {
-        Vec3::splat(self.scale)
-    }
-    fn set_scale(&mut self, scale: Vec3, _: u32) {
-        self.scale = scale.min_element();
+                let sdf = collider_sdf(&sdf, other, context.entity2, &context);
+
+                e.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+
+                evaluations = sdf.evaluations();
+            }
+            (
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+                SdfColliderKind::Ellipsoid(e),
+            ) => {
+                let Some(sdf) = self.sdf_source(&context) else {
+                    return;
+                };
+
+                let sdf = collider_sdf(&sdf, self, context.entity1, &context);
+
+                e.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+
+                evaluations = sdf.evaluations();
+            }
+
+            (
+                SdfColliderKind::SphereCluster(cluster),
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+            ) => {
+                let Some(sdf) = other.sdf_source(&context) else {
+                    return;
+                };
+
+                let sdf = collider_sdf(&sdf, other, context.entity2, &context);
+
+                cluster.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+
+                evaluations = sdf.evaluations();
+            }
+            (
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+                SdfColliderKind::SphereCluster(cluster),
+            ) => {
+                let Some(sdf) = self.sdf_source(&context) else {
+                    return;
+                };
+
+                let sdf = collider_sdf(&sdf, self, context.entity1, &context);
+
+                cluster.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+
+                evaluations = sdf.evaluations();
+            }
+
+            (SdfColliderKind::SphereCluster(cluster), SdfColliderKind::Ellipsoid(e)) => {
+                let sdf = CountingSdf::new(*e);
+                cluster.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::Ellipsoid(e), SdfColliderKind::SphereCluster(cluster)) => {
+                let sdf = CountingSdf::new(*e);
+                cluster.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::SphereCluster(c1), SdfColliderKind::SphereCluster(c2)) => {
+                let sdf = CountingSdf::new(c2);
+                c1.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+
+            // Spheres and capsules treat the cluster as an SDF, giving a contact with the closest
+            // sphere in the cluster
+            (&SdfColliderKind::Sphere(mut s), SdfColliderKind::SphereCluster(cluster)) => {
+                s.radius *= scale1;
+                let sdf = CountingSdf::new(cluster);
+                s.get_collisions(
+                    iso1,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::SphereCluster(cluster), &SdfColliderKind::Sphere(mut s)) => {
+                s.radius *= scale2;
+                let sdf = CountingSdf::new(cluster);
+                s.get_collisions(
+                    iso2,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (&SdfColliderKind::Capsule(mut c), SdfColliderKind::SphereCluster(cluster)) => {
+                c.radius *= scale1;
+                c.half_length *= scale1;
+                let sdf = CountingSdf::new(cluster);
+                c.get_collisions(
+                    iso1,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::SphereCluster(cluster), &SdfColliderKind::Capsule(mut c)) => {
+                c.radius *= scale2;
+                c.half_length *= scale2;
+                let sdf = CountingSdf::new(cluster);
+                c.get_collisions(
+                    iso2,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+
+            // Polylines collide segment by segment against SDFs, smaller shapes and other polylines
+            // treat them as an SDF
+            (
+                SdfColliderKind::Polyline(polyline),
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+            ) => {
+                let Some(sdf) = other.sdf_source(&context) else {
+                    return;
+                };
+
+                let sdf = collider_sdf(&sdf, other, context.entity2, &context);
+                polyline.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (
+                SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_),
+                SdfColliderKind::Polyline(polyline),
+            ) => {
+                let Some(sdf) = self.sdf_source(&context) else {
+                    return;
+                };
+
+                let sdf = collider_sdf(&sdf, self, context.entity1, &context);
+                polyline.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::Polyline(polyline), SdfColliderKind::Ellipsoid(e)) => {
+                let sdf = CountingSdf::new(*e);
+                polyline.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::Ellipsoid(e), SdfColliderKind::Polyline(polyline)) => {
+                let sdf = CountingSdf::new(*e);
+                polyline.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::Polyline(p1), SdfColliderKind::Polyline(p2)) => {
+                let sdf = CountingSdf::new(p2);
+                p1.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::SphereCluster(cluster), SdfColliderKind::Polyline(polyline)) => {
+                let sdf = CountingSdf::new(polyline);
+                cluster.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::Polyline(polyline), SdfColliderKind::SphereCluster(cluster)) => {
+                let sdf = CountingSdf::new(polyline);
+                cluster.get_collisions(
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (&SdfColliderKind::Sphere(mut s), SdfColliderKind::Polyline(polyline)) => {
+                s.radius *= scale1;
+                let sdf = CountingSdf::new(polyline);
+                s.get_collisions(
+                    iso1,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::Polyline(polyline), &SdfColliderKind::Sphere(mut s)) => {
+                s.radius *= scale2;
+                let sdf = CountingSdf::new(polyline);
+                s.get_collisions(
+                    iso2,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (&SdfColliderKind::Capsule(mut c), SdfColliderKind::Polyline(polyline)) => {
+                c.radius *= scale1;
+                c.half_length *= scale1;
+                let sdf = CountingSdf::new(polyline);
+                c.get_collisions(
+                    iso1,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso2,
+                        scale: scale2,
+                    },
+                    ManifoldAdder::normal(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+            (SdfColliderKind::Polyline(polyline), &SdfColliderKind::Capsule(mut c)) => {
+                c.radius *= scale2;
+                c.half_length *= scale2;
+                let sdf = CountingSdf::new(polyline);
+                c.get_collisions(
+                    iso2,
+                    &sdf,
+                    ScaledIsometry3d {
+                        iso: iso1,
+                        scale: scale1,
+                    },
+                    ManifoldAdder::flipped(manifolds),
+                    pred_dist,
+                );
+                evaluations = sdf.evaluations();
+            }
+
+            (t1, t2) => match *narrow_phase.unsupported_pairs {
+                UnsupportedPairs::Ignore => {}
+                UnsupportedPairs::Warn => warn_once!(
+                    "Unsupported collision: {:?} vs {:?} ({} vs {})",
+                    t1,
+                    t2,
+                    context.entity1,
+                    context.entity2
+                ),
+                UnsupportedPairs::BoundingSpheres => {
+                    let (Some(radius1), Some(radius2)) = (
+                        self.bounding_radius(&context),
+                        other.bounding_radius(&context),
+                    ) else {
+                        return;
+                    };
+                    let (current1, current2) = current;
+                    // Keep the larger collider exact, it's usually the level geometry
+                    if radius1 <= radius2 {
+                        self.with_shape(Sphere::new(radius1 / scale1))
+                            .isometry_contact_manifolds(
+                                other, current1, current2, pred_dist, contacts, context,
+                            );
+                    } else {
+                        self.isometry_contact_manifolds(
+                            &other.with_shape(Sphere::new(radius2 / scale2)),
+                            current1,
+                            current2,
+                            pred_dist,
+                            contacts,
+                            context,
+                        );
+                    }
+                    return;
+                }
+            },
+        }
+
+        if prediction1.is_some() || prediction2.is_some() {
+            let rotation_back1 = current.0.rotation * iso1.rotation.inverse();
+            let rotation_back2 = current.1.rotation * iso2.rotation.inverse();
+            for manifold in contacts.iter_mut() {
+                unpredict_manifold(manifold, prediction1, rotation_back1, 1.);
+                unpredict_manifold(manifold, prediction2, rotation_back2, -1.);
+            }
+        }
+
+        let one_way1 = context.one_way_surfaces.get(context.entity1).ok();
+        let one_way2 = context.one_way_surfaces.get(context.entity2).ok();
+        if one_way1.is_some() || one_way2.is_some() {
+            one_way::filter_one_way_contacts(
+                contacts,
+                one_way1.map(|surface| (surface, iso1.rotation)),
+                one_way2.map(|surface| (surface, iso2.rotation)),
+            );
+        }
+
+        let slope1 = context.slope_frictions.get(context.entity1).ok();
+        let slope2 = context.slope_frictions.get(context.entity2).ok();
+        if slope1.is_some() || slope2.is_some() {
+            slope::apply_slope_friction(
+                contacts,
+                context.pair_friction(context.entity1, context.entity2),
+                slope1.map(|slope| (slope, iso1.rotation)),
+                slope2.map(|slope| (slope, iso2.rotation)),
+            );
+        }
+
+        let compliance1 = context.compliances.get(context.entity1).ok();
+        let compliance2 = context.compliances.get(context.entity2).ok();
+        if compliance1.is_some() || compliance2.is_some() {
+            compliance::offset_compliant_contacts(contacts, compliance1, compliance2);
+        }
+
+        let motion1 = narrow_phase.surface_motion.get(context.entity1);
+        let motion2 = narrow_phase.surface_motion.get(context.entity2);
+        if motion1.is_some() || motion2.is_some() {
+            for manifold in contacts.iter_mut() {
+                let Some(point) = manifold.points.first() else {
+                    continue;
+                };
+                // Relative velocity of the first surface over the second, along the surface
+                let velocity = motion1.map_or(Vec3::ZERO, |m| m.velocity_at(point.point))
+                    - motion2.map_or(Vec3::ZERO, |m| m.velocity_at(point.point));
+                manifold.tangent_velocity =
+                    velocity - manifold.normal * velocity.dot(manifold.normal);
+            }
+        }
+
+        narrow_phase.diagnostics.record_pair(
+            &self.collider,
+            &other.collider,
+            evaluations,
+            contacts.len(),
+        );
+
+        if let Some(max_angle) = narrow_phase.stabilization.normal_smoothing {
+            let previous = if self.reloaded || other.reloaded {
+                PairNormals::default()
+            } else {
+                narrow_phase.contact_cache.normals(entity1, entity2)
+            };
+            let mut normals = PairNormals::default();
+            for manifold in contacts.iter_mut() {
+                manifold.normal = previous.smooth(manifold.normal, max_angle);
+                normals.push(manifold.normal);
+            }
+            narrow_phase
+                .contact_cache
+                .insert_normals(entity1, entity2, normals);
+        }
+
+        if let Some(quantum) = narrow_phase.stabilization.quantum {
+            let world_offset = context.world_offset();
+            for manifold in contacts.iter_mut() {
+                quantize_manifold(manifold, quantum, world_offset);
+            }
+        }
+    }
+
+    /// How far ahead to look for contacts with this collider if it's a fast kinematic SDF asset,
+    /// so approaching bodies don't hit the geometry where it was at the start of the step.
+    fn kinematic_prediction(
+        &self,
+        entity: Entity,
+        iso: Isometry3d,
+        pred_dist: f32,
+        context: &SdfContext,
+    ) -> Option<(SurfaceMotion, f32)> {
+        if !self.collider.is_sdf() || pred_dist <= 0. {
+            return None;
+        }
+        let motion = *context.narrow_phase().surface_motion.kinematic(entity)?;
+        let speed = motion.max_speed(iso.translation.into(), self.bounding_radius(context)?);
+        // Looking further ahead than the speculative margin would find contacts avian ignores
+        (speed > 0.).then(|| (motion, pred_dist / speed))
+    }
+}
+
+/// Moves the contacts generated against a predicted pose back to the current pose of the
+/// collider, keeping the normals of the predicted geometry.
+///
+/// `side` is 1 for the first collider of the pair and -1 for the second.
+fn unpredict_manifold(
+    manifold: &mut ContactManifold,
+    prediction: Option<(SurfaceMotion, f32)>,
+    rotation_back: Quat,
+    side: f32,
+) {
+    let Some((motion, dt)) = prediction else {
+        return;
+    };
+    for point in manifold.points.iter_mut() {
+        // The surface moved this far towards the other collider in the predicted time
+        let approach = motion.velocity_at(point.point).dot(manifold.normal) * side * dt;
+        point.penetration -= approach;
+        if side > 0. {
+            point.anchor1 = rotation_back * point.anchor1;
+        } else {
+            point.anchor2 = rotation_back * point.anchor2;
+        }
+    }
+}
+
+// Snapping contacts to a grid keeps them identical between steps while bodies are at rest,
+// instead of drifting by tiny amounts that keep the solver busy and bodies awake. Points are
+// snapped in world coordinates, so the grid doesn't move when the origin is shifted
+fn quantize_manifold(manifold: &mut ContactManifold, quantum: f32, world_offset: Vec3) {
+    let snap = |v: Vec3| (v / quantum).round() * quantum;
+    manifold.normal = snap_direction(manifold.normal, quantum);
+    for point in manifold.points.iter_mut() {
+        point.point = snap(point.point + world_offset) - world_offset;
+        point.anchor1 = snap(point.anchor1);
+        point.anchor2 = snap(point.anchor2);
+        // Rounding shallow contacts down to zero would drop the push that holds bodies up
+        point.penetration = (point.penetration / quantum).ceil() * quantum;
+    }
+}
+
+/// Snaps a direction to rings of latitude `step` radians apart around Y, each split into as many
+/// directions as fit `step` apart, so directions are never snapped further than about `step`.
+fn snap_direction(dir: Vec3, step: f32) -> Vec3 {
+    let polar = (ops::acos(dir.y.clamp(-1., 1.)) / step).round() * step;
+    let (sin_polar, cos_polar) = ops::sin_cos(polar);
+    let azimuth_step = TAU / (TAU * sin_polar.abs() / step).round().max(1.);
+    let azimuth = (ops::atan2(dir.z, dir.x) / azimuth_step).round() * azimuth_step;
+    let (sin_azimuth, cos_azimuth) = ops::sin_cos(azimuth);
+    Vec3::new(sin_polar * cos_azimuth, cos_polar, sin_polar * sin_azimuth)
+}
+
+impl ScalableCollider for SdfCollider {
+    fn scale(&self) -> Vec3 {
+        Vec3::splat(self.scale)
+    }
+    fn set_scale(&mut self, scale: Vec3, _: u32) {
+        // Child colliders inherit the global scale of their hierarchy, which can be mirrored
+        self.scale = scale.abs().min_element();
+    }
+}
+
+type NarrowPhaseSdf<'a, S> = CountingSdf<
+    WithMarchQuality<Shelled<Parameterized<SmoothedNormals<'a, S>, ExecutableSdf3d<'a>>>>,
+>;
+
+/// Capsule-vs-SDF contacts, warm started from the previous step of the pair if
+/// [`ContactStabilization::persistence`](crate::ContactStabilization::persistence) is enabled.
+#[allow(clippy::too_many_arguments)]
+fn persistent_capsule_sdf_collisions<T: From<Contact>>(
+    capsule: &Capsule3d,
+    capsule_iso: Isometry3d,
+    sdf: &impl LocalSdf,
+    sdf_iso: ScaledIsometry3d,
+    adder: ManifoldAdder<T>,
+    pred_dist: f32,
+    (entity1, entity2): (Entity, Entity),
+    reloaded: bool,
+    context: &SdfContext,
+) {
+    let narrow_phase = context.narrow_phase();
+    let Some(tolerance) = narrow_phase.stabilization.persistence else {
+        capsule.get_collisions(capsule_iso, sdf, sdf_iso, adder, pred_dist);
+        return;
+    };
+    let cache = &narrow_phase.contact_cache;
+    let mut warm = if reloaded {
+        SegmentWarmStart::default()
+    } else {
+        cache.get(entity1, entity2)
+    };
+    capsule_sdf_collisions(
+        capsule,
+        capsule_iso,
+        sdf,
+        sdf_iso,
+        adder,
+        pred_dist,
+        Some((&mut warm, tolerance)),
+    );
+    cache.insert(entity1, entity2, warm);
+}
+
+/// Wraps the SDF asset of a collider the way the narrow phase evaluates it.
+fn collider_sdf<'a, S: LocalSdf>(
+    sdf: &'a S,
+    collider: &SdfCollider,
+    entity: Entity,
+    context: &'a SdfContext,
+) -> NarrowPhaseSdf<'a, S> {
+    let quality = context.march_quality(entity);
+    let bounds = quality
+        .far_field
+        .and_then(|_| collider.far_field_bounds(context));
+    CountingSdf::new(
+        WithMarchQuality::new(
+            collider.shelled(collider.parameterized(
+                SmoothedNormals::new(sdf, collider.normal_smoothing / collider.scale),
+                context,
+            )),
+            quality,
+        )
+        .with_bounds(bounds),
+    )
+}
+
+impl SdfCollider {
+    pub(crate) fn world_aabb(&self, iso: Isometry3d, context: &SdfContext) -> ColliderAabb {
+        let aabb = match &self.collider {
+            &SdfColliderKind::Sphere(mut s) => {
+                s.radius *= self.scale;
+                s.aabb_3d(iso)
+            }
+            &SdfColliderKind::Capsule(mut c) => {
+                c.radius *= self.scale;
+                c.half_length *= self.scale;
+                c.aabb_3d(iso)
+            }
+            &SdfColliderKind::Ellipsoid(mut e) => {
+                e.half_size *= self.scale;
+                e.aabb_3d(iso)
+            }
+            SdfColliderKind::SphereCluster(cluster) => {
+                let mut aabb = cluster.aabb_3d(Isometry3d::from_rotation(iso.rotation));
+                aabb.min *= self.scale;
+                aabb.max *= self.scale;
+                aabb.translate_by(iso.translation);
+                aabb
+            }
+            SdfColliderKind::Polyline(polyline) => {
+                let mut aabb = polyline.aabb_3d(Isometry3d::from_rotation(iso.rotation));
+                aabb.min *= self.scale;
+                aabb.max *= self.scale;
+                aabb.translate_by(iso.translation);
+                aabb
+            }
+            SdfColliderKind::SweptSphere(_) | SdfColliderKind::Segment(_) => {
+                let (mut capsule, iso) = self.line_capsule(iso).unwrap();
+                capsule.radius *= self.scale;
+                capsule.half_length *= self.scale;
+                capsule.aabb_3d(iso)
+            }
+            SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_) => {
+                let Some(sdf) = self.sdf_source(context) else {
+                    let Some(mut placeholder) = context.placeholder(self) else {
+                        return ColliderAabb::INVALID;
+                    };
+                    placeholder.radius *= self.scale;
+                    let aabb = placeholder.aabb_3d(iso);
+                    return ColliderAabb {
+                        min: aabb.min.into(),
+                        max: aabb.max.into(),
+                    };
+                };
+
+                let fake_iso = Isometry3d::new(Vec3A::ZERO, iso.rotation);
+                let rotated_aabb = |sdf: SdfSource| {
+                    #[cfg(not(feature = "tight-aabb"))]
+                    let aabb = sdf.aabb(fake_iso);
+                    #[cfg(feature = "tight-aabb")]
+                    let aabb = tight_aabb(&sdf, sdf.aabb(Isometry3d::IDENTITY), fake_iso.rotation);
+                    aabb
+                };
+
+                let mut aabb = rotated_aabb(sdf);
+                if let Some(target) = self.blend_target(context) {
+                    aabb = aabb.merge(&rotated_aabb(SdfSource::Asset(target)));
+                }
+                if let Some(region) = self.region {
+                    let region = region.transformed_by(Vec3A::ZERO, iso.rotation);
+                    aabb.min = aabb.min.max(region.min);
+                    aabb.max = aabb.max.min(region.max).max(aabb.min);
+                }
+                aabb.min -= self.surface_margin();
+                aabb.max += self.surface_margin();
+                aabb.min *= self.scale;
+                aabb.max *= self.scale;
+                aabb.translate_by(iso.translation);
+                aabb
+            }
+        };
+        ColliderAabb {
+            min: aabb.min.into(),
+            max: aabb.max.into(),
+        }
     }
 }
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use bevy::math::{
+    bounding::{Aabb3d, BoundingVolume},
+    Isometry3d, Vec3,
+};
+use bevy_prototype_sdf::ExecutableSdf3d;
+
+use crate::LocalSdf;
+
+/// An SDF evaluated by user code instead of a `bevy_prototype_sdf` asset, like procedural noise
+/// terrain, a voxel grid or a neural SDF.
+///
+/// Colliders made with [`SdfCollider::custom`](crate::SdfCollider::custom) collide and are queried
+/// like SDF asset colliders, including their shell, inversion, region, [`SdfParams`] inflation
+/// and march quality. Features that read the asset itself, like surface tags, deformation and
+/// baked patches, don't apply to them.
+///
+/// [`SdfParams`]: crate::SdfParams
+pub trait SdfBackend: Send + Sync + 'static {
+    /// Signed distance to the surface, negative inside it.
+    fn distance(&self, local_point: Vec3) -> f32;
+
+    /// Direction the distance increases fastest in, estimated from 4 distances unless overridden.
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        const EPSILON: f32 = 0.001;
+        const CORNERS: [Vec3; 4] = [
+            Vec3::new(1., -1., -1.),
+            Vec3::new(-1., -1., 1.),
+            Vec3::new(-1., 1., -1.),
+            Vec3::new(1., 1., 1.),
+        ];
+        CORNERS
+            .iter()
+            .map(|&corner| corner * self.distance(local_point + corner * EPSILON))
+            .sum::<Vec3>()
+            .normalize_or(Vec3::Y)
+    }
+
+    /// Local bounds of the surface, nothing outside them is solid.
+    fn aabb(&self) -> Aabb3d;
+}
+
+/// The [`SdfBackend`] of a collider, shared between clones of it.
+#[derive(Clone)]
+pub struct CustomSdf(pub Arc<dyn SdfBackend>);
+
+impl CustomSdf {
+    pub fn new(backend: impl SdfBackend) -> Self {
+        Self(Arc::new(backend))
+    }
+}
+
+impl std::fmt::Debug for CustomSdf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CustomSdf").field(&self.0.aabb()).finish()
+    }
+}
+
+// Backends can't be reflected, colliders restored through reflection have an empty one
+impl Default for CustomSdf {
+    fn default() -> Self {
+        Self::new(EmptySdf)
+    }
+}
+
+struct EmptySdf;
+
+impl SdfBackend for EmptySdf {
+    fn distance(&self, _: Vec3) -> f32 {
+        f32::INFINITY
+    }
+
+    fn gradient(&self, _: Vec3) -> Vec3 {
+        Vec3::Y
+    }
+
+    fn aabb(&self) -> Aabb3d {
+        Aabb3d::new(Vec3::ZERO, Vec3::ZERO)
+    }
+}
+
+/// Where the SDF of an asset or custom collider comes from, before its settings are applied.
+pub(crate) enum SdfSource<'a> {
+    Asset(ExecutableSdf3d<'a>),
+    Custom(&'a dyn SdfBackend),
+}
+
+impl SdfSource<'_> {
+    /// Bounds of the surface with its local space placed at `iso`.
+    pub fn aabb(&self, iso: Isometry3d) -> Aabb3d {
+        match self {
+            Self::Asset(sdf) => sdf.aabb(iso),
+            Self::Custom(backend) => backend.aabb().transformed_by(iso.translation, iso.rotation),
+        }
+    }
+}
+
+impl LocalSdf for SdfSource<'_> {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        match self {
+            Self::Asset(sdf) => sdf.distance(local_point),
+            Self::Custom(backend) => backend.distance(local_point),
+        }
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        match self {
+            Self::Asset(sdf) => sdf.gradient(local_point),
+            Self::Custom(backend) => backend.gradient(local_point),
+        }
+    }
+}
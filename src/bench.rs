@@ -0,0 +1,10 @@
+//! Entry points into internals for the benchmarks in `benches/`, not a public API.
+
+#[cfg(feature = "tight-aabb")]
+pub fn tight_aabb(
+    sdf: &impl crate::LocalSdf,
+    local_aabb: bevy::math::bounding::Aabb3d,
+    rotation: bevy::math::Quat,
+) -> bevy::math::bounding::Aabb3d {
+    crate::primitives::tight_aabb(sdf, local_aabb, rotation)
+}
@@ -0,0 +1,110 @@
+use std::f32::consts::PI;
+
+use avian3d::prelude::*;
+use bevy::{
+    ecs::{intern::Interned, schedule::ScheduleLabel},
+    prelude::*,
+};
+
+use crate::{primitives::LocalSdf, SdfCollider, SdfContext};
+
+/// Applies buoyancy and drag from [`FluidVolume`]s to dynamic bodies with an [`SdfCollider`].
+pub struct BuoyancyPlugin {
+    schedule: Interned<dyn ScheduleLabel>,
+}
+
+impl BuoyancyPlugin {
+    pub fn new(schedule: impl ScheduleLabel) -> Self {
+        Self {
+            schedule: schedule.intern(),
+        }
+    }
+}
+
+impl Default for BuoyancyPlugin {
+    fn default() -> Self {
+        Self::new(FixedPostUpdate)
+    }
+}
+
+impl Plugin for BuoyancyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            self.schedule,
+            apply_buoyancy.before(PhysicsSystems::StepSimulation),
+        );
+    }
+}
+
+/// Marks an [`SdfCollider`] as a body of fluid, where the inside of the SDF is submerged.
+#[derive(Component, Debug, Clone, Copy)]
+#[require(Sensor)]
+pub struct FluidVolume {
+    pub density: f32,
+    /// Fraction of linear velocity lost per second when fully submerged
+    pub linear_drag: f32,
+    /// Fraction of angular velocity lost per second when fully submerged
+    pub angular_drag: f32,
+}
+
+impl Default for FluidVolume {
+    fn default() -> Self {
+        Self {
+            density: 1.,
+            linear_drag: 0.5,
+            angular_drag: 0.5,
+        }
+    }
+}
+
+fn apply_buoyancy(
+    time: Res<Time>,
+    gravity: Res<Gravity>,
+    fluids: Query<(Entity, &FluidVolume, &Position, &Rotation, &SdfCollider)>,
+    mut bodies: Query<
+        (
+            Entity,
+            &RigidBody,
+            &Position,
+            &SdfCollider,
+            &ComputedMass,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+        ),
+        Without<FluidVolume>,
+    >,
+    context: SdfContext,
+) {
+    let dt = time.delta_secs();
+    for (fluid_entity, fluid, fluid_pos, fluid_rot, fluid_collider) in fluids.iter() {
+        let Some(fluid_sdf) = fluid_collider.local_sdf(&context) else {
+            continue;
+        };
+        let inv_rot = fluid_rot.0.inverse();
+
+        for (entity, rb, pos, collider, mass, mut lin_vel, mut ang_vel) in bodies.iter_mut() {
+            if entity == fluid_entity || !rb.is_dynamic() || mass.value() <= 0. {
+                continue;
+            }
+            let Some(radius) = collider.bounding_radius(&context) else {
+                continue;
+            };
+
+            // Treat the body as a sphere and the fluid surface as locally flat
+            let local_pos = inv_rot * (pos.0 - fluid_pos.0) / fluid_collider.scale;
+            let depth = -fluid_sdf.distance(local_pos) * fluid_collider.scale;
+            let submerged_height = (depth + radius).clamp(0., radius * 2.);
+            if submerged_height <= 0. {
+                continue;
+            }
+
+            let submerged_volume =
+                PI * submerged_height * submerged_height * (3. * radius - submerged_height) / 3.;
+            let fraction = submerged_volume / (4. / 3. * PI * radius * radius * radius);
+
+            lin_vel.0 -= gravity.0 * fluid.density * submerged_volume / mass.value() * dt;
+            lin_vel.0 *= (1. - fluid.linear_drag * fraction * dt).max(0.);
+            ang_vel.0 *= (1. - fluid.angular_drag * fraction * dt).max(0.);
+        }
+    }
+}
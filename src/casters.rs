@@ -0,0 +1,171 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::SdfSpatialQuery;
+
+/// Casts a ray from the entity's transform against SDF colliders every physics step,
+/// storing the results in [`SdfRayHits`].
+#[derive(Component, Debug, Clone)]
+#[require(SdfRayHits)]
+pub struct SdfRayCaster {
+    pub enabled: bool,
+    pub origin: Vec3,
+    pub direction: Dir3,
+    pub max_distance: f32,
+    pub max_hits: u32,
+    pub solid: bool,
+    pub ignore_self: bool,
+    pub query_filter: SpatialQueryFilter,
+}
+
+impl SdfRayCaster {
+    pub fn new(origin: Vec3, direction: Dir3) -> Self {
+        Self {
+            enabled: true,
+            origin,
+            direction,
+            max_distance: f32::MAX,
+            max_hits: u32::MAX,
+            solid: true,
+            ignore_self: true,
+            query_filter: SpatialQueryFilter::DEFAULT,
+        }
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_max_hits(mut self, max_hits: u32) -> Self {
+        self.max_hits = max_hits;
+        self
+    }
+
+    pub fn with_solidness(mut self, solid: bool) -> Self {
+        self.solid = solid;
+        self
+    }
+
+    pub fn with_ignore_self(mut self, ignore: bool) -> Self {
+        self.ignore_self = ignore;
+        self
+    }
+
+    pub fn with_query_filter(mut self, query_filter: SpatialQueryFilter) -> Self {
+        self.query_filter = query_filter;
+        self
+    }
+}
+
+#[derive(Component, Debug, Default, Clone)]
+pub struct SdfRayHits(pub Vec<RayHitData>);
+
+/// Casts a sphere from the entity's transform against SDF colliders every physics step,
+/// storing the results in [`SdfShapeHits`].
+#[derive(Component, Debug, Clone)]
+#[require(SdfShapeHits)]
+pub struct SdfShapeCaster {
+    pub enabled: bool,
+    pub shape: Sphere,
+    pub origin: Vec3,
+    pub direction: Dir3,
+    pub max_hits: u32,
+    pub ignore_self: bool,
+    pub config: ShapeCastConfig,
+    pub query_filter: SpatialQueryFilter,
+}
+
+impl SdfShapeCaster {
+    pub fn new(shape: Sphere, origin: Vec3, direction: Dir3) -> Self {
+        Self {
+            enabled: true,
+            shape,
+            origin,
+            direction,
+            max_hits: 1,
+            ignore_self: true,
+            config: ShapeCastConfig::DEFAULT,
+            query_filter: SpatialQueryFilter::DEFAULT,
+        }
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.config.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_max_hits(mut self, max_hits: u32) -> Self {
+        self.max_hits = max_hits;
+        self
+    }
+
+    pub fn with_ignore_self(mut self, ignore: bool) -> Self {
+        self.ignore_self = ignore;
+        self
+    }
+
+    pub fn with_query_filter(mut self, query_filter: SpatialQueryFilter) -> Self {
+        self.query_filter = query_filter;
+        self
+    }
+}
+
+#[derive(Component, Debug, Default, Clone)]
+pub struct SdfShapeHits(pub Vec<ShapeHitData>);
+
+fn caster_filter(
+    entity: Entity,
+    filter: &SpatialQueryFilter,
+    ignore_self: bool,
+) -> SpatialQueryFilter {
+    let mut filter = filter.clone();
+    if ignore_self {
+        filter.excluded_entities.insert(entity);
+    }
+    filter
+}
+
+pub(crate) fn update_ray_casters(
+    mut casters: Query<(Entity, &SdfRayCaster, &GlobalTransform, &mut SdfRayHits)>,
+    query: SdfSpatialQuery,
+) {
+    for (entity, caster, transform, mut hits) in casters.iter_mut() {
+        hits.0.clear();
+        if !caster.enabled {
+            continue;
+        }
+
+        let filter = caster_filter(entity, &caster.query_filter, caster.ignore_self);
+        hits.0 = query.ray_hits(
+            transform.transform_point(caster.origin),
+            transform.rotation() * caster.direction,
+            caster.max_distance,
+            caster.max_hits,
+            caster.solid,
+            &filter,
+        );
+    }
+}
+
+pub(crate) fn update_shape_casters(
+    mut casters: Query<(Entity, &SdfShapeCaster, &GlobalTransform, &mut SdfShapeHits)>,
+    query: SdfSpatialQuery,
+) {
+    for (entity, caster, transform, mut hits) in casters.iter_mut() {
+        hits.0.clear();
+        if !caster.enabled {
+            continue;
+        }
+
+        let filter = caster_filter(entity, &caster.query_filter, caster.ignore_self);
+        hits.0 = query.shape_hits(
+            &caster.shape,
+            transform.transform_point(caster.origin),
+            transform.rotation() * caster.direction,
+            caster.max_hits,
+            &caster.config,
+            &filter,
+        );
+    }
+}
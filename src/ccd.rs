@@ -0,0 +1,269 @@
+use avian3d::prelude::*;
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
+
+use crate::{
+    adder::Contact, collider::SdfColliderKind, spatial_query::ColliderShape, SdfCollider,
+    SdfContext,
+};
+
+/// Gap between a swept shape and a surface below which they count as touching.
+const SWEEP_TOLERANCE: f32 = 0.005;
+/// Steps of conservative advancement before a sweep stops where it got to.
+const MAX_SWEEP_ITERATIONS: u32 = 32;
+/// Most segments the sweep spheres split a capsule's axis into, long thin capsules space them
+/// further apart.
+const MAX_CAPSULE_SEGMENTS: usize = 32;
+
+/// Poses of [`SweptCcd`] bodies and their SDF colliders at the start of the physics step.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct SweepStarts {
+    bodies: EntityHashMap<Isometry3d>,
+    /// Body, collider and the pose of the collider relative to the body
+    colliders: Vec<(Entity, Entity, Isometry3d)>,
+}
+
+pub(crate) fn record_sweep_starts(
+    mut starts: ResMut<SweepStarts>,
+    bodies: Query<(Entity, &Position, &Rotation), With<SweptCcd>>,
+    colliders: Query<(Entity, &ColliderOf, &Position, &Rotation), With<SdfCollider>>,
+) {
+    let starts = &mut *starts;
+    starts.bodies.clear();
+    starts.colliders.clear();
+    starts.bodies.extend(
+        bodies
+            .iter()
+            .map(|(entity, pos, rot)| (entity, Isometry3d::new(pos.0, rot.0))),
+    );
+    for (entity, collider_of, pos, rot) in colliders.iter() {
+        let Some(body) = starts.bodies.get(&collider_of.body) else {
+            continue;
+        };
+        let offset = body.inverse() * Isometry3d::new(pos.0, rot.0);
+        starts.colliders.push((collider_of.body, entity, offset));
+    }
+}
+
+/// Motion of a body over the physics step, with the translation and rotation interpolated
+/// linearly between the start and the end.
+#[derive(Clone, Copy, Debug)]
+struct BodySweep {
+    start: Isometry3d,
+    end: Isometry3d,
+}
+
+impl BodySweep {
+    fn at(&self, t: f32) -> Isometry3d {
+        Isometry3d::new(
+            self.start.translation.lerp(self.end.translation, t),
+            self.start.rotation.slerp(self.end.rotation, t),
+        )
+    }
+
+    fn linear(&self) -> Vec3 {
+        (self.end.translation - self.start.translation).into()
+    }
+
+    /// Rotation over the step as an axis scaled by the angle, the short way around.
+    fn angular(&self) -> Vec3 {
+        let delta = self.end.rotation * self.start.rotation.inverse();
+        let delta = if delta.w < 0. { -delta } else { delta };
+        delta.to_scaled_axis()
+    }
+
+    /// Fraction of the motion, up to `max`, spheres at `centers` in the space of the body travel
+    /// before touching `other`, with the world normal of the surface they touch.
+    ///
+    /// Uses conservative advancement: every step is bounded by the gap to the surface and the
+    /// fastest any of the centers moves, so thin geometry can't be skipped over.
+    #[allow(clippy::too_many_arguments)]
+    fn time_of_impact(
+        &self,
+        centers: &[Vec3],
+        radius: f32,
+        other: &SdfCollider,
+        other_iso: Isometry3d,
+        max: f32,
+        context: &SdfContext,
+        contacts: &mut Vec<Contact>,
+    ) -> Option<(f32, Vec3)> {
+        let linear = self.linear();
+        let angular = self.angular();
+        let reach = centers
+            .iter()
+            .map(|center| center.length())
+            .fold(0., f32::max);
+        let bound = linear.length() + angular.length() * reach;
+        if bound <= 0. {
+            return None;
+        }
+
+        let sphere = ColliderShape::Sphere(Sphere::new(radius));
+        let inv_other = other_iso.inverse();
+        let mut t = 0.;
+        let mut normal = -linear.normalize_or_zero();
+        for _ in 0..MAX_SWEEP_ITERATIONS {
+            let body = self.at(t);
+            let mut closest: Option<(f32, Vec3)> = None;
+            for &center in centers {
+                let world_center = body.transform_point(center);
+                other.local_shape_contacts(
+                    &sphere,
+                    Quat::IDENTITY,
+                    inv_other.transform_point(world_center).into(),
+                    bound * (max - t),
+                    context,
+                    contacts,
+                );
+                // Only surfaces a sphere moves towards can stop it, not the floor it slides along
+                let velocity = linear + angular.cross((world_center - body.translation).into());
+                for contact in contacts.iter() {
+                    let contact_normal = other_iso.rotation * contact.normal;
+                    if velocity.dot(contact_normal) < -SWEEP_TOLERANCE
+                        && closest.is_none_or(|(penetration, _)| contact.penetration > penetration)
+                    {
+                        closest = Some((contact.penetration, contact_normal));
+                    }
+                }
+            }
+            let (penetration, closest_normal) = closest?;
+            normal = closest_normal;
+
+            let gap = -penetration;
+            if gap <= SWEEP_TOLERANCE {
+                // Surfaces already touched at the start of the step are left to the narrow phase
+                return (t > 0.).then_some((t, normal));
+            }
+            t += gap / bound;
+            if t >= max {
+                return None;
+            }
+        }
+        Some((t, normal))
+    }
+}
+
+/// Spheres a collider is swept as, all with the same radius and centered in the local space of
+/// the collider: itself for a sphere and a chain along the axis of a capsule. Other shapes are
+/// swept as their bounding sphere, which may stop them a little early.
+fn sweep_spheres(collider: &SdfCollider, context: &SdfContext) -> Option<(f32, Vec<Vec3>)> {
+    let scale = collider.scale;
+    match *collider.collider() {
+        SdfColliderKind::Sphere(sphere) => Some((sphere.radius * scale, vec![Vec3::ZERO])),
+        SdfColliderKind::Capsule(capsule) => {
+            let radius = capsule.radius * scale;
+            let half_length = capsule.half_length * scale;
+            // Spheres a radius apart reach within a seventh of the radius of the capsule's sides
+            let segments =
+                ((2. * half_length / radius).ceil() as usize).clamp(1, MAX_CAPSULE_SEGMENTS);
+            let centers = (0..=segments)
+                .map(|i| Vec3::Y * half_length * (2. * i as f32 / segments as f32 - 1.))
+                .collect();
+            Some((radius, centers))
+        }
+        _ => Some((collider.bounding_radius(context)?, vec![Vec3::ZERO])),
+    }
+}
+
+/// Moves [`SweptCcd`] bodies back to where their SDF colliders first touched another collider
+/// during the step, and removes their velocity into that surface.
+///
+/// Avian's own swept CCD only sweeps its built-in colliders, so SDF colliders are swept here
+/// after the solver instead.
+pub(crate) fn sweep_ccd_bodies(
+    starts: Res<SweepStarts>,
+    swept: Query<&SweptCcd>,
+    rigid_bodies: Query<&RigidBody>,
+    colliders: Query<
+        (
+            Entity,
+            &SdfCollider,
+            Option<&ColliderOf>,
+            Option<&CollisionLayers>,
+        ),
+        Without<Sensor>,
+    >,
+    mut poses: ParamSet<(
+        Query<(&Position, &Rotation)>,
+        Query<(&mut Position, &mut Rotation, &mut LinearVelocity)>,
+    )>,
+    context: SdfContext,
+    mut contacts: Local<Vec<Contact>>,
+) {
+    let mut earliest = EntityHashMap::<(BodySweep, f32, Vec3)>::default();
+    let current = poses.p0();
+    for &(body, entity, offset) in &starts.colliders {
+        let (Ok(ccd), Some(&start), Ok((pos, rot))) =
+            (swept.get(body), starts.bodies.get(&body), current.get(body))
+        else {
+            continue;
+        };
+        let sweep = BodySweep {
+            start,
+            end: Isometry3d::new(pos.0, rot.0),
+        };
+        if sweep.linear().length() <= ccd.linear_threshold.max(f32::EPSILON)
+            && sweep.angular().length() <= ccd.angular_threshold
+        {
+            continue;
+        }
+        let Ok((_, collider, _, layers)) = colliders.get(entity) else {
+            continue;
+        };
+        let Some((radius, centers)) = sweep_spheres(collider, &context) else {
+            continue;
+        };
+        let centers: Vec<Vec3> = centers
+            .into_iter()
+            .map(|center| offset.transform_point(center).into())
+            .collect();
+        let layers = layers.copied().unwrap_or_default();
+
+        for (other_entity, other, other_of, other_layers) in colliders.iter() {
+            let other_body = other_of.map(|collider_of| collider_of.body);
+            if other_body == Some(body) || context.query_only_pair(entity, other_entity) {
+                continue;
+            }
+            let dynamic = other_body
+                .and_then(|other_body| rigid_bodies.get(other_body).ok())
+                .is_some_and(RigidBody::is_dynamic);
+            if dynamic && !ccd.include_dynamic {
+                continue;
+            }
+            if !layers.interacts_with(other_layers.copied().unwrap_or_default()) {
+                continue;
+            }
+            let Ok((other_pos, other_rot)) = current.get(other_entity) else {
+                continue;
+            };
+
+            let max = earliest.get(&body).map_or(1., |&(_, toi, _)| toi);
+            let other_iso = Isometry3d::new(other_pos.0, other_rot.0);
+            if let Some((toi, normal)) = sweep.time_of_impact(
+                &centers,
+                radius,
+                other,
+                other_iso,
+                max,
+                &context,
+                &mut contacts,
+            ) {
+                earliest.insert(body, (sweep, toi, normal));
+            }
+        }
+    }
+
+    let mut bodies = poses.p1();
+    for (body, (sweep, toi, normal)) in earliest {
+        let Ok((mut pos, mut rot, mut lin_vel)) = bodies.get_mut(body) else {
+            continue;
+        };
+        let pose = sweep.at(toi);
+        pos.0 = pose.translation.into();
+        rot.0 = pose.rotation;
+        let approach = lin_vel.0.dot(normal);
+        if approach < 0. {
+            lin_vel.0 -= normal * approach;
+        }
+    }
+}
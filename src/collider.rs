@@ -1,49 +1,278 @@
 use bevy::{
-    asset::prelude::Handle, ecs::prelude::Component, math::primitives::*, reflect::Reflect,
+    asset::prelude::{Assets, Handle},
+    ecs::{
+        prelude::{Component, Insert, On, Query, ResMut},
+        reflect::ReflectComponent,
+    },
+    math::{
+        bounding::{Aabb3d, BoundingVolume},
+        primitives::*,
+        Isometry3d, Vec3, Vec3A,
+    },
+    reflect::{std_traits::ReflectDefault, Reflect},
+};
+use bevy_prototype_sdf::{ExecutableSdf3d, Sdf, Sdf3d};
+
+use crate::{
+    backend::{CustomSdf, SdfBackend, SdfSource},
+    primitives::{
+        capsule_between, Ellipsoid, LineSdf, LocalSdf, Parameterized, SdfShell, Shelled,
+        SphereCluster, WithMarchQuality,
+    },
+    swept::SweptSphere,
+    Polyline, SdfContext, SdfMarchQuality, SdfMassProperties, SdfParams,
 };
-use bevy_prototype_sdf::Sdf3d;
 
 #[derive(Component, Debug, Reflect)]
+#[reflect(Component, Default, Debug)]
 #[type_path(sdf_peck)]
 pub struct SdfCollider {
     pub(crate) collider: SdfColliderKind,
     pub(crate) scale: f32,
+    pub(crate) normal_smoothing: f32,
+    pub(crate) shell: Option<SdfShell>,
+    pub(crate) inverted: bool,
+    pub(crate) region: Option<Aabb3d>,
+    // Mirrored from the `SdfParams` component on the same entity
+    #[reflect(ignore)]
+    pub(crate) params: Option<SdfParams>,
+    // Mirrored from the `SdfMassProperties` component on the same entity
+    #[reflect(ignore)]
+    pub(crate) mass_properties: Option<SdfMassProperties>,
+    // Moved into `Assets<Sdf3d>` as soon as the collider is inserted
+    #[reflect(ignore)]
+    embedded: Option<Sdf3d>,
+    // Set when the SDF asset was processed again, until the next physics step is done
+    #[reflect(ignore)]
+    pub(crate) reloaded: bool,
+    // Unscaled bounding radius the collider acts as while `SdfColliderLod` simplifies it
+    #[reflect(ignore)]
+    pub(crate) simplified: Option<f32>,
+}
+
+impl Default for SdfCollider {
+    fn default() -> Self {
+        Self::from_kind(SdfColliderKind::default())
+    }
 }
 
 impl SdfCollider {
     pub fn sphere(radius: f32) -> Self {
-        Self {
-            collider: SdfColliderKind::Sphere(Sphere::new(radius)),
-            scale: 1.,
-        }
+        Self::from_kind(SdfColliderKind::Sphere(Sphere::new(radius)))
     }
 
     pub fn capsule(radius: f32, length: f32) -> Self {
+        Self::from_kind(SdfColliderKind::Capsule(Capsule3d::new(radius, length)))
+    }
+
+    pub fn ellipsoid(half_size: Vec3) -> Self {
+        Self::from_kind(SdfColliderKind::Ellipsoid(Ellipsoid::new(half_size)))
+    }
+
+    /// Creates a collider out of many small spheres, for debris and particles.
+    pub fn sphere_cluster(cluster: SphereCluster) -> Self {
+        Self::from_kind(SdfColliderKind::SphereCluster(cluster))
+    }
+
+    /// Creates a chain of capsules through `points`, for rails, pipes and rope proxies.
+    pub fn polyline(points: impl IntoIterator<Item = Vec3>, radius: f32) -> Self {
+        Self::from_kind(SdfColliderKind::Polyline(Polyline::new(points, radius)))
+    }
+
+    /// Creates a sphere that the narrow phase stretches along the body's motion over the last
+    /// step, for fast projectiles.
+    pub fn swept_sphere(radius: f32) -> Self {
+        Self::from_kind(SdfColliderKind::SweptSphere(SweptSphere::new(radius)))
+    }
+
+    /// Creates a line from `start` to `end` in the local space of the collider.
+    pub fn segment(start: Vec3, end: Vec3) -> Self {
+        Self::from_kind(SdfColliderKind::Segment(Segment3d::new(start, end)))
+    }
+
+    pub fn sdf(handle: Handle<Sdf3d>) -> Self {
+        Self::from_kind(SdfColliderKind::Arbitrary(handle))
+    }
+
+    /// Creates a collider from an SDF evaluated by user code, see [`SdfBackend`].
+    pub fn custom(backend: impl SdfBackend) -> Self {
+        Self::from_kind(SdfColliderKind::Custom(CustomSdf::new(backend)))
+    }
+
+    /// Creates a collider from an SDF that doesn't come from the asset server.
+    ///
+    /// The SDF is added to `Assets<Sdf3d>` when the collider is inserted, and is freed again once
+    /// the collider is gone.
+    pub fn sdf_owned(sdf: Sdf3d) -> Self {
         Self {
-            collider: SdfColliderKind::Capsule(Capsule3d::new(radius, length)),
-            scale: 1.,
+            embedded: Some(sdf),
+            ..Self::from_kind(SdfColliderKind::Arbitrary(Handle::default()))
         }
     }
 
-    pub fn sdf(handle: Handle<Sdf3d>) -> Self {
+    fn from_kind(collider: SdfColliderKind) -> Self {
         Self {
-            collider: SdfColliderKind::Arbitrary(handle),
+            collider,
             scale: 1.,
+            normal_smoothing: 0.,
+            shell: None,
+            inverted: false,
+            region: None,
+            params: None,
+            mass_properties: None,
+            embedded: None,
+            reloaded: false,
+            simplified: None,
         }
     }
 
+    /// Rounds off the contact normals of SDF asset colliders as if their edges had this radius,
+    /// without changing the surface itself.
+    pub fn with_normal_smoothing(mut self, radius: f32) -> Self {
+        self.normal_smoothing = radius;
+        self
+    }
+
+    /// Only treats the part of an SDF asset collider where the distance is between `inner` and
+    /// `outer` as solid, turning solid shapes into hollow containers or crusts.
+    pub fn with_shell(mut self, inner: f32, outer: f32) -> Self {
+        self.shell = Some(SdfShell::new(inner, outer));
+        self
+    }
+
+    pub fn shell(&self) -> Option<SdfShell> {
+        self.shell
+    }
+
+    /// Turns an SDF asset collider inside out, so everything outside its surface is solid and
+    /// bodies are kept inside it like a container.
+    ///
+    /// The bounds of the collider stay those of the asset, bodies outside them get no contacts.
+    pub fn with_inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Restricts an SDF asset collider to a region of its local space, for SDFs far larger than
+    /// the area anything happens in, like planets.
+    ///
+    /// The collider's AABB is limited to the region, so the broad phase only pairs it with bodies
+    /// nearby, and only the surface inside the region is sampled for contacts and queries. The SDF
+    /// itself is still evaluated in full at every sample.
+    pub fn with_region(mut self, region: Option<Aabb3d>) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Moves the region the collider is restricted to, like a chunk following the player.
+    ///
+    /// The AABB and contacts of the collider are refreshed during the next step.
+    pub fn set_region(&mut self, region: Option<Aabb3d>) {
+        self.region = region;
+        self.reloaded = true;
+    }
+
+    pub fn region(&self) -> Option<Aabb3d> {
+        self.region
+    }
+
     pub fn collider(&self) -> &SdfColliderKind {
         &self.collider
     }
+
+    pub fn uniform_scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// The SDF asset of the collider, if it's made of one.
+    pub fn sdf_handle(&self) -> Option<&Handle<Sdf3d>> {
+        match &self.collider {
+            SdfColliderKind::Arbitrary(handle) => Some(handle),
+            _ => None,
+        }
+    }
+
+    /// Replaces the shape of the collider, keeping its other settings.
+    ///
+    /// The AABB and contacts of the collider are refreshed during the next step, and avian
+    /// recomputes its mass from the change detection of the component.
+    pub fn set_shape(&mut self, shape: impl Into<SdfColliderKind>) {
+        self.collider = shape.into();
+        self.embedded = None;
+        self.simplified = None;
+        self.reloaded = true;
+    }
+
+    /// Turns the collider into a sphere with this radius, for colliders that grow and shrink.
+    pub fn set_sphere_radius(&mut self, radius: f32) {
+        self.set_shape(Sphere::new(radius));
+    }
+
+    pub fn set_capsule(&mut self, radius: f32, length: f32) {
+        self.set_shape(Capsule3d::new(radius, length));
+    }
+
+    pub fn set_sdf_handle(&mut self, handle: Handle<Sdf3d>) {
+        self.set_shape(SdfColliderKind::Arbitrary(handle));
+    }
+
+    /// Overrides the scale of the collider, until the scale of the entity's transform changes.
+    pub fn set_uniform_scale(&mut self, scale: f32) {
+        self.scale = scale.abs();
+        self.reloaded = true;
+    }
 }
 
+/// Thickness segments collide with in their local units, as capsules with a zero radius can't be
+/// marched against.
+pub(crate) const SEGMENT_RADIUS: f32 = 0.001;
+
 #[derive(Component, Debug, Reflect)]
+#[reflect(Default, Debug)]
 pub enum SdfColliderKind {
     Sphere(Sphere),
     Capsule(Capsule3d),
+    Ellipsoid(Ellipsoid),
+    SphereCluster(SphereCluster),
+    SweptSphere(SweptSphere),
+    /// A line without thickness, for lasers, wires and thin rods
+    Segment(Segment3d),
+    Polyline(Polyline),
     // TODO: Uneven capsule
     // TODO: Torus
-    Arbitrary(Handle<Sdf3d>),
+    // Handles can't be serialized, scenes store the asset path in `SdfAssetPath` instead
+    Arbitrary(#[reflect(ignore)] Handle<Sdf3d>),
+    /// An SDF evaluated by user code, colliding like an SDF asset
+    Custom(#[reflect(ignore)] CustomSdf),
+}
+
+impl SdfColliderKind {
+    /// Whether the shape is an SDF asset or custom SDF, rather than an analytic shape.
+    pub fn is_sdf(&self) -> bool {
+        matches!(self, Self::Arbitrary(_) | Self::Custom(_))
+    }
+}
+
+impl From<Sphere> for SdfColliderKind {
+    fn from(sphere: Sphere) -> Self {
+        Self::Sphere(sphere)
+    }
+}
+
+impl From<Capsule3d> for SdfColliderKind {
+    fn from(capsule: Capsule3d) -> Self {
+        Self::Capsule(capsule)
+    }
+}
+
+impl From<Polyline> for SdfColliderKind {
+    fn from(polyline: Polyline) -> Self {
+        Self::Polyline(polyline)
+    }
 }
 
 impl Default for SdfColliderKind {
@@ -51,3 +280,241 @@ impl Default for SdfColliderKind {
         Self::Sphere(Sphere::default())
     }
 }
+
+pub(crate) enum ColliderSdf<'a> {
+    Sphere(Sphere),
+    Capsule(Capsule3d),
+    Ellipsoid(Ellipsoid),
+    Cluster(&'a SphereCluster),
+    Line(LineSdf),
+    Polyline(&'a Polyline),
+    Asset(WithMarchQuality<Shelled<Parameterized<SdfSource<'a>, ExecutableSdf3d<'a>>>>),
+}
+
+impl LocalSdf for ColliderSdf<'_> {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        match self {
+            Self::Sphere(s) => s.distance(local_point),
+            Self::Capsule(c) => c.distance(local_point),
+            Self::Ellipsoid(e) => e.distance(local_point),
+            Self::Cluster(c) => c.distance(local_point),
+            Self::Line(l) => l.distance(local_point),
+            Self::Polyline(p) => p.distance(local_point),
+            Self::Asset(sdf) => sdf.distance(local_point),
+        }
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        match self {
+            Self::Sphere(s) => s.gradient(local_point),
+            Self::Capsule(c) => c.gradient(local_point),
+            Self::Ellipsoid(e) => e.gradient(local_point),
+            Self::Cluster(c) => c.gradient(local_point),
+            Self::Line(l) => l.gradient(local_point),
+            Self::Polyline(p) => p.gradient(local_point),
+            Self::Asset(sdf) => sdf.gradient(local_point),
+        }
+    }
+
+    fn record_march_iterations(&self, iterations: u32) {
+        if let Self::Asset(sdf) = self {
+            sdf.record_march_iterations(iterations);
+        }
+    }
+
+    fn march_quality(&self) -> SdfMarchQuality {
+        match self {
+            Self::Asset(sdf) => sdf.march_quality(),
+            _ => SdfMarchQuality::DEFAULT,
+        }
+    }
+}
+
+impl ColliderSdf<'_> {
+    /// Marches SDF assets with this quality instead of the global one.
+    pub(crate) fn with_march_quality(self, quality: SdfMarchQuality) -> Self {
+        match self {
+            Self::Asset(sdf) => {
+                Self::Asset(WithMarchQuality::new(sdf.sdf, quality).with_bounds(sdf.bounds))
+            }
+            other => other,
+        }
+    }
+}
+
+impl SdfCollider {
+    /// Radius of a sphere around the collider's origin that contains the whole collider.
+    pub(crate) fn bounding_radius(&self, context: &SdfContext) -> Option<f32> {
+        if let Some(placeholder) = context.placeholder(self) {
+            return Some(placeholder.radius * self.scale);
+        }
+        let unscaled = match &self.collider {
+            SdfColliderKind::Sphere(s) => s.radius,
+            SdfColliderKind::Capsule(c) => c.radius + c.half_length,
+            SdfColliderKind::Ellipsoid(e) => e.half_size.max_element(),
+            SdfColliderKind::SphereCluster(c) => {
+                c.centers().map(Vec3::length).fold(0., f32::max) + c.radius
+            }
+            SdfColliderKind::SweptSphere(s) => s.radius + s.start.length().max(s.end.length()),
+            SdfColliderKind::Segment(s) => {
+                SEGMENT_RADIUS + s.vertices[0].length().max(s.vertices[1].length())
+            }
+            SdfColliderKind::Polyline(p) => {
+                p.points().iter().map(|p| p.length()).fold(0., f32::max) + p.radius
+            }
+            SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_) => {
+                let radius = |sdf: SdfSource| {
+                    let aabb = sdf.aabb(Isometry3d::IDENTITY);
+                    Vec3::from(aabb.min.abs().max(aabb.max.abs())).length()
+                };
+                let target = self
+                    .blend_target(context)
+                    .map_or(0., |target| radius(SdfSource::Asset(target)));
+                radius(self.sdf_source(context)?).max(target) + self.surface_margin()
+            }
+        };
+        Some(unscaled * self.scale)
+    }
+
+    /// The SDF of an asset or custom collider, without its settings applied.
+    pub(crate) fn sdf_source<'a>(&'a self, context: &'a SdfContext) -> Option<SdfSource<'a>> {
+        match &self.collider {
+            SdfColliderKind::Arbitrary(handle) => {
+                Some(SdfSource::Asset(context.get(handle.id())?.1))
+            }
+            SdfColliderKind::Custom(custom) => Some(SdfSource::Custom(&*custom.0)),
+            _ => None,
+        }
+    }
+
+    /// How far the shell and parameters reach outside the surface of the SDF asset, in its local
+    /// units.
+    pub(crate) fn surface_margin(&self) -> f32 {
+        let inflate = self.params.as_ref().map_or(0., |params| params.inflate);
+        let outer = self.shell.map_or(0., |shell| shell.outer);
+        (inflate + outer).max(0.)
+    }
+
+    /// Local bounds of the surface of an SDF asset collider, including its shell and parameters.
+    ///
+    /// Inverted colliders are solid outside their bounds, so they have none.
+    pub(crate) fn far_field_bounds(&self, context: &SdfContext) -> Option<Aabb3d> {
+        if self.inverted {
+            return None;
+        }
+        let mut bounds = self.sdf_source(context)?.aabb(Isometry3d::IDENTITY);
+        if let Some(target) = self.blend_target(context) {
+            bounds = bounds.merge(&target.aabb(Isometry3d::IDENTITY));
+        }
+        bounds.min -= self.surface_margin();
+        bounds.max += self.surface_margin();
+        Some(bounds)
+    }
+
+    /// The asset [`SdfParams`] blends towards, if it's loaded and used.
+    pub(crate) fn blend_target<'a>(&self, context: &'a SdfContext) -> Option<ExecutableSdf3d<'a>> {
+        let params = self.params.as_ref().filter(|params| params.blend != 0.)?;
+        Some(context.get(params.blend_target.as_ref()?.id())?.1)
+    }
+
+    /// Applies the [`SdfParams`] of this collider to its SDF asset.
+    pub(crate) fn parameterized<'a, S: LocalSdf>(
+        &self,
+        sdf: S,
+        context: &'a SdfContext,
+    ) -> Parameterized<S, ExecutableSdf3d<'a>> {
+        let (blend, inflate) = self
+            .params
+            .as_ref()
+            .map_or((0., 0.), |params| (params.blend, params.inflate));
+        Parameterized::new(sdf, self.blend_target(context), blend, inflate)
+    }
+
+    /// Applies the inversion and shell of this collider to its SDF asset.
+    pub(crate) fn shelled<S: LocalSdf>(&self, sdf: S) -> Shelled<S> {
+        Shelled::new(sdf, self.shell, self.inverted).with_region(self.region)
+    }
+
+    /// The capsule swept spheres and segments collide as, with its pose for a body at `iso`.
+    ///
+    /// The capsule isn't scaled yet, its pose is.
+    pub(crate) fn line_capsule(&self, iso: Isometry3d) -> Option<(Capsule3d, Isometry3d)> {
+        match self.collider {
+            SdfColliderKind::SweptSphere(swept) => {
+                // Swept spheres follow the motion in world space, regardless of the rotation
+                let (capsule, offset, rotation) = swept.capsule();
+                let translation = iso.translation + Vec3A::from(offset * self.scale);
+                Some((capsule, Isometry3d::new(translation, rotation)))
+            }
+            SdfColliderKind::Segment(segment) => {
+                let (capsule, offset, rotation) =
+                    capsule_between(segment.vertices[0], segment.vertices[1], SEGMENT_RADIUS);
+                let local = Isometry3d::new(offset * self.scale, rotation);
+                Some((capsule, iso * local))
+            }
+            _ => None,
+        }
+    }
+
+    /// A collider with the same settings but a different shape.
+    pub(crate) fn with_shape(&self, shape: impl Into<SdfColliderKind>) -> Self {
+        Self {
+            collider: shape.into(),
+            scale: self.scale,
+            normal_smoothing: self.normal_smoothing,
+            shell: self.shell,
+            inverted: self.inverted,
+            region: self.region,
+            params: self.params.clone(),
+            mass_properties: self.mass_properties,
+            embedded: None,
+            reloaded: self.reloaded,
+            simplified: None,
+        }
+    }
+
+    pub(crate) fn local_sdf<'a>(&'a self, context: &'a SdfContext) -> Option<ColliderSdf<'a>> {
+        if let Some(placeholder) = context.placeholder(self) {
+            return Some(ColliderSdf::Sphere(placeholder));
+        }
+        self.full_sdf(context)
+    }
+
+    /// Like [`local_sdf`](Self::local_sdf), but never replaced by a placeholder sphere.
+    pub(crate) fn full_sdf<'a>(&'a self, context: &'a SdfContext) -> Option<ColliderSdf<'a>> {
+        Some(match &self.collider {
+            &SdfColliderKind::Sphere(s) => ColliderSdf::Sphere(s),
+            &SdfColliderKind::Capsule(c) => ColliderSdf::Capsule(c),
+            &SdfColliderKind::Ellipsoid(e) => ColliderSdf::Ellipsoid(e),
+            SdfColliderKind::SphereCluster(c) => ColliderSdf::Cluster(c),
+            &SdfColliderKind::SweptSphere(s) => ColliderSdf::Sphere(Sphere::new(s.radius)),
+            &SdfColliderKind::Segment(s) => ColliderSdf::Line(LineSdf {
+                start: s.vertices[0],
+                end: s.vertices[1],
+                radius: SEGMENT_RADIUS,
+            }),
+            SdfColliderKind::Polyline(p) => ColliderSdf::Polyline(p),
+            SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_) => ColliderSdf::Asset(
+                WithMarchQuality::new(
+                    self.shelled(self.parameterized(self.sdf_source(context)?, context)),
+                    *context.default_march_quality,
+                )
+                .with_bounds(self.far_field_bounds(context)),
+            ),
+        })
+    }
+}
+
+pub(crate) fn add_embedded_sdfs(
+    trigger: On<Insert, SdfCollider>,
+    mut colliders: Query<&mut SdfCollider>,
+    mut sdfs: ResMut<Assets<Sdf3d>>,
+) {
+    let Ok(mut collider) = colliders.get_mut(trigger.entity) else {
+        return;
+    };
+    let Some(sdf) = collider.embedded.take() else {
+        return;
+    };
+    collider.collider = SdfColliderKind::Arbitrary(sdfs.add(sdf));
+}
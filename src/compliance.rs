@@ -0,0 +1,87 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+/// Makes an [`SdfCollider`](crate::SdfCollider) soft, like mud, snow or foam.
+///
+/// Bodies sink up to `depth` into the surface, pushed back by a damped spring, before the surface
+/// becomes rigid. Avian's solver has no per-contact softness, so the contacts report `depth` less
+/// penetration and the spring is applied to the velocity of the bodies before every step.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Debug)]
+pub struct SdfContactCompliance {
+    /// How deep bodies can sink into the surface before it stops them
+    pub depth: f32,
+    /// Acceleration pushing bodies out per unit of depth, regardless of their mass
+    pub stiffness: f32,
+    /// Fraction of the velocity into the surface lost per second
+    pub damping: f32,
+}
+
+impl SdfContactCompliance {
+    pub fn new(depth: f32, stiffness: f32) -> Self {
+        Self {
+            depth,
+            stiffness,
+            damping: 4.,
+        }
+    }
+
+    pub fn with_damping(mut self, damping: f32) -> Self {
+        self.damping = damping;
+        self
+    }
+}
+
+/// Moves the contacts of a pair with compliant colliders out by their sink depth.
+pub(crate) fn offset_compliant_contacts(
+    contacts: &mut [ContactManifold],
+    compliance1: Option<&SdfContactCompliance>,
+    compliance2: Option<&SdfContactCompliance>,
+) {
+    let depth = compliance1.map_or(0., |c| c.depth) + compliance2.map_or(0., |c| c.depth);
+    for point in contacts.iter_mut().flat_map(|m| m.points.iter_mut()) {
+        point.penetration -= depth;
+    }
+}
+
+pub(crate) fn apply_compliant_springs(
+    collisions: Collisions,
+    surfaces: Query<&SdfContactCompliance>,
+    mut bodies: Query<(&RigidBody, &mut LinearVelocity)>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    for pair in collisions.iter() {
+        for (surface, body, side) in [
+            (pair.collider1, pair.collider2, 1.),
+            (pair.collider2, pair.collider1, -1.),
+        ] {
+            let Ok(compliance) = surfaces.get(surface) else {
+                continue;
+            };
+            let Ok((rb, mut lin_vel)) = bodies.get_mut(body) else {
+                continue;
+            };
+            if !rb.is_dynamic() {
+                continue;
+            }
+            for manifold in pair.manifolds.iter() {
+                // Undo the offset to get how deep the body sank into the surface
+                let sunk = manifold
+                    .points
+                    .iter()
+                    .map(|point| point.penetration + compliance.depth)
+                    .fold(0f32, f32::max)
+                    .min(compliance.depth);
+                if sunk <= 0. {
+                    continue;
+                }
+                // Normals point from the first collider to the second
+                let normal = manifold.normal * side;
+                let into_surface = lin_vel.0.dot(normal).min(0.);
+                let push = compliance.stiffness * sunk - compliance.damping * into_surface;
+                lin_vel.0 += normal * push * dt;
+            }
+        }
+    }
+}
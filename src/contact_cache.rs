@@ -0,0 +1,123 @@
+use std::sync::Mutex;
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_math::ops;
+
+use crate::primitives::SegmentWarmStart;
+
+const SHARDS: usize = 16;
+
+/// Contact normals kept per pair, further contacts aren't smoothed
+const MAX_PAIR_NORMALS: usize = 4;
+
+/// Weight of the new normal when it's blended with the previous one
+const NORMAL_BLEND: f32 = 0.5;
+
+/// Contact normals of a pair in the last step, that the next normals are blended with.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PairNormals {
+    normals: [Vec3; MAX_PAIR_NORMALS],
+    len: usize,
+}
+
+impl PairNormals {
+    pub fn push(&mut self, normal: Vec3) {
+        if self.len < MAX_PAIR_NORMALS {
+            self.normals[self.len] = normal;
+            self.len += 1;
+        }
+    }
+
+    /// Blends `normal` with the closest previous normal if they're less than `max_angle` apart,
+    /// larger changes like moving onto another face pass through unchanged.
+    pub fn smooth(&self, normal: Vec3, max_angle: f32) -> Vec3 {
+        let min_cos = ops::cos(max_angle);
+        self.normals[..self.len]
+            .iter()
+            .copied()
+            .max_by(|a, b| a.dot(normal).total_cmp(&b.dot(normal)))
+            .filter(|previous| previous.dot(normal) >= min_cos)
+            .map_or(normal, |previous| {
+                previous.lerp(normal, NORMAL_BLEND).normalize_or(normal)
+            })
+    }
+}
+
+#[derive(Debug, Default)]
+struct CachedPair {
+    warm: SegmentWarmStart,
+    normals: PairNormals,
+    used: u32,
+}
+
+/// Warm starts and normals of the pairs of the last steps, split over several locks since pairs
+/// are generated in parallel.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct SdfContactCache {
+    shards: [Mutex<HashMap<(Entity, Entity), CachedPair>>; SHARDS],
+    tick: u32,
+}
+
+impl SdfContactCache {
+    fn shard(
+        &self,
+        entity1: Entity,
+        entity2: Entity,
+    ) -> &Mutex<HashMap<(Entity, Entity), CachedPair>> {
+        &self.shards[(entity1.index() ^ entity2.index()) as usize % SHARDS]
+    }
+
+    pub fn get(&self, entity1: Entity, entity2: Entity) -> SegmentWarmStart {
+        let shard = self.shard(entity1, entity2).lock().unwrap();
+        shard
+            .get(&(entity1, entity2))
+            .map_or_else(SegmentWarmStart::default, |pair| pair.warm)
+    }
+
+    pub fn insert(&self, entity1: Entity, entity2: Entity, warm: SegmentWarmStart) {
+        let mut shard = self.shard(entity1, entity2).lock().unwrap();
+        let pair = shard.entry((entity1, entity2)).or_default();
+        pair.warm = warm;
+        pair.used = self.tick;
+    }
+
+    pub fn normals(&self, entity1: Entity, entity2: Entity) -> PairNormals {
+        let shard = self.shard(entity1, entity2).lock().unwrap();
+        shard
+            .get(&(entity1, entity2))
+            .map_or_else(PairNormals::default, |pair| pair.normals)
+    }
+
+    pub fn insert_normals(&self, entity1: Entity, entity2: Entity, normals: PairNormals) {
+        let mut shard = self.shard(entity1, entity2).lock().unwrap();
+        let pair = shard.entry((entity1, entity2)).or_default();
+        pair.normals = normals;
+        pair.used = self.tick;
+    }
+}
+
+/// Forgets pairs that weren't generated during the last step.
+pub(crate) fn evict_stale_warm_starts(mut cache: ResMut<SdfContactCache>) {
+    let cache = &mut *cache;
+    let tick = cache.tick;
+    for shard in cache.shards.iter_mut() {
+        shard.get_mut().unwrap().retain(|_, pair| pair.used == tick);
+    }
+    cache.tick = tick.wrapping_add(1);
+}
+
+#[test]
+fn test_pair_normal_smoothing() {
+    let mut normals = PairNormals::default();
+    normals.push(Vec3::Y);
+    normals.push(Vec3::X);
+
+    // Small changes are halved, towards the closest previous normal
+    let tilted = Vec3::new(0., 1., 0.1).normalize();
+    let smoothed = normals.smooth(tilted, 0.2);
+    assert!(smoothed.angle_between(Vec3::Y) < tilted.angle_between(Vec3::Y) * 0.6);
+    assert!(smoothed.is_normalized());
+    // Normals far from every previous one pass through
+    assert_eq!(normals.smooth(Vec3::Z, 0.2), Vec3::Z);
+    assert_eq!(PairNormals::default().smooth(tilted, 0.2), tilted);
+}
@@ -0,0 +1,334 @@
+use std::ops::Deref;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::SystemParam, math::FloatPow, prelude::*};
+use bevy_prototype_sdf::{dim3::Dim3, ExecutableSdfs};
+
+use crate::{
+    compliance::SdfContactCompliance, contact_cache::SdfContactCache,
+    diagnostics::SdfCollisionDiagnostics, filters::SdfContactFilters, motion::SdfSurfaceMotion,
+    one_way::OneWaySurface, patches::SdfPatchCache, slope::SlopeFriction, MissingSdfPolicy,
+    SdfCollider, SdfColliderKind, SdfMarchQuality,
+};
+
+#[derive(SystemParam)]
+pub struct SdfContext<'w, 's> {
+    sdfs: ExecutableSdfs<'w, Dim3>,
+    pub(crate) query_config: Res<'w, SdfQueryConfig>,
+    pub(crate) missing_sdf: Res<'w, MissingSdfPolicy>,
+    pub(crate) default_march_quality: Res<'w, SdfMarchQuality>,
+    narrow_phase: Option<NarrowPhaseResources<'w>>,
+    march_overrides: Query<'w, 's, &'static SdfMarchQuality>,
+    pub(crate) one_way_surfaces: Query<'w, 's, &'static OneWaySurface>,
+    pub(crate) slope_frictions: Query<'w, 's, &'static SlopeFriction>,
+    pub(crate) compliances: Query<'w, 's, &'static SdfContactCompliance>,
+    query_only: Query<'w, 's, (), With<SdfQueryOnly>>,
+    frictions: Query<'w, 's, &'static Friction>,
+    lod_viewers: Query<'w, 's, &'static GlobalTransform, With<SdfLodViewer>>,
+    world_offset: Option<Res<'w, WorldOffset>>,
+}
+
+/// Resources of contact generation, only added by [`SdfCollisionPlugin`](crate::SdfCollisionPlugin)
+/// so queries work without it.
+#[derive(SystemParam)]
+pub(crate) struct NarrowPhaseResources<'w> {
+    pub lod: Res<'w, NarrowPhaseLod>,
+    pub stabilization: Res<'w, ContactStabilization>,
+    pub prediction_distance: Res<'w, SdfPredictionDistance>,
+    pub diagnostics: Res<'w, SdfCollisionDiagnostics>,
+    pub patches: Res<'w, SdfPatchCache>,
+    pub unsupported_pairs: Res<'w, UnsupportedPairs>,
+    pub surface_motion: Res<'w, SdfSurfaceMotion>,
+    pub contact_cache: Res<'w, SdfContactCache>,
+    pub contact_filters: Res<'w, SdfContactFilters>,
+    pub default_friction: Res<'w, DefaultFriction>,
+}
+
+impl<'w> Deref for SdfContext<'w, '_> {
+    type Target = ExecutableSdfs<'w, Dim3>;
+    fn deref(&self) -> &Self::Target {
+        &self.sdfs
+    }
+}
+
+/// Reduces how often contacts are updated for pairs far away from every [`SdfLodViewer`].
+#[derive(Resource, Debug, Clone)]
+pub struct NarrowPhaseLod {
+    /// Pairs closer than this to a viewer are always updated
+    pub distance: f32,
+    /// Distant pairs are updated once every `interval` steps, reusing their old contacts otherwise
+    pub interval: u32,
+    tick: u32,
+}
+
+impl Default for NarrowPhaseLod {
+    fn default() -> Self {
+        Self {
+            distance: 50.,
+            interval: 1,
+            tick: 0,
+        }
+    }
+}
+
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct SdfLodViewer;
+
+/// Makes an SDF asset collider act as its bounding sphere for contacts and queries while it's far
+/// from everything that could touch it, for detailed props in large worlds.
+///
+/// The full SDF is used while the origin of a dynamic body or [`SdfLodViewer`] is within
+/// `distance` of the bounding sphere.
+#[derive(Component, Reflect, Debug, Clone, Copy)]
+#[reflect(Component, Debug)]
+#[type_path(sdf_peck)]
+pub struct SdfColliderLod {
+    pub distance: f32,
+}
+
+impl SdfColliderLod {
+    pub fn new(distance: f32) -> Self {
+        Self { distance }
+    }
+}
+
+/// Excludes an [`SdfCollider`] from contact generation, while still letting spatial queries hit
+/// it, for visibility blockers and interaction hitboxes.
+#[derive(Component, Reflect, Debug, Default, Clone, Copy)]
+#[reflect(Component, Debug, Default)]
+#[type_path(sdf_peck)]
+pub struct SdfQueryOnly;
+
+/// Quantizes and smooths generated contacts so resting bodies get identical contacts every step
+/// and can sleep.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct ContactStabilization {
+    /// Grid size contact positions and penetrations are snapped to, and the angle in radians
+    /// normals are snapped to. Penetrations are rounded up so resting contacts keep pushing.
+    /// Disabled if `None`
+    pub quantum: Option<f32>,
+    /// Capsules that moved less than this relative to an SDF asset since their contacts were
+    /// generated keep the contacts at the same places, only updating their depth, which makes
+    /// resting contacts cheap and coherent. Disabled if `None`
+    pub persistence: Option<f32>,
+    /// Blends contact normals with the closest normal of the same pair in the previous step if
+    /// they're less than this angle apart, in radians, so grazing contacts on baked SDFs don't
+    /// flicker. Disabled if `None`
+    pub normal_smoothing: Option<f32>,
+}
+
+/// Predicted contact distances for pairs of [`SdfCollider`]s, replacing the distance avian derives
+/// from its speculative margin.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct SdfPredictionDistance {
+    /// Used for pairs with an SDF asset collider, where larger margins keep bodies from tunneling
+    /// through thin shells. Follows avian if `None`
+    pub sdf: Option<f32>,
+    /// Used for pairs of analytic shapes, where smaller margins keep the number of speculative
+    /// contacts down. Follows avian if `None`
+    pub primitives: Option<f32>,
+}
+
+impl SdfPredictionDistance {
+    /// The predicted distance for a pair, given the one avian computed.
+    pub(crate) fn for_pair(
+        &self,
+        collider1: &SdfCollider,
+        collider2: &SdfCollider,
+        physics: f32,
+    ) -> f32 {
+        let distance = if collider1.collider().is_sdf() || collider2.collider().is_sdf() {
+            self.sdf
+        } else {
+            self.primitives
+        };
+        distance.unwrap_or(physics)
+    }
+}
+
+/// Where the origin of physics space is in the game world, for games that move their origin along
+/// with the player to keep coordinates small.
+///
+/// Add the shift to this in the same frame every position is moved back by it. Contact
+/// quantization and the [`SdfQueryGrid`](crate::SdfQueryGrid) then work in world coordinates, so
+/// shifting the origin doesn't move resting contacts or leave the grid behind until it's rebuilt.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct WorldOffset(pub Vec3);
+
+/// What the narrow phase does with pairs of collider kinds it can't generate contacts for.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnsupportedPairs {
+    /// Log a warning the first time an unsupported pair is found, and generate no contacts
+    #[default]
+    Warn,
+    /// Silently generate no contacts
+    Ignore,
+    /// Replace the smaller collider of the pair by its bounding sphere, which keeps them apart
+    /// at the cost of contacts that are too early
+    BoundingSpheres,
+}
+
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SdfQueryConfig {
+    pub start_penetrating: StartPenetrating,
+}
+
+/// What a shape cast reports when the shape already overlaps a collider at the start of the cast.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StartPenetrating {
+    /// Report a hit at the start of the cast with a normal opposing the cast direction
+    #[default]
+    ReportZero,
+    /// Skip colliders the shape starts inside of
+    Ignore,
+    /// Report a hit at the start of the cast with the direction that resolves the overlap
+    ReportWithDepenetrationNormal,
+}
+
+pub(crate) fn advance_lod_tick(mut lod: ResMut<NarrowPhaseLod>) {
+    lod.tick = lod.tick.wrapping_add(1);
+}
+
+pub(crate) fn simplify_distant_colliders(
+    mut colliders: Query<(&mut SdfCollider, &SdfColliderLod, &Position)>,
+    bodies: Query<(&RigidBody, &Position)>,
+    viewers: Query<&GlobalTransform, With<SdfLodViewer>>,
+    context: SdfContext,
+) {
+    for (mut collider, lod, pos) in colliders.iter_mut() {
+        if !collider.collider().is_sdf() {
+            continue;
+        }
+        let Some(radius) = collider.bounding_radius(&context) else {
+            continue;
+        };
+
+        let reach_sq = (radius + lod.distance).squared();
+        let near = bodies
+            .iter()
+            .any(|(rb, body)| rb.is_dynamic() && body.distance_squared(pos.0) < reach_sq)
+            || viewers
+                .iter()
+                .any(|viewer| viewer.translation().distance_squared(pos.0) < reach_sq);
+        let simplified = (!near).then_some(radius / collider.scale);
+        if collider.simplified != simplified {
+            collider.simplified = simplified;
+            // Refreshes the AABB and contacts of the collider during the next step
+            collider.reloaded = true;
+        }
+    }
+}
+
+impl<'w> SdfContext<'w, '_> {
+    /// The resources contact generation uses, which only runs with the narrow phase added.
+    pub(crate) fn narrow_phase(&self) -> &NarrowPhaseResources<'w> {
+        self.narrow_phase
+            .as_ref()
+            .expect("contacts are only generated with the narrow phase of SdfCollisionPlugin")
+    }
+
+    /// Returns the sphere a collider acts as while it's simplified by [`SdfColliderLod`], or while
+    /// its SDF asset is missing if configured.
+    pub(crate) fn placeholder(&self, collider: &SdfCollider) -> Option<Sphere> {
+        if let Some(radius) = collider.simplified {
+            return Some(Sphere::new(radius));
+        }
+        let MissingSdfPolicy::Placeholder(radius) = *self.missing_sdf else {
+            return None;
+        };
+        let SdfColliderKind::Arbitrary(handle) = collider.collider() else {
+            return None;
+        };
+        self.sdfs
+            .get(handle.id())
+            .is_none()
+            .then(|| Sphere::new(radius))
+    }
+
+    /// The [`WorldOffset`] of physics space, zero if there is none.
+    pub(crate) fn world_offset(&self) -> Vec3 {
+        self.world_offset
+            .as_ref()
+            .map_or(Vec3::ZERO, |offset| offset.0)
+    }
+
+    /// Whether the pair is skipped by the narrow phase because one of them is [`SdfQueryOnly`].
+    pub(crate) fn query_only_pair(&self, entity1: Entity, entity2: Entity) -> bool {
+        self.query_only.contains(entity1) || self.query_only.contains(entity2)
+    }
+
+    /// The march quality for the collider on `entity`, from its component or the global resource.
+    pub(crate) fn march_quality(&self, entity: Entity) -> SdfMarchQuality {
+        self.march_overrides
+            .get(entity)
+            .copied()
+            .unwrap_or(*self.default_march_quality)
+    }
+
+    /// The combined dynamic friction of a pair of colliders.
+    pub(crate) fn pair_friction(&self, entity1: Entity, entity2: Entity) -> f32 {
+        let friction = |entity| {
+            self.frictions
+                .get(entity)
+                .copied()
+                .unwrap_or(self.narrow_phase().default_friction.0)
+        };
+        friction(entity1)
+            .combine(friction(entity2))
+            .dynamic_coefficient
+    }
+
+    pub(crate) fn skip_distant_pair(
+        &self,
+        entity1: Entity,
+        entity2: Entity,
+        position1: Vec3A,
+        position2: Vec3A,
+    ) -> bool {
+        let lod = &self.narrow_phase().lod;
+        if lod.interval <= 1 || self.lod_viewers.is_empty() {
+            return false;
+        }
+
+        let midpoint = Vec3::from((position1 + position2) * 0.5);
+        let max_dist_sq = lod.distance * lod.distance;
+        if self
+            .lod_viewers
+            .iter()
+            .any(|viewer| viewer.translation().distance_squared(midpoint) < max_dist_sq)
+        {
+            return false;
+        }
+
+        // Spread the updates of distant pairs over the interval
+        let offset = entity1.index() ^ entity2.index();
+        lod.tick.wrapping_add(offset) % lod.interval != 0
+    }
+}
+
+/// Controls whether [`SdfSpatialQuery::cast_rays`](crate::SdfSpatialQuery::cast_rays) and
+/// [`SdfSpatialQuery::avoidance`](crate::SdfSpatialQuery::avoidance) split their batches over the
+/// compute task pool.
+///
+/// AABB updates and the narrow phase always follow avian's `parallel` feature.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SdfParallelism {
+    /// Run in parallel only if the `parallel` feature is enabled
+    #[default]
+    FollowPhysics,
+    Serial,
+    Parallel {
+        /// Batches smaller than this run on the calling thread
+        min_batch_size: usize,
+    },
+}
+
+impl SdfParallelism {
+    pub(crate) fn should_parallelize(&self, len: usize) -> bool {
+        match *self {
+            Self::FollowPhysics => cfg!(feature = "parallel") && len >= 64,
+            Self::Serial => false,
+            Self::Parallel { min_batch_size } => len >= min_batch_size,
+        }
+    }
+}
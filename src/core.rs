@@ -0,0 +1,120 @@
+//! Contact generation between shapes and SDFs, without a Bevy app or avian.
+//!
+//! Everything here takes SDFs and their placement directly, so collision logic can be unit tested
+//! or run on a server without building an ECS world. This module is always available, the
+//! `plugin` feature adds the integration with avian on top of it.
+//!
+//! Contact normals point from the shape towards the SDF, and penetrations are positive when the
+//! two overlap.
+
+use bevy::math::{primitives::*, Isometry3d};
+
+pub use crate::{
+    adder::Contact,
+    polyline::Polyline,
+    primitives::{
+        march_edge, march_edge_refined, Ellipsoid, LocalSdf, MarchResult, ScaledIsometry3d,
+        SdfMarchQuality, SdfShell, SphereCluster, TimeOfImpact,
+    },
+};
+use crate::{
+    adder::{ManifoldAdder, Manifolds},
+    primitives::{sdf_sdf_contact, Collider},
+};
+
+fn contacts<C: Collider<S>, S: LocalSdf>(
+    shape: &C,
+    iso: C::Isometry,
+    sdf: &S,
+    sdf_iso: ScaledIsometry3d,
+    pred_dist: f32,
+) -> Vec<Contact> {
+    let mut contacts = Vec::new();
+    shape.get_collisions(
+        iso,
+        sdf,
+        sdf_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        pred_dist,
+    );
+    contacts
+}
+
+/// Contacts between a sphere and an SDF, including those within `pred_dist` of touching.
+pub fn sphere_contacts(
+    sphere: Sphere,
+    iso: Isometry3d,
+    sdf: &impl LocalSdf,
+    sdf_iso: ScaledIsometry3d,
+    pred_dist: f32,
+) -> Vec<Contact> {
+    contacts(&sphere, iso, sdf, sdf_iso, pred_dist)
+}
+
+/// Contacts between a capsule and an SDF, including those within `pred_dist` of touching.
+pub fn capsule_contacts(
+    capsule: Capsule3d,
+    iso: Isometry3d,
+    sdf: &impl LocalSdf,
+    sdf_iso: ScaledIsometry3d,
+    pred_dist: f32,
+) -> Vec<Contact> {
+    contacts(&capsule, iso, sdf, sdf_iso, pred_dist)
+}
+
+/// Contacts between a scaled ellipsoid and an SDF, including those within `pred_dist` of touching.
+pub fn ellipsoid_contacts(
+    ellipsoid: &Ellipsoid,
+    iso: ScaledIsometry3d,
+    sdf: &impl LocalSdf,
+    sdf_iso: ScaledIsometry3d,
+    pred_dist: f32,
+) -> Vec<Contact> {
+    contacts(ellipsoid, iso, sdf, sdf_iso, pred_dist)
+}
+
+/// Contacts between a scaled sphere cluster and an SDF, including those within `pred_dist` of
+/// touching.
+pub fn sphere_cluster_contacts(
+    cluster: &SphereCluster,
+    iso: ScaledIsometry3d,
+    sdf: &impl LocalSdf,
+    sdf_iso: ScaledIsometry3d,
+    pred_dist: f32,
+) -> Vec<Contact> {
+    contacts(cluster, iso, sdf, sdf_iso, pred_dist)
+}
+
+/// Contacts between a scaled polyline and an SDF, including those within `pred_dist` of touching.
+pub fn polyline_contacts(
+    polyline: &Polyline,
+    iso: ScaledIsometry3d,
+    sdf: &impl LocalSdf,
+    sdf_iso: ScaledIsometry3d,
+    pred_dist: f32,
+) -> Vec<Contact> {
+    contacts(polyline, iso, sdf, sdf_iso, pred_dist)
+}
+
+/// The deepest contact between two SDFs, found by descending from a few starting points.
+///
+/// Unlike the other shapes this isn't exact, shallow overlaps between two concave SDFs can be
+/// missed.
+pub fn sdf_contacts(
+    sdf1: &impl LocalSdf,
+    iso1: ScaledIsometry3d,
+    sdf2: &impl LocalSdf,
+    iso2: ScaledIsometry3d,
+    pred_dist: f32,
+) -> Vec<Contact> {
+    let mut contacts = Vec::new();
+    sdf_sdf_contact(
+        sdf1,
+        iso1,
+        sdf2,
+        iso2,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        pred_dist,
+    );
+    contacts
+}
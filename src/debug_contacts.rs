@@ -0,0 +1,86 @@
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use avian3d::prelude::*;
+use bevy::{platform::hash::FixedState, prelude::*};
+
+use crate::Contact;
+
+/// Contacts of the last physics step, collected when the plugin is built
+/// [`with_debug`](crate::SdfCollisionPlugin::with_debug) and drawn with gizmos.
+#[derive(Resource, Debug)]
+pub struct SdfDebugContacts {
+    pub contacts: Vec<SdfDebugContact>,
+    /// Radius of the spheres drawn at contact points
+    pub point_radius: f32,
+    /// Length of the normal arrows per unit of penetration, on top of the point radius
+    pub penetration_scale: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SdfDebugContact {
+    pub entity1: Entity,
+    pub entity2: Entity,
+    pub contact: Contact,
+}
+
+impl Default for SdfDebugContacts {
+    fn default() -> Self {
+        Self {
+            contacts: Vec::new(),
+            point_radius: 0.03,
+            penetration_scale: 10.,
+        }
+    }
+}
+
+pub(crate) fn collect_debug_contacts(
+    collisions: Collisions,
+    mut debug_contacts: ResMut<SdfDebugContacts>,
+) {
+    debug_contacts.contacts.clear();
+    for pair in collisions.iter() {
+        for manifold in pair.manifolds.iter() {
+            debug_contacts
+                .contacts
+                .extend(manifold.points.iter().map(|point| SdfDebugContact {
+                    entity1: pair.collider1,
+                    entity2: pair.collider2,
+                    contact: Contact::new(
+                        point.point,
+                        point.anchor1,
+                        point.anchor2,
+                        manifold.normal,
+                        point.penetration,
+                    ),
+                }));
+        }
+    }
+}
+
+pub(crate) fn draw_debug_contacts(debug_contacts: Res<SdfDebugContacts>, mut gizmos: Gizmos) {
+    for &SdfDebugContact {
+        entity1,
+        entity2,
+        contact,
+    } in debug_contacts.contacts.iter()
+    {
+        let color = pair_color(entity1, entity2);
+        gizmos.sphere(contact.point, debug_contacts.point_radius, color);
+
+        let length = debug_contacts.point_radius
+            + contact.penetration.max(0.) * debug_contacts.penetration_scale;
+        gizmos.arrow(
+            contact.point,
+            contact.point + contact.normal * length,
+            color,
+        );
+    }
+}
+
+// Stable across runs so the same pair keeps its color
+fn pair_color(entity1: Entity, entity2: Entity) -> Color {
+    let mut hasher = FixedState::default().build_hasher();
+    entity1.min(entity2).hash(&mut hasher);
+    entity1.max(entity2).hash(&mut hasher);
+    Color::hsl((hasher.finish() % 360) as f32, 0.8, 0.6)
+}
@@ -0,0 +1,62 @@
+use avian3d::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_prototype_sdf::Sdf3d;
+
+use crate::{SdfCollider, SdfColliderKind};
+
+/// Maps world space onto the local space of an SDF asset used by a collider.
+#[derive(Clone, Copy, Debug)]
+pub struct SdfLocalFrame {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: f32,
+}
+
+impl SdfLocalFrame {
+    pub fn to_local_point(&self, world_point: Vec3) -> Vec3 {
+        self.rotation.inverse() * (world_point - self.translation) / self.scale
+    }
+
+    pub fn to_local_direction(&self, world_direction: Vec3) -> Vec3 {
+        self.rotation.inverse() * world_direction
+    }
+
+    pub fn to_local_length(&self, world_length: f32) -> f32 {
+        world_length / self.scale
+    }
+}
+
+#[derive(SystemParam)]
+pub struct SdfDeformer<'w, 's> {
+    sdfs: ResMut<'w, Assets<Sdf3d>>,
+    colliders: Query<'w, 's, (&'static Position, &'static Rotation, &'static SdfCollider)>,
+}
+
+impl SdfDeformer<'_, '_> {
+    /// Edits the SDF asset used by the collider on `entity`.
+    ///
+    /// Once the asset is processed again, every collider using it is invalidated through
+    /// [`SdfProcessed`](bevy_prototype_sdf::SdfProcessed), just like a hot reload.
+    /// Returns false if the entity has no loaded SDF asset collider.
+    pub fn edit(&mut self, entity: Entity, edit: impl FnOnce(&mut Sdf3d, SdfLocalFrame)) -> bool {
+        let Ok((pos, rot, collider)) = self.colliders.get(entity) else {
+            return false;
+        };
+        let SdfColliderKind::Arbitrary(handle) = collider.collider() else {
+            return false;
+        };
+        let Some(sdf) = self.sdfs.get_mut(handle.id()) else {
+            return false;
+        };
+
+        edit(
+            sdf,
+            SdfLocalFrame {
+                translation: pos.0,
+                rotation: rot.0,
+                scale: collider.scale,
+            },
+        );
+        true
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+
+use crate::{primitives::LocalSdf, SdfColliderKind, SdfMarchQuality};
+
+const KINDS: usize = 6;
+
+/// Counts the work done by SDF collision detection, reset every frame.
+///
+/// The previous frame is available through [`last_frame`](Self::last_frame), and is also
+/// reported to the [`DiagnosticsStore`](bevy::diagnostic::DiagnosticsStore) if the plugin is built
+/// [`with_debug`](crate::SdfCollisionPlugin::with_debug).
+#[derive(Resource, Debug, Default)]
+pub struct SdfCollisionDiagnostics {
+    distance_evaluations: AtomicU32,
+    gradient_evaluations: AtomicU32,
+    march_iterations: AtomicU32,
+    contacts: AtomicU32,
+    pairs: [[AtomicU32; KINDS]; KINDS],
+    last_frame: SdfCollisionStats,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SdfCollisionStats {
+    pub distance_evaluations: u32,
+    pub gradient_evaluations: u32,
+    pub march_iterations: u32,
+    pub contacts: u32,
+    pairs: [[u32; KINDS]; KINDS],
+}
+
+impl SdfCollisionStats {
+    /// Number of collider pairs of these kinds that went through the narrow phase
+    pub fn pairs(&self, kind1: &SdfColliderKind, kind2: &SdfColliderKind) -> u32 {
+        let (a, b) = pair_index(kind1, kind2);
+        self.pairs[a][b]
+    }
+
+    pub fn total_pairs(&self) -> u32 {
+        self.pairs.iter().flatten().sum()
+    }
+}
+
+impl SdfCollisionDiagnostics {
+    pub const DISTANCE_EVALUATIONS: DiagnosticPath =
+        DiagnosticPath::const_new("sdf_peck/distance_evaluations");
+    pub const GRADIENT_EVALUATIONS: DiagnosticPath =
+        DiagnosticPath::const_new("sdf_peck/gradient_evaluations");
+    pub const MARCH_ITERATIONS: DiagnosticPath =
+        DiagnosticPath::const_new("sdf_peck/march_iterations");
+    pub const CONTACTS: DiagnosticPath = DiagnosticPath::const_new("sdf_peck/contacts");
+    pub const PAIRS: DiagnosticPath = DiagnosticPath::const_new("sdf_peck/pairs");
+
+    pub fn last_frame(&self) -> &SdfCollisionStats {
+        &self.last_frame
+    }
+
+    pub(crate) fn record_pair(
+        &self,
+        kind1: &SdfColliderKind,
+        kind2: &SdfColliderKind,
+        evaluations: SdfEvaluations,
+        contacts: usize,
+    ) {
+        let (a, b) = pair_index(kind1, kind2);
+        self.pairs[a][b].fetch_add(1, Ordering::Relaxed);
+        self.contacts.fetch_add(contacts as u32, Ordering::Relaxed);
+        if evaluations == SdfEvaluations::default() {
+            return;
+        }
+        self.distance_evaluations
+            .fetch_add(evaluations.distance, Ordering::Relaxed);
+        self.gradient_evaluations
+            .fetch_add(evaluations.gradient, Ordering::Relaxed);
+        self.march_iterations
+            .fetch_add(evaluations.march_iterations, Ordering::Relaxed);
+    }
+
+    fn take(&mut self) -> SdfCollisionStats {
+        let take = |counter: &mut AtomicU32| std::mem::take(counter.get_mut());
+        SdfCollisionStats {
+            distance_evaluations: take(&mut self.distance_evaluations),
+            gradient_evaluations: take(&mut self.gradient_evaluations),
+            march_iterations: take(&mut self.march_iterations),
+            contacts: take(&mut self.contacts),
+            pairs: self.pairs.each_mut().map(|row| row.each_mut().map(take)),
+        }
+    }
+}
+
+fn kind_index(kind: &SdfColliderKind) -> usize {
+    match kind {
+        SdfColliderKind::Sphere(_) => 0,
+        SdfColliderKind::Capsule(_) => 1,
+        SdfColliderKind::Ellipsoid(_) => 2,
+        SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_) => 3,
+        SdfColliderKind::SphereCluster(_) => 4,
+        SdfColliderKind::Polyline(_) => 5,
+        // Swept spheres and segments reach the narrow phase as capsules
+        SdfColliderKind::SweptSphere(_) | SdfColliderKind::Segment(_) => 1,
+    }
+}
+
+// Pairs are unordered, so only the upper half of the table is used
+fn pair_index(kind1: &SdfColliderKind, kind2: &SdfColliderKind) -> (usize, usize) {
+    let (a, b) = (kind_index(kind1), kind_index(kind2));
+    (a.min(b), a.max(b))
+}
+
+pub(crate) fn register_diagnostics(app: &mut App) {
+    for path in [
+        SdfCollisionDiagnostics::DISTANCE_EVALUATIONS,
+        SdfCollisionDiagnostics::GRADIENT_EVALUATIONS,
+        SdfCollisionDiagnostics::MARCH_ITERATIONS,
+        SdfCollisionDiagnostics::CONTACTS,
+        SdfCollisionDiagnostics::PAIRS,
+    ] {
+        app.register_diagnostic(Diagnostic::new(path));
+    }
+}
+
+pub(crate) fn flush_diagnostics(
+    mut diagnostics: Diagnostics,
+    mut collision_diagnostics: ResMut<SdfCollisionDiagnostics>,
+) {
+    let frame = collision_diagnostics.take();
+    collision_diagnostics.last_frame = frame;
+
+    diagnostics.add_measurement(&SdfCollisionDiagnostics::DISTANCE_EVALUATIONS, || {
+        frame.distance_evaluations as f64
+    });
+    diagnostics.add_measurement(&SdfCollisionDiagnostics::GRADIENT_EVALUATIONS, || {
+        frame.gradient_evaluations as f64
+    });
+    diagnostics.add_measurement(&SdfCollisionDiagnostics::MARCH_ITERATIONS, || {
+        frame.march_iterations as f64
+    });
+    diagnostics.add_measurement(&SdfCollisionDiagnostics::CONTACTS, || frame.contacts as f64);
+    diagnostics.add_measurement(&SdfCollisionDiagnostics::PAIRS, || {
+        frame.total_pairs() as f64
+    });
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct SdfEvaluations {
+    pub distance: u32,
+    pub gradient: u32,
+    pub march_iterations: u32,
+}
+
+/// Counts evaluations of the wrapped SDF for a single narrow phase pair.
+pub(crate) struct CountingSdf<S> {
+    sdf: S,
+    distance: Cell<u32>,
+    gradient: Cell<u32>,
+    march_iterations: Cell<u32>,
+}
+
+impl<S: LocalSdf> CountingSdf<S> {
+    pub fn new(sdf: S) -> Self {
+        Self {
+            sdf,
+            distance: Cell::new(0),
+            gradient: Cell::new(0),
+            march_iterations: Cell::new(0),
+        }
+    }
+
+    pub fn evaluations(&self) -> SdfEvaluations {
+        SdfEvaluations {
+            distance: self.distance.get(),
+            gradient: self.gradient.get(),
+            march_iterations: self.march_iterations.get(),
+        }
+    }
+}
+
+impl<S: LocalSdf> LocalSdf for CountingSdf<S> {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        self.distance.set(self.distance.get() + 1);
+        self.sdf.distance(local_point)
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        self.gradient.set(self.gradient.get() + 1);
+        self.sdf.gradient(local_point)
+    }
+
+    fn record_march_iterations(&self, iterations: u32) {
+        self.march_iterations
+            .set(self.march_iterations.get() + iterations);
+    }
+
+    fn march_quality(&self) -> SdfMarchQuality {
+        self.sdf.march_quality()
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::Contact;
+
+/// Inspects, modifies or drops the contacts between SDF colliders as they're generated, before
+/// they become avian's contact manifolds.
+///
+/// Filters are added with [`SdfCollisionPlugin::with_contact_filter`](crate::SdfCollisionPlugin),
+/// and run in the order they were added. Closures taking the same arguments are filters too.
+pub trait SdfContactFilter: Send + Sync + 'static {
+    /// Returns false to drop the contact. The normal points from `entity1` to `entity2`.
+    fn filter(&self, entity1: Entity, entity2: Entity, contact: &mut Contact) -> bool;
+}
+
+impl<F> SdfContactFilter for F
+where
+    F: Fn(Entity, Entity, &mut Contact) -> bool + Send + Sync + 'static,
+{
+    fn filter(&self, entity1: Entity, entity2: Entity, contact: &mut Contact) -> bool {
+        self(entity1, entity2, contact)
+    }
+}
+
+#[derive(Resource, Default, Clone)]
+pub(crate) struct SdfContactFilters(pub Vec<Arc<dyn SdfContactFilter>>);
+
+impl SdfContactFilters {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Runs the contact through every filter, stopping at the first that drops it.
+    pub fn apply(&self, entity1: Entity, entity2: Entity, contact: &mut Contact) -> bool {
+        self.0
+            .iter()
+            .all(|filter| filter.filter(entity1, entity2, contact))
+    }
+}
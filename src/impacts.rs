@@ -0,0 +1,114 @@
+use avian3d::prelude::*;
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
+use bevy_prototype_sdf::{dim3::Dim3, ExecutableSdfs};
+
+use crate::{SdfCollider, SdfSurfaceTags};
+
+/// Triggered when two colliders start touching, with what impact sounds and effects need.
+#[derive(Event, Debug, Clone)]
+pub struct SdfImpactEvent {
+    pub entity1: Entity,
+    pub entity2: Entity,
+    /// The deepest contact point of the pair in world space
+    pub point: Vec3,
+    /// Contact normal in world space, pointing from `entity1` to `entity2`
+    pub normal: Vec3,
+    /// Speed at which the colliders approached each other along the normal before the impact
+    pub speed: f32,
+    /// Tag of the surface that was hit, if either collider has [`SdfSurfaceTags`] there
+    pub tag: Option<String>,
+}
+
+/// Velocities of rigid bodies at the start of the physics step, before contacts are solved.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct ImpactVelocities(EntityHashMap<BodyVelocity>);
+
+#[derive(Debug, Clone, Copy)]
+struct BodyVelocity {
+    linear: Vec3,
+    angular: Vec3,
+    center_of_mass: Vec3,
+}
+
+impl BodyVelocity {
+    fn at(&self, point: Vec3) -> Vec3 {
+        self.linear + self.angular.cross(point - self.center_of_mass)
+    }
+}
+
+pub(crate) fn record_impact_velocities(
+    mut velocities: ResMut<ImpactVelocities>,
+    bodies: Query<(
+        Entity,
+        &Position,
+        &Rotation,
+        &LinearVelocity,
+        &AngularVelocity,
+        &ComputedCenterOfMass,
+    )>,
+) {
+    velocities.0.clear();
+    velocities.0.extend(
+        bodies
+            .iter()
+            .map(|(entity, pos, rot, lin_vel, ang_vel, com)| {
+                let velocity = BodyVelocity {
+                    linear: lin_vel.0,
+                    angular: ang_vel.0,
+                    center_of_mass: pos.0 + rot.0 * com.0,
+                };
+                (entity, velocity)
+            }),
+    );
+}
+
+pub(crate) fn trigger_impact_events(
+    mut commands: Commands,
+    collisions: Collisions,
+    velocities: Res<ImpactVelocities>,
+    colliders: Query<(
+        &Position,
+        &Rotation,
+        &SdfCollider,
+        Option<&ColliderOf>,
+        Option<&SdfSurfaceTags>,
+    )>,
+    sdfs: ExecutableSdfs<Dim3>,
+) {
+    for pair in collisions.iter().filter(|pair| pair.collision_started()) {
+        let deepest = pair
+            .manifolds
+            .iter()
+            .flat_map(|manifold| manifold.points.iter().map(move |point| (manifold, point)))
+            .max_by(|a, b| a.1.penetration.total_cmp(&b.1.penetration));
+        let Some((manifold, point)) = deepest else {
+            continue;
+        };
+
+        let velocity = |entity: Entity| {
+            colliders
+                .get(entity)
+                .ok()
+                .and_then(|(.., collider_of, _)| velocities.0.get(&collider_of?.body))
+                .map_or(Vec3::ZERO, |velocity| velocity.at(point.point))
+        };
+        let speed = (velocity(pair.collider1) - velocity(pair.collider2)).dot(manifold.normal);
+
+        let tag = [pair.collider1, pair.collider2]
+            .into_iter()
+            .find_map(|entity| {
+                let (pos, rot, collider, _, tags) = colliders.get(entity).ok()?;
+                tags?.tag_at(pos, rot, collider, point.point, &sdfs)
+            })
+            .map(str::to_owned);
+
+        commands.trigger(SdfImpactEvent {
+            entity1: pair.collider1,
+            entity2: pair.collider2,
+            point: point.point,
+            normal: manifold.normal,
+            speed,
+            tag,
+        });
+    }
+}
@@ -0,0 +1,94 @@
+use avian3d::prelude::*;
+use bevy::{ecs::entity::EntityHashSet, prelude::*};
+
+use crate::{primitives::LocalSdf, SdfCollider, SdfContext};
+
+/// Marks an [`SdfCollider`] whose inside is a volume, like water or a room, that reports
+/// [`SdfInteriorEnter`] and [`SdfInteriorExit`] for every [`SdfInteriorTracker`] crossing its
+/// surface.
+///
+/// Unlike sensor contacts, only the center of the tracked entity counts, so nothing is reported
+/// until it's actually inside.
+#[derive(Component, Debug, Default, Clone)]
+pub struct SdfInteriorVolume {
+    inside: EntityHashSet,
+}
+
+impl SdfInteriorVolume {
+    /// Whether the center of `entity` was inside the volume after the last physics step.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.inside.contains(&entity)
+    }
+}
+
+/// Tracks whether the center of this entity is inside any [`SdfInteriorVolume`].
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct SdfInteriorTracker;
+
+/// Triggered when the center of an [`SdfInteriorTracker`] moves inside an [`SdfInteriorVolume`].
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdfInteriorEnter {
+    pub volume: Entity,
+    pub entity: Entity,
+}
+
+/// Triggered when the center of an [`SdfInteriorTracker`] leaves an [`SdfInteriorVolume`], or
+/// when the tracker is removed while inside it.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdfInteriorExit {
+    pub volume: Entity,
+    pub entity: Entity,
+}
+
+pub(crate) fn track_interiors(
+    mut volumes: Query<(
+        Entity,
+        &mut SdfInteriorVolume,
+        &Position,
+        &Rotation,
+        &SdfCollider,
+    )>,
+    trackers: Query<(Entity, &GlobalTransform, Option<&Position>), With<SdfInteriorTracker>>,
+    context: SdfContext,
+    mut commands: Commands,
+) {
+    for (volume, mut interior, pos, rot, collider) in volumes.iter_mut() {
+        // Keep the previous state while the SDF isn't available
+        let Some(sdf) = collider.local_sdf(&context) else {
+            continue;
+        };
+        let inv_rot = rot.0.inverse();
+
+        let exited = interior
+            .inside
+            .iter()
+            .copied()
+            .filter(|&entity| !trackers.contains(entity))
+            .collect::<Vec<_>>();
+        for entity in exited {
+            interior.inside.remove(&entity);
+            commands.trigger(SdfInteriorExit { volume, entity });
+        }
+
+        for (entity, transform, tracked_pos) in trackers.iter() {
+            if entity == volume {
+                continue;
+            }
+            // Bodies have moved during the step, but their transforms aren't synced yet
+            let center = tracked_pos.map_or(transform.translation(), |p| p.0);
+            let local_center = inv_rot * (center - pos.0) / collider.scale;
+            let inside = sdf.distance(local_center) < 0.;
+            if inside == interior.inside.contains(&entity) {
+                continue;
+            }
+
+            if inside {
+                interior.inside.insert(entity);
+                commands.trigger(SdfInteriorEnter { volume, entity });
+            } else {
+                interior.inside.remove(&entity);
+                commands.trigger(SdfInteriorExit { volume, entity });
+            }
+        }
+    }
+}
@@ -1,64 +1,210 @@
-mod collider;
-use std::marker::PhantomData;
+//! SDF colliders for avian.
+//!
+//! The contact generation itself lives in [`core`], which doesn't need a Bevy app or avian. The
+//! default `plugin` feature adds [`SdfCollider`] and the plugins that integrate it with avian.
 
-pub use collider::{SdfCollider, SdfColliderKind};
+pub mod core;
+
+mod adder;
+pub use adder::Contact;
 
 mod primitives;
+pub use primitives::{
+    march_edge, march_edge_refined, Ellipsoid, LocalSdf, MarchResult, SdfGradient, SdfMarchQuality,
+    SdfShell, SphereCluster, TimeOfImpact,
+};
 
-mod adder;
+mod polyline;
+pub use polyline::Polyline;
+
+mod scratch;
+
+#[doc(hidden)]
+pub mod bench;
+
+#[cfg(feature = "plugin")]
+mod plugin;
+#[cfg(feature = "plugin")]
+pub use plugin::{SdfCollisionPlugin, SdfQueryPlugin};
+
+#[cfg(feature = "plugin")]
+mod collider;
+#[cfg(feature = "plugin")]
+pub use collider::{SdfCollider, SdfColliderKind};
 
+#[cfg(feature = "plugin")]
+mod backend;
+#[cfg(feature = "plugin")]
+pub use backend::{CustomSdf, SdfBackend};
+
+#[cfg(feature = "plugin")]
+mod compliance;
+#[cfg(feature = "plugin")]
+pub use compliance::SdfContactCompliance;
+
+#[cfg(feature = "plugin")]
+mod contact_cache;
+
+#[cfg(feature = "plugin")]
+mod context;
+#[cfg(feature = "plugin")]
+pub use context::{
+    ContactStabilization, NarrowPhaseLod, SdfColliderLod, SdfContext, SdfLodViewer, SdfParallelism,
+    SdfPredictionDistance, SdfQueryConfig, SdfQueryOnly, StartPenetrating, UnsupportedPairs,
+    WorldOffset,
+};
+
+#[cfg(feature = "plugin")]
+mod anchors;
+#[cfg(feature = "plugin")]
+pub use anchors::SdfSurfaceAnchor;
+
+#[cfg(feature = "plugin")]
 mod avian;
 
+#[cfg(feature = "plugin")]
+mod diagnostics;
+#[cfg(feature = "plugin")]
+pub use diagnostics::{SdfCollisionDiagnostics, SdfCollisionStats};
+
+#[cfg(feature = "plugin")]
+mod filters;
+#[cfg(feature = "plugin")]
+pub use filters::SdfContactFilter;
+
+#[cfg(feature = "plugin")]
+mod impacts;
+#[cfg(feature = "plugin")]
+pub use impacts::SdfImpactEvent;
+
+#[cfg(feature = "plugin")]
+mod interior;
+#[cfg(feature = "plugin")]
+pub use interior::{SdfInteriorEnter, SdfInteriorExit, SdfInteriorTracker, SdfInteriorVolume};
+
+#[cfg(feature = "plugin")]
+mod one_way;
+#[cfg(feature = "plugin")]
+pub use one_way::OneWaySurface;
+
+#[cfg(feature = "plugin")]
+mod slope;
+#[cfg(feature = "plugin")]
+pub use slope::SlopeFriction;
+
+#[cfg(feature = "plugin")]
+mod pending;
+#[cfg(feature = "plugin")]
+pub use pending::{MissingSdf, MissingSdfPolicy, PendingSdfCollider};
+
+#[cfg(feature = "plugin")]
+mod motion;
+
+#[cfg(feature = "plugin")]
+mod mass;
+#[cfg(feature = "plugin")]
+pub use mass::SdfMassProperties;
+
+#[cfg(feature = "plugin")]
+mod navigation;
+#[cfg(feature = "plugin")]
+pub use navigation::{WalkableHeightfield, WalkableSettings, WalkableSpan};
+
+#[cfg(feature = "plugin")]
+mod params;
+#[cfg(feature = "plugin")]
+pub use params::SdfParams;
+
+#[cfg(feature = "plugin")]
+mod patches;
+#[cfg(feature = "plugin")]
+pub use patches::BakeSurfacePatches;
+
+#[cfg(feature = "plugin")]
+mod reload;
+#[cfg(feature = "plugin")]
+pub use reload::SdfRecomputeBudget;
+
+#[cfg(feature = "plugin")]
+mod scene;
+#[cfg(feature = "plugin")]
+pub use scene::{SdfAssetPath, SdfColliderConstructor, SdfColliderConstructorHierarchy};
+
+#[cfg(feature = "plugin")]
 mod spatial_query;
-pub use spatial_query::ColliderShape;
+#[cfg(feature = "plugin")]
+pub use spatial_query::{ColliderShape, RayHitDetails};
 
-use avian3d::prelude::*;
-use bevy::{
-    ecs::{intern::Interned, schedule::ScheduleLabel, system::SystemParamItem},
-    prelude::*,
+#[cfg(feature = "plugin")]
+mod queries;
+#[cfg(feature = "plugin")]
+pub use queries::{
+    AngularCastHit, BudgetedCastHit, RayPassHit, SceneDistance, SdfEscape, SdfSpatialQuery,
+    SdfWorldQuery, ShapeCrossing, SphereCastHit, SurfaceProjection, SurfaceSample,
 };
-use bevy_prototype_sdf::SdfProcessed;
-
-pub struct SdfCollisionPlugin<H: CollisionHooks = ()> {
-    schedule: Interned<dyn ScheduleLabel>,
-    phantom: PhantomData<H>,
-}
-
-impl<H: CollisionHooks> Default for SdfCollisionPlugin<H> {
-    fn default() -> Self {
-        Self {
-            schedule: FixedPostUpdate.intern(),
-            phantom: PhantomData,
-        }
-    }
-}
-
-impl<H: CollisionHooks + 'static> Plugin for SdfCollisionPlugin<H>
-where
-    for<'w, 's> SystemParamItem<'w, 's, H>: CollisionHooks,
-{
-    fn build(&self, app: &mut App) {
-        app.register_type::<SdfCollider>()
-            .add_plugins((
-                ColliderBackendPlugin::<SdfCollider>::new(self.schedule),
-                SpatialQueryPlugin::<SdfCollider>::default(),
-                NarrowPhasePlugin::<SdfCollider, H>::default(),
-            ))
-            .add_observer(invalidate_changed_handle_colliders);
-    }
-}
-
-fn invalidate_changed_handle_colliders(
-    trigger: On<SdfProcessed>,
-    mut query: Query<&mut SdfCollider>,
-) {
-    let SdfProcessed(id) = trigger.event();
-    let id = AssetId::from(*id);
-    for mut col in query.iter_mut() {
-        if let SdfColliderKind::Arbitrary(handle) = col.collider() {
-            if handle.id() == id {
-                col.set_changed();
-            }
-        }
-    }
-}
+
+#[cfg(feature = "plugin")]
+mod query_grid;
+#[cfg(feature = "plugin")]
+pub use query_grid::SdfQueryGrid;
+
+#[cfg(feature = "plugin")]
+mod buoyancy;
+#[cfg(feature = "plugin")]
+pub use buoyancy::{BuoyancyPlugin, FluidVolume};
+
+#[cfg(feature = "plugin")]
+mod ccd;
+
+#[cfg(feature = "plugin")]
+mod casters;
+#[cfg(feature = "plugin")]
+pub use casters::{SdfRayCaster, SdfRayHits, SdfShapeCaster, SdfShapeHits};
+
+#[cfg(feature = "plugin")]
+mod deform;
+#[cfg(feature = "plugin")]
+pub use deform::{SdfDeformer, SdfLocalFrame};
+
+#[cfg(feature = "plugin")]
+mod rolling;
+#[cfg(feature = "plugin")]
+pub use rolling::SdfRollingResistance;
+
+#[cfg(feature = "plugin")]
+mod swept;
+#[cfg(feature = "plugin")]
+pub use swept::SweptSphere;
+
+#[cfg(feature = "plugin")]
+mod tags;
+#[cfg(feature = "plugin")]
+pub use tags::{SdfSurfaceTagContact, SdfSurfaceTags};
+
+#[cfg(feature = "plugin")]
+mod local_contacts;
+#[cfg(feature = "plugin")]
+pub use local_contacts::{SdfLocalContact, SdfLocalContacts};
+
+#[cfg(feature = "mesh")]
+mod meshing;
+
+#[cfg(feature = "mesh")]
+mod mesh_colliders;
+#[cfg(feature = "mesh")]
+pub use mesh_colliders::SdfColliderFrom;
+
+#[cfg(feature = "parry")]
+mod parry;
+#[cfg(feature = "parry")]
+pub use parry::{ColliderRepresentation, SdfInterop, SdfInteropProxy, UnsupportedShape};
+
+#[cfg(feature = "debug-gizmos")]
+mod debug_contacts;
+#[cfg(feature = "debug-gizmos")]
+pub use debug_contacts::{SdfDebugContact, SdfDebugContacts};
+
+#[cfg(feature = "march")]
+mod march;
+#[cfg(feature = "march")]
+pub use march::{SdfObject, SdfObjectPlugin};
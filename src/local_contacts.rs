@@ -0,0 +1,63 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{
+    primitives::{mean_curvature, LocalSdf},
+    SdfCollider, SdfContext,
+};
+
+/// Add to an entity with an SDF asset or custom collider to record its contacts in the SDF's local
+/// space, with the collider's shell, inversion and parameters applied like in the narrow phase.
+#[derive(Component, Debug, Default)]
+pub struct SdfLocalContacts(pub Vec<SdfLocalContact>);
+
+#[derive(Clone, Copy, Debug)]
+pub struct SdfLocalContact {
+    pub other: Entity,
+    pub local_point: Vec3,
+    pub local_gradient: Vec3,
+    /// Sum of the principal curvatures of the surface at the contact, zero on flat surfaces
+    pub curvature: f32,
+    pub penetration: f32,
+}
+
+pub(crate) fn record_local_contacts(
+    collisions: Collisions,
+    mut query: Query<(
+        Entity,
+        &Position,
+        &Rotation,
+        &SdfCollider,
+        &mut SdfLocalContacts,
+    )>,
+    context: SdfContext,
+) {
+    for (entity, pos, rot, collider, mut records) in query.iter_mut() {
+        records.0.clear();
+        if !collider.collider().is_sdf() {
+            continue;
+        }
+        let Some(sdf) = collider.full_sdf(&context) else {
+            continue;
+        };
+
+        let inv_rot = rot.0.inverse();
+        for pair in collisions.collisions_with(entity) {
+            let other = if pair.collider1 == entity {
+                pair.collider2
+            } else {
+                pair.collider1
+            };
+            for point in pair.manifolds.iter().flat_map(|m| m.points.iter()) {
+                let local_point = inv_rot * (point.point - pos.0) / collider.scale;
+                records.0.push(SdfLocalContact {
+                    other,
+                    local_point,
+                    local_gradient: sdf.gradient(local_point),
+                    curvature: mean_curvature(&sdf, local_point) / collider.scale,
+                    penetration: point.penetration,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use bevy_march::{MarcherMaterial, RenderedSdf};
+use bevy_prototype_sdf::Sdf3d;
+use std::marker::PhantomData;
+
+use crate::{SdfCollider, SdfColliderKind};
+
+/// Keeps the [`RenderedSdf`] and [`SdfCollider`] of [`SdfObject`]s in sync.
+pub struct SdfObjectPlugin<M: MarcherMaterial>(PhantomData<M>);
+
+impl<M: MarcherMaterial> Default for SdfObjectPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: MarcherMaterial> Plugin for SdfObjectPlugin<M> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (sync_sdf_objects::<M>, uniform_sdf_object_scale::<M>)
+                .before(TransformSystems::Propagate),
+        );
+    }
+}
+
+/// An SDF that is both rendered with `bevy_march` and used as a collider.
+///
+/// Changing the handle updates both, and the scale is kept uniform so the rendered surface
+/// matches the collider, which only supports uniform scaling.
+#[derive(Component, Debug, Clone)]
+pub struct SdfObject<M: MarcherMaterial> {
+    pub sdf: Handle<Sdf3d>,
+    pub material: Handle<M>,
+}
+
+impl<M: MarcherMaterial> SdfObject<M> {
+    pub fn new(sdf: Handle<Sdf3d>, material: Handle<M>) -> Self {
+        Self { sdf, material }
+    }
+}
+
+fn sync_sdf_objects<M: MarcherMaterial>(
+    mut commands: Commands,
+    mut query: Query<(Entity, &SdfObject<M>, Option<&mut SdfCollider>), Changed<SdfObject<M>>>,
+) {
+    for (entity, object, collider) in query.iter_mut() {
+        commands.entity(entity).insert(RenderedSdf {
+            sdf: object.sdf.clone(),
+            material: object.material.clone(),
+        });
+
+        match collider {
+            Some(mut collider) => {
+                let same_sdf = matches!(
+                    collider.collider(),
+                    SdfColliderKind::Arbitrary(handle) if *handle == object.sdf
+                );
+                if !same_sdf {
+                    collider.collider = SdfColliderKind::Arbitrary(object.sdf.clone());
+                }
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(SdfCollider::sdf(object.sdf.clone()));
+            }
+        }
+    }
+}
+
+fn uniform_sdf_object_scale<M: MarcherMaterial>(
+    mut query: Query<&mut Transform, (With<SdfObject<M>>, Changed<Transform>)>,
+) {
+    for mut transform in query.iter_mut() {
+        let scale = transform.scale.abs().min_element();
+        if transform.scale != Vec3::splat(scale) {
+            transform.scale = Vec3::splat(scale);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+
+use crate::SdfCollider;
+
+/// Precomputed mass properties for the SDF collider on the same entity, used instead of
+/// estimating them from its shape.
+///
+/// The values describe the unscaled shape, so `ColliderDensity` and the scale of the collider still
+/// apply. SDF asset colliders can't be estimated and weigh as much as a unit cube without this.
+/// Avian's `Mass`, `AngularInertia` and `CenterOfMass` components override the result as for any
+/// other collider.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Debug)]
+pub struct SdfMassProperties {
+    pub volume: f32,
+    /// Principal angular inertia per unit of mass, along the local axes of the collider
+    pub unit_principal_angular_inertia: Vec3,
+    pub center_of_mass: Vec3,
+}
+
+pub(crate) fn apply_sdf_mass_properties(
+    changed: Query<Entity, Changed<SdfMassProperties>>,
+    mut removed: RemovedComponents<SdfMassProperties>,
+    mut colliders: Query<(Option<&SdfMassProperties>, &mut SdfCollider)>,
+) {
+    for entity in changed.iter().chain(removed.read()) {
+        let Ok((properties, mut collider)) = colliders.get_mut(entity) else {
+            continue;
+        };
+        // Avian recomputes the mass of the changed collider
+        collider.mass_properties = properties.copied();
+    }
+}
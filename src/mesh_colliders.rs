@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use bevy_mesh::{Mesh, Mesh3d, VertexAttributeValues};
+
+use crate::{Ellipsoid, SdfCollider, SdfColliderKind};
+
+/// Keeps the [`SdfCollider`] of an entity fitted to its [`Mesh3d`], so sizes are only defined
+/// once for rendering and collision.
+///
+/// The collider is inserted or replaced when either component is inserted, when this component
+/// changes, and when the mesh asset is loaded or modified. Shapes are centered on the entity like
+/// the meshes of Bevy's primitives, and enclose the whole mesh.
+#[derive(Component, Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component, Default, Debug)]
+#[type_path(sdf_peck)]
+pub enum SdfColliderFrom {
+    #[default]
+    Sphere,
+    /// A capsule along the Y axis, as wide as the wider of the X and Z extents
+    Capsule,
+    Ellipsoid,
+}
+
+impl SdfColliderFrom {
+    /// The shape fitted to a mesh with these half extents around its origin.
+    pub fn fit(&self, half_extents: Vec3) -> SdfColliderKind {
+        match self {
+            Self::Sphere => Sphere::new(half_extents.max_element()).into(),
+            Self::Capsule => {
+                let radius = half_extents.x.max(half_extents.z);
+                let length = (half_extents.y - radius).max(0.) * 2.;
+                Capsule3d::new(radius, length).into()
+            }
+            Self::Ellipsoid => SdfColliderKind::Ellipsoid(Ellipsoid::new(half_extents)),
+        }
+    }
+}
+
+/// Largest distance of the vertices of a mesh from its origin along each axis.
+fn mesh_half_extents(mesh: &Mesh) -> Option<Vec3> {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+    positions
+        .iter()
+        .map(|&position| Vec3::from(position).abs())
+        .reduce(Vec3::max)
+}
+
+fn fit_collider(
+    entity: Entity,
+    from: &SdfColliderFrom,
+    mesh: &Mesh3d,
+    collider: Option<Mut<SdfCollider>>,
+    meshes: &Assets<Mesh>,
+    commands: &mut Commands,
+) {
+    // Meshes that are still loading are fitted once their asset is added
+    let Some(half_extents) = meshes.get(mesh.id()).and_then(mesh_half_extents) else {
+        return;
+    };
+    let shape = from.fit(half_extents);
+    match collider {
+        Some(mut collider) => collider.set_shape(shape),
+        None => {
+            let mut collider = SdfCollider::default();
+            collider.set_shape(shape);
+            commands.entity(entity).insert(collider);
+        }
+    }
+}
+
+pub(crate) fn fit_inserted_colliders(
+    trigger: On<Insert, (SdfColliderFrom, Mesh3d)>,
+    mut query: Query<(&SdfColliderFrom, &Mesh3d, Option<&mut SdfCollider>)>,
+    meshes: Res<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    let Ok((from, mesh, collider)) = query.get_mut(trigger.entity) else {
+        return;
+    };
+    fit_collider(trigger.entity, from, mesh, collider, &meshes, &mut commands);
+}
+
+pub(crate) fn refit_changed_colliders(
+    mut mesh_events: MessageReader<AssetEvent<Mesh>>,
+    mut query: Query<(
+        Entity,
+        Ref<SdfColliderFrom>,
+        &Mesh3d,
+        Option<&mut SdfCollider>,
+    )>,
+    meshes: Res<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    let changed_meshes: Vec<AssetId<Mesh>> = mesh_events
+        .read()
+        .filter_map(|event| match *event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => Some(id),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, from, mesh, collider) in query.iter_mut() {
+        // Newly inserted components were already fitted by the observer
+        let from_changed = from.is_changed() && !from.is_added();
+        if from_changed || changed_meshes.contains(&mesh.id()) {
+            fit_collider(entity, &from, mesh, collider, &meshes, &mut commands);
+        }
+    }
+}
+
+#[test]
+fn test_fit_to_primitive_meshes() {
+    use bevy_mesh::Meshable;
+
+    let sphere = Sphere::new(0.5).mesh().build();
+    let half_extents = mesh_half_extents(&sphere).unwrap();
+    let SdfColliderKind::Sphere(fitted) = SdfColliderFrom::Sphere.fit(half_extents) else {
+        panic!("expected a sphere");
+    };
+    assert!((fitted.radius - 0.5).abs() < 1e-3, "{fitted:?}");
+
+    let capsule = Capsule3d::new(0.3, 1.).mesh().build();
+    let half_extents = mesh_half_extents(&capsule).unwrap();
+    let SdfColliderKind::Capsule(fitted) = SdfColliderFrom::Capsule.fit(half_extents) else {
+        panic!("expected a capsule");
+    };
+    assert!((fitted.radius - 0.3).abs() < 1e-3, "{fitted:?}");
+    assert!((fitted.half_length - 0.5).abs() < 1e-3, "{fitted:?}");
+
+    let SdfColliderKind::Ellipsoid(fitted) = SdfColliderFrom::Ellipsoid.fit(half_extents) else {
+        panic!("expected an ellipsoid");
+    };
+    assert!(fitted.half_size.abs_diff_eq(Vec3::new(0.3, 0.8, 0.3), 1e-3));
+}
@@ -0,0 +1,176 @@
+use bevy::{asset::RenderAssetUsages, math::bounding::Aabb3d, prelude::*};
+use bevy_mesh::{Indices, Mesh, PrimitiveTopology};
+
+use crate::{primitives::LocalSdf, SdfCollider, SdfContext};
+
+impl SdfCollider {
+    /// Extracts the surface of this collider as a mesh, for debug rendering or exporting the
+    /// collider to other tools.
+    ///
+    /// The mesh is in the unscaled local space of the collider, so it lines up when added to the
+    /// same entity. `resolution` is the number of cells along the longest side of the collider.
+    /// Returns `None` if the collider's SDF isn't loaded.
+    pub fn to_mesh(&self, context: &SdfContext, resolution: u32) -> Option<Mesh> {
+        let sdf = self.local_sdf(context)?;
+        let aabb = self.world_aabb(Isometry3d::IDENTITY, context);
+        if !aabb.min.is_finite() || !aabb.max.is_finite() {
+            return None;
+        }
+        let bounds = Aabb3d {
+            min: (aabb.min / self.scale).into(),
+            max: (aabb.max / self.scale).into(),
+        };
+
+        let surface = surface_nets(&sdf, bounds, resolution.max(1));
+        Some(
+            Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::default(),
+            )
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, surface.positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, surface.normals)
+            .with_inserted_indices(Indices::U32(surface.indices)),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct SurfaceMesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub indices: Vec<u32>,
+}
+
+/// Meshes the zero surface of an SDF within `bounds` with surface nets, placing one vertex in
+/// every cell the surface passes through and connecting the cells around every crossed edge.
+pub(crate) fn surface_nets(sdf: &impl LocalSdf, bounds: Aabb3d, resolution: u32) -> SurfaceMesh {
+    let min = Vec3::from(bounds.min);
+    let size = Vec3::from(bounds.max) - min;
+    let cell_size = size.max_element() / resolution as f32;
+    // Pad by a cell so surfaces touching the bounds are closed
+    let origin = min - cell_size;
+    let dims = (size / cell_size).ceil().as_uvec3() + 3;
+
+    let point_index = |p: UVec3| (p.x + dims.x * (p.y + dims.y * p.z)) as usize;
+    let point = |p: UVec3| origin + p.as_vec3() * cell_size;
+    let mut distances = Vec::with_capacity(dims.element_product() as usize);
+    for z in 0..dims.z {
+        for y in 0..dims.y {
+            for x in 0..dims.x {
+                distances.push(sdf.distance(point(UVec3::new(x, y, z))));
+            }
+        }
+    }
+
+    // One vertex per cell the surface passes through, at the mean of its edge crossings
+    let mut mesh = SurfaceMesh::default();
+    let mut cell_vertices = vec![u32::MAX; distances.len()];
+    for z in 0..dims.z - 1 {
+        for y in 0..dims.y - 1 {
+            for x in 0..dims.x - 1 {
+                let cell = UVec3::new(x, y, z);
+                let corners = CORNERS.map(|corner| distances[point_index(cell + corner)]);
+                let mut sum = Vec3::ZERO;
+                let mut crossings = 0;
+                for (a, b) in EDGES {
+                    let (da, db) = (corners[a], corners[b]);
+                    if (da < 0.) == (db < 0.) {
+                        continue;
+                    }
+                    let t = da / (da - db);
+                    sum += CORNERS[a].as_vec3().lerp(CORNERS[b].as_vec3(), t);
+                    crossings += 1;
+                }
+                if crossings == 0 {
+                    continue;
+                }
+
+                let position = point(cell) + sum / crossings as f32 * cell_size;
+                cell_vertices[point_index(cell)] = mesh.positions.len() as u32;
+                mesh.positions.push(position);
+                mesh.normals
+                    .push(sdf.gradient(position).normalize_or(Vec3::Y));
+            }
+        }
+    }
+
+    // A quad between the four cells around every grid edge the surface crosses
+    for z in 1..dims.z - 1 {
+        for y in 1..dims.y - 1 {
+            for x in 1..dims.x - 1 {
+                let p = UVec3::new(x, y, z);
+                let inside = distances[point_index(p)] < 0.;
+                for axis in 0..3 {
+                    let next = p + UVec3::AXES[axis];
+                    if (distances[point_index(next)] < 0.) == inside {
+                        continue;
+                    }
+                    let b = UVec3::AXES[(axis + 1) % 3];
+                    let c = UVec3::AXES[(axis + 2) % 3];
+                    // Counter-clockwise around the edge, so the quad faces along it
+                    let quad =
+                        [p, p - b, p - b - c, p - c].map(|cell| cell_vertices[point_index(cell)]);
+                    if quad.contains(&u32::MAX) {
+                        continue;
+                    }
+                    let [v0, v1, v2, v3] = quad;
+                    if inside {
+                        mesh.indices.extend([v0, v1, v2, v0, v2, v3]);
+                    } else {
+                        mesh.indices.extend([v0, v2, v1, v0, v3, v2]);
+                    }
+                }
+            }
+        }
+    }
+    mesh
+}
+
+const CORNERS: [UVec3; 8] = [
+    UVec3::new(0, 0, 0),
+    UVec3::new(1, 0, 0),
+    UVec3::new(0, 1, 0),
+    UVec3::new(1, 1, 0),
+    UVec3::new(0, 0, 1),
+    UVec3::new(1, 0, 1),
+    UVec3::new(0, 1, 1),
+    UVec3::new(1, 1, 1),
+];
+
+const EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+#[test]
+fn test_surface_nets() {
+    use crate::Ellipsoid;
+
+    let ellipsoid = Ellipsoid {
+        half_size: Vec3::new(2., 1., 1.5),
+    };
+    let bounds = Aabb3d::new(Vec3::ZERO, ellipsoid.half_size);
+    let mesh = surface_nets(&ellipsoid, bounds, 16);
+
+    assert!(!mesh.indices.is_empty());
+    assert_eq!(mesh.indices.len() % 3, 0);
+    for &position in mesh.positions.iter() {
+        assert!(ellipsoid.distance(position).abs() < 0.1, "{position}");
+    }
+    // Triangles wind counter-clockwise when seen from outside
+    for triangle in mesh.indices.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[triangle[i] as usize]);
+        let face_normal = (b - a).cross(c - a);
+        assert!(face_normal.dot(a + b + c) > 0.);
+    }
+}
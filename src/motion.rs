@@ -0,0 +1,188 @@
+use avian3d::{dynamics::solver::contact::ContactConstraints, prelude::*};
+use bevy::{ecs::entity::EntityHashMap, prelude::*};
+
+use crate::SdfCollider;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct SurfaceMotion {
+    /// Point the surface turns around, the center of mass of the collider's body
+    center: Vec3,
+    /// Point the surface scales around, the position of the collider
+    origin: Vec3,
+    linear: Vec3,
+    angular: Vec3,
+    /// How fast the scale changes, relative to the scale, per second
+    scale_rate: f32,
+}
+
+impl SurfaceMotion {
+    pub fn velocity_at(&self, point: Vec3) -> Vec3 {
+        self.linear
+            + self.angular.cross(point - self.center)
+            + (point - self.origin) * self.scale_rate
+    }
+
+    /// Upper bound for the speed of any point within `radius` of `position`.
+    pub fn max_speed(&self, position: Vec3, radius: f32) -> f32 {
+        let reach = radius + position.distance(self.center);
+        let scale_reach = radius + position.distance(self.origin);
+        self.linear.length() + self.angular.length() * reach + self.scale_rate.abs() * scale_reach
+    }
+
+    /// Where a collider at `iso` moving with the surface ends up after `dt` seconds, turning
+    /// around the center of the motion like a collider offset from its body.
+    pub fn advance(&self, iso: Isometry3d, dt: f32) -> Isometry3d {
+        let rotation = Quat::from_scaled_axis(self.angular * dt);
+        let offset = Vec3::from(iso.translation) - self.center;
+        Isometry3d::new(
+            self.center + rotation * offset + self.linear * dt,
+            rotation * iso.rotation,
+        )
+    }
+}
+
+/// Motion of SDF collider surfaces that the solver doesn't know about.
+///
+/// Scaling isn't a body motion, and colliders without a moving rigid body can be animated through
+/// their transform while describing their motion with [`LinearVelocity`] and [`AngularVelocity`].
+///
+/// The motion of kinematic bodies is kept separately, the solver knows about it but the narrow
+/// phase uses it to generate contacts against where fast SDF platforms are going to be.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct SdfSurfaceMotion {
+    surfaces: EntityHashMap<SurfaceMotion>,
+    kinematic: EntityHashMap<SurfaceMotion>,
+}
+
+impl SdfSurfaceMotion {
+    pub fn get(&self, entity: Entity) -> Option<&SurfaceMotion> {
+        self.surfaces.get(&entity)
+    }
+
+    pub fn kinematic(&self, entity: Entity) -> Option<&SurfaceMotion> {
+        self.kinematic.get(&entity)
+    }
+
+    pub fn scale_rate(&self, entity: Entity) -> f32 {
+        self.get(entity).map_or(0., |motion| motion.scale_rate)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.surfaces.is_empty()
+    }
+}
+
+/// Records how the surface of every SDF collider moves, following the rigid body it is attached to
+/// when it's a child of one.
+pub(crate) fn record_surface_motion(
+    mut motion: ResMut<SdfSurfaceMotion>,
+    colliders: Query<(
+        Entity,
+        &SdfCollider,
+        &Position,
+        Option<&ColliderOf>,
+        Option<&LinearVelocity>,
+        Option<&AngularVelocity>,
+    )>,
+    bodies: Query<(
+        &RigidBody,
+        &Position,
+        &Rotation,
+        Option<&ComputedCenterOfMass>,
+        Option<&LinearVelocity>,
+        Option<&AngularVelocity>,
+    )>,
+    mut previous_scales: Local<EntityHashMap<f32>>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    motion.surfaces.clear();
+    motion.kinematic.clear();
+    for (entity, collider, pos, collider_of, lin_vel, ang_vel) in colliders.iter() {
+        let scale = collider.uniform_scale();
+        let old_scale = previous_scales.insert(entity, scale).unwrap_or(scale);
+        let scale_rate = if old_scale > 0. && dt > 0. {
+            (scale - old_scale) / old_scale / dt
+        } else {
+            0.
+        };
+
+        // Colliders on child entities move with their body, around its center of mass
+        let (body, center, lin_vel, ang_vel) =
+            match collider_of.and_then(|collider_of| bodies.get(collider_of.body).ok()) {
+                Some((body, body_pos, body_rot, com, body_lin_vel, body_ang_vel)) => (
+                    Some(body),
+                    body_pos.0 + body_rot.0 * com.map_or(Vec3::ZERO, |com| com.0),
+                    body_lin_vel,
+                    body_ang_vel,
+                ),
+                None => (None, pos.0, lin_vel, ang_vel),
+            };
+        let velocity = (
+            lin_vel.map_or(Vec3::ZERO, |v| v.0),
+            ang_vel.map_or(Vec3::ZERO, |v| v.0),
+        );
+        if body.is_some_and(|body| body.is_kinematic()) && velocity != (Vec3::ZERO, Vec3::ZERO) {
+            motion.kinematic.insert(
+                entity,
+                SurfaceMotion {
+                    center,
+                    origin: pos.0,
+                    linear: velocity.0,
+                    angular: velocity.1,
+                    scale_rate: 0.,
+                },
+            );
+        }
+
+        // The solver already accounts for the velocity of moving bodies
+        let moving_body = body.is_some_and(|body| body.is_dynamic() || body.is_kinematic());
+        let (linear, angular) = match moving_body {
+            true => (Vec3::ZERO, Vec3::ZERO),
+            false => velocity,
+        };
+
+        if scale_rate != 0. || linear != Vec3::ZERO || angular != Vec3::ZERO {
+            motion.surfaces.insert(
+                entity,
+                SurfaceMotion {
+                    center,
+                    origin: pos.0,
+                    linear,
+                    angular,
+                    scale_rate,
+                },
+            );
+        }
+    }
+    previous_scales.retain(|entity, _| colliders.contains(*entity));
+}
+
+/// Moves the contacts on scaling SDF colliders along with their surface every substep, so bodies
+/// inside a shrinking container don't lag a whole step behind its walls.
+pub(crate) fn reproject_scaling_contacts(
+    mut constraints: ResMut<ContactConstraints>,
+    motion: Res<SdfSurfaceMotion>,
+    time: Res<Time<Substeps>>,
+) {
+    if motion.is_empty() {
+        return;
+    }
+    let dt = time.delta_secs();
+    for constraint in constraints.iter_mut() {
+        let rate1 = motion.scale_rate(constraint.collider1);
+        let rate2 = motion.scale_rate(constraint.collider2);
+        if rate1 == 0. && rate2 == 0. {
+            continue;
+        }
+
+        let normal = constraint.normal;
+        for point in constraint.points.iter_mut() {
+            // Points on a scaling surface move away from the collider's origin, the normal points
+            // from the first collider to the second
+            let surface1 = (point.anchor1 * rate1).dot(normal) * dt;
+            let surface2 = (point.anchor2 * rate2).dot(normal) * dt;
+            point.initial_separation += surface2 - surface1;
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use bevy::{math::bounding::Aabb3d, prelude::*};
+use bevy_math::ops;
+
+const NORMAL_STEP: f32 = 0.01;
+
+/// Settings for [`SdfSpatialQuery::walkable_heightfield`](crate::SdfSpatialQuery::walkable_heightfield).
+#[derive(Clone, Copy, Debug)]
+pub struct WalkableSettings {
+    /// Horizontal size of a heightfield column
+    pub cell_size: f32,
+    /// Vertical precision of the floors, and the thinnest solid the rasterizer can find
+    pub cell_height: f32,
+    /// Steepest walkable slope, in radians
+    pub max_slope: f32,
+    /// Free space an agent needs above a floor to stand on it
+    pub min_clearance: f32,
+}
+
+impl Default for WalkableSettings {
+    fn default() -> Self {
+        Self {
+            cell_size: 0.3,
+            cell_height: 0.1,
+            max_slope: 45f32.to_radians(),
+            min_clearance: 2.,
+        }
+    }
+}
+
+/// A floor found in a heightfield column.
+#[derive(Clone, Copy, Debug)]
+pub struct WalkableSpan {
+    /// Height of the top of the solid
+    pub floor: f32,
+    /// Height of the bottom of the solid above, infinite if there is none within the bounds
+    pub ceiling: f32,
+    pub normal: Vec3,
+    /// Whether the slope and clearance of this floor pass the [`WalkableSettings`]
+    pub walkable: bool,
+}
+
+/// Floors of SDF geometry rasterized into a grid of columns along the Y axis, in the layout
+/// recast-style navmesh builders expect for their heightfields.
+#[derive(Clone, Debug)]
+pub struct WalkableHeightfield {
+    /// Corner of the grid with the lowest coordinates
+    pub origin: Vec3,
+    pub cell_size: f32,
+    pub width: usize,
+    pub depth: usize,
+    /// Spans of every column from the top down, indexed by `x + z * width`
+    pub columns: Vec<Vec<WalkableSpan>>,
+}
+
+impl WalkableHeightfield {
+    pub fn column(&self, x: usize, z: usize) -> &[WalkableSpan] {
+        &self.columns[x + z * self.width]
+    }
+
+    /// World-space center of a column at the bottom of the grid
+    pub fn column_origin(&self, x: usize, z: usize) -> Vec3 {
+        self.origin + Vec3::new(x as f32 + 0.5, 0., z as f32 + 0.5) * self.cell_size
+    }
+}
+
+pub(crate) fn rasterize_walkable(
+    distance: impl Fn(Vec3) -> f32,
+    bounds: Aabb3d,
+    settings: &WalkableSettings,
+) -> WalkableHeightfield {
+    let min = Vec3::from(bounds.min);
+    let max = Vec3::from(bounds.max);
+    let size = ((max - min) / settings.cell_size).ceil().max(Vec3::ZERO);
+    let mut heightfield = WalkableHeightfield {
+        origin: min,
+        cell_size: settings.cell_size,
+        width: size.x as usize,
+        depth: size.z as usize,
+        columns: Vec::new(),
+    };
+
+    let min_normal_y = ops::cos(settings.max_slope);
+    for z in 0..heightfield.depth {
+        for x in 0..heightfield.width {
+            let column = heightfield.column_origin(x, z);
+            let mut spans = Vec::new();
+            let mut ceiling = f32::INFINITY;
+            let mut y = max.y;
+            let mut solid = distance(column.with_y(y)) < 0.;
+            if solid {
+                // Geometry above the bounds still limits the clearance
+                ceiling = y;
+            }
+
+            // The distance is a safe vertical step both inside and outside the surface
+            while y > min.y {
+                let d = distance(column.with_y(y));
+                if solid == (d < 0.) {
+                    y -= d.abs().max(settings.cell_height * 0.5);
+                    continue;
+                }
+
+                if solid {
+                    ceiling = y + d;
+                } else {
+                    let floor = Vec3::new(column.x, y - d, column.z);
+                    let normal = normal_at(&distance, floor);
+                    spans.push(WalkableSpan {
+                        floor: floor.y,
+                        ceiling,
+                        normal,
+                        walkable: false,
+                    });
+                }
+                solid = !solid;
+                y -= settings.cell_height * 0.5;
+            }
+
+            for span in spans.iter_mut() {
+                span.walkable = span.normal.y >= min_normal_y
+                    && span.ceiling - span.floor >= settings.min_clearance;
+            }
+            heightfield.columns.push(spans);
+        }
+    }
+
+    heightfield
+}
+
+fn normal_at(distance: &impl Fn(Vec3) -> f32, point: Vec3) -> Vec3 {
+    let h = NORMAL_STEP;
+    Vec3::new(
+        distance(point + Vec3::X * h) - distance(point - Vec3::X * h),
+        distance(point + Vec3::Y * h) - distance(point - Vec3::Y * h),
+        distance(point + Vec3::Z * h) - distance(point - Vec3::Z * h),
+    )
+    .normalize_or(Vec3::Y)
+}
+
+#[test]
+fn test_walkable_heightfield() {
+    // Ground at y = 0 with a 1 unit thick slab floating at y = 1..2 over positive x
+    let distance = |p: Vec3| {
+        let ground = p.y;
+        let q = (p - Vec3::new(5., 1.5, 0.)).abs() - Vec3::new(5., 0.5, 10.);
+        let slab = q.max(Vec3::ZERO).length() + q.max_element().min(0.);
+        ground.min(slab)
+    };
+    let bounds = Aabb3d::new(Vec3::new(0., 1., 0.), Vec3::new(4., 2., 4.));
+    let settings = WalkableSettings {
+        cell_size: 1.,
+        min_clearance: 1.5,
+        ..default()
+    };
+    let heightfield = rasterize_walkable(distance, bounds, &settings);
+    assert_eq!((heightfield.width, heightfield.depth), (8, 8));
+
+    // Open ground
+    let open = heightfield.column(1, 4);
+    assert_eq!(open.len(), 1);
+    assert!(open[0].floor.abs() < 0.01 && open[0].walkable);
+
+    // Under the slab there's only 1 unit of clearance, but the slab's top is walkable
+    let covered = heightfield.column(6, 4);
+    assert_eq!(covered.len(), 2, "{covered:?}");
+    assert!((covered[0].floor - 2.).abs() < 0.01 && covered[0].walkable);
+    assert!(covered[1].floor.abs() < 0.01 && !covered[1].walkable);
+    assert!((covered[1].ceiling - 1.).abs() < 0.05);
+}
@@ -0,0 +1,48 @@
+use std::f32::consts::FRAC_PI_4;
+
+use avian3d::prelude::ContactManifold;
+use bevy::prelude::*;
+use bevy_math::ops;
+
+/// Makes an [`SdfCollider`](crate::SdfCollider) only collide on the side facing `direction`,
+/// like a platform bodies can jump through from below.
+///
+/// Contacts are dropped unless the surface normal is within `max_angle` of `direction`, which is
+/// in the local space of the collider.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Debug)]
+pub struct OneWaySurface {
+    pub direction: Dir3,
+    pub max_angle: f32,
+}
+
+impl OneWaySurface {
+    pub fn new(direction: Dir3) -> Self {
+        Self {
+            direction,
+            max_angle: FRAC_PI_4,
+        }
+    }
+
+    pub fn with_max_angle(mut self, max_angle: f32) -> Self {
+        self.max_angle = max_angle;
+        self
+    }
+
+    fn allows(&self, rotation: Quat, surface_normal: Vec3) -> bool {
+        (rotation * self.direction).dot(surface_normal) >= ops::cos(self.max_angle)
+    }
+}
+
+/// Drops the manifolds whose normals face away from the one-way surfaces of the pair.
+pub(crate) fn filter_one_way_contacts(
+    contacts: &mut Vec<ContactManifold>,
+    surface1: Option<(&OneWaySurface, Quat)>,
+    surface2: Option<(&OneWaySurface, Quat)>,
+) {
+    // Manifold normals point from the first collider to the second
+    contacts.retain(|manifold| {
+        surface1.is_none_or(|(surface, rotation)| surface.allows(rotation, manifold.normal))
+            && surface2.is_none_or(|(surface, rotation)| surface.allows(rotation, -manifold.normal))
+    });
+}
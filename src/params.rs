@@ -0,0 +1,58 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_prototype_sdf::Sdf3d;
+
+use crate::{reload::wake_touching_bodies, SdfCollider};
+
+/// Animatable parameters for the SDF asset of a collider on the same entity, evaluated per entity
+/// so animated shapes don't need an asset per frame.
+///
+/// Changing them refreshes the collider like a hot reload of its asset.
+#[derive(Component, Debug, Clone, Default, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug)]
+pub struct SdfParams {
+    /// Another SDF asset the collider's asset is blended towards
+    #[reflect(ignore)]
+    pub blend_target: Option<Handle<Sdf3d>>,
+    /// How far the collider is blended towards the target, from 0 to 1
+    pub blend: f32,
+    /// Grows the surface outwards by this distance, or shrinks it if negative
+    pub inflate: f32,
+}
+
+impl SdfParams {
+    pub fn blended(target: Handle<Sdf3d>, blend: f32) -> Self {
+        Self {
+            blend_target: Some(target),
+            blend,
+            inflate: 0.,
+        }
+    }
+
+    pub fn inflated(inflate: f32) -> Self {
+        Self {
+            inflate,
+            ..default()
+        }
+    }
+}
+
+pub(crate) fn apply_sdf_params(
+    changed: Query<Entity, Changed<SdfParams>>,
+    mut removed: RemovedComponents<SdfParams>,
+    mut colliders: Query<(Option<&SdfParams>, &mut SdfCollider)>,
+    collider_of: Query<&ColliderOf>,
+    collisions: Option<Collisions>,
+    mut commands: Commands,
+) {
+    for entity in changed.iter().chain(removed.read()) {
+        let Ok((params, mut collider)) = colliders.get_mut(entity) else {
+            continue;
+        };
+        collider.params = params.cloned();
+        collider.reloaded = true;
+        if let Some(collisions) = &collisions {
+            wake_touching_bodies(entity, collisions, &collider_of, &mut commands);
+        }
+    }
+}
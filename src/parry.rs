@@ -0,0 +1,220 @@
+use avian3d::{
+    parry::{math::Vector, shape::TypedShape},
+    prelude::*,
+};
+use bevy::{math::FloatPow, prelude::*};
+use bevy_math::ops;
+
+use crate::{SdfCollider, SdfColliderKind, SphereCluster};
+
+/// Angle between consecutive directions of a Fibonacci spiral, spreading them evenly
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+/// Returned when a collider has no equivalent shape in the other representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedShape;
+
+impl std::fmt::Display for UnsupportedShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the collider shape has no equivalent in the other representation")
+    }
+}
+
+impl std::error::Error for UnsupportedShape {}
+
+impl TryFrom<&Collider> for SdfCollider {
+    type Error = UnsupportedShape;
+
+    /// Converts the unscaled shape, the entity's scale is applied to both representations.
+    fn try_from(collider: &Collider) -> Result<Self, Self::Error> {
+        match collider.shape().as_typed_shape() {
+            TypedShape::Ball(ball) => Ok(Self::sphere(ball.radius)),
+            TypedShape::Capsule(capsule) => {
+                let (a, b) = (capsule.segment.a, capsule.segment.b);
+                // SDF capsules are always centered and along the Y axis
+                if a.x != 0. || a.z != 0. || b.x != 0. || b.z != 0. || a.y != -b.y {
+                    return Err(UnsupportedShape);
+                }
+                Ok(Self::capsule(capsule.radius, (b.y - a.y).abs()))
+            }
+            _ => Err(UnsupportedShape),
+        }
+    }
+}
+
+impl TryFrom<&SdfCollider> for Collider {
+    type Error = UnsupportedShape;
+
+    /// Converts the unscaled shape, the entity's scale is applied to both representations.
+    fn try_from(collider: &SdfCollider) -> Result<Self, Self::Error> {
+        match collider.collider() {
+            SdfColliderKind::Sphere(sphere) => Ok(Collider::sphere(sphere.radius)),
+            SdfColliderKind::Capsule(capsule) => {
+                Ok(Collider::capsule(capsule.radius, capsule.half_length * 2.))
+            }
+            SdfColliderKind::Segment(segment) => {
+                Ok(Collider::segment(segment.vertices[0], segment.vertices[1]))
+            }
+            SdfColliderKind::Polyline(polyline) => Ok(Collider::compound(
+                polyline
+                    .segments()
+                    .map(|(a, b)| {
+                        let capsule = Collider::capsule_endpoints(polyline.radius, a, b);
+                        (Vec3::ZERO, Quat::IDENTITY, capsule)
+                    })
+                    .collect(),
+            )),
+            _ => Err(UnsupportedShape),
+        }
+    }
+}
+
+/// Selects which collider representation an entity uses.
+///
+/// Whichever collider the entity has is converted into the selected one, and the other is removed
+/// so only one backend handles the entity. Changing the value switches the representation.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColliderRepresentation {
+    Sdf,
+    Parry,
+}
+
+pub(crate) fn switch_collider_representations(
+    mut commands: Commands,
+    query: Query<
+        (
+            Entity,
+            &ColliderRepresentation,
+            Option<&Collider>,
+            Option<&SdfCollider>,
+        ),
+        Or<(
+            Changed<ColliderRepresentation>,
+            Added<Collider>,
+            Added<SdfCollider>,
+        )>,
+    >,
+) {
+    for (entity, representation, parry, sdf) in query.iter() {
+        match (representation, parry, sdf) {
+            (ColliderRepresentation::Sdf, Some(parry), _) => match SdfCollider::try_from(parry) {
+                Ok(sdf) => {
+                    commands.entity(entity).insert(sdf).remove::<Collider>();
+                }
+                Err(err) => warn!("Can't switch {entity} to an SDF collider: {err}"),
+            },
+            (ColliderRepresentation::Parry, _, Some(sdf)) => match Collider::try_from(sdf) {
+                Ok(parry) => {
+                    commands
+                        .entity(entity)
+                        .insert(parry)
+                        .remove::<SdfCollider>();
+                }
+                Err(err) => warn!("Can't switch {entity} to a parry collider: {err}"),
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Lets an entity with avian's [`Collider`] collide with SDF colliders, for mesh-colliding
+/// characters in an SDF level.
+///
+/// A child collider samples the support points of the shape in `samples` directions and places
+/// small spheres just inside them, which collide like a [`SphereCluster`]. Only convex shapes have
+/// support points, and SDF geometry thinner than the gaps between the samples can poke through.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SdfInterop {
+    pub samples: u32,
+    /// Radius of the spheres at the samples
+    pub radius: f32,
+}
+
+impl Default for SdfInterop {
+    fn default() -> Self {
+        Self {
+            samples: 64,
+            radius: 0.05,
+        }
+    }
+}
+
+/// The child collider sampling the shape of an [`SdfInterop`] entity.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct SdfInteropProxy;
+
+/// Spheres just inside the support points of a convex shape, in evenly spread directions.
+fn support_cluster(collider: &Collider, interop: &SdfInterop) -> Option<SphereCluster> {
+    let support_map = collider.shape().as_support_map()?;
+    let samples = interop.samples.max(1);
+    let mut centers: Vec<Vec3> = Vec::new();
+    for i in 0..samples {
+        let y = 1. - 2. * (i as f32 + 0.5) / samples as f32;
+        let ring = (1. - y * y).max(0.).sqrt();
+        let angle = i as f32 * GOLDEN_ANGLE;
+        let direction = Vec3::new(ops::cos(angle) * ring, y, ops::sin(angle) * ring);
+        let support =
+            support_map.local_support_point(&Vector::new(direction.x, direction.y, direction.z));
+        let center = Vec3::new(support.x, support.y, support.z) - direction * interop.radius;
+        // Corners of polyhedra are the support point of many directions
+        let min_spacing_sq = (interop.radius * 0.5).squared();
+        if centers
+            .iter()
+            .all(|other| other.distance_squared(center) > min_spacing_sq)
+        {
+            centers.push(center);
+        }
+    }
+    Some(SphereCluster::new(centers, interop.radius))
+}
+
+pub(crate) fn sync_interop_proxies(
+    mut commands: Commands,
+    changed: Query<
+        (
+            Entity,
+            &Collider,
+            &SdfInterop,
+            Option<&CollisionLayers>,
+            Option<&Children>,
+        ),
+        Or<(Changed<Collider>, Changed<SdfInterop>)>,
+    >,
+    mut proxies: Query<&mut SdfCollider, With<SdfInteropProxy>>,
+    mut removed: RemovedComponents<SdfInterop>,
+    children: Query<&Children>,
+) {
+    for entity in removed.read() {
+        for child in children.get(entity).into_iter().flatten() {
+            if proxies.contains(*child) {
+                commands.entity(*child).despawn();
+            }
+        }
+    }
+
+    for (entity, collider, interop, layers, entity_children) in changed.iter() {
+        let Some(cluster) = support_cluster(collider, interop) else {
+            warn!("Can't collide {entity} with SDF colliders: only convex shapes are supported");
+            continue;
+        };
+        let proxy = entity_children
+            .into_iter()
+            .flatten()
+            .copied()
+            .find(|child| proxies.contains(*child));
+        match proxy.and_then(|proxy| proxies.get_mut(proxy).ok()) {
+            Some(mut proxy) => proxy.set_shape(SdfColliderKind::SphereCluster(cluster)),
+            None => {
+                commands.spawn((
+                    SdfInteropProxy,
+                    SdfCollider::sphere_cluster(cluster),
+                    // The entity's own collider already accounts for its mass
+                    ColliderDensity(0.),
+                    layers.copied().unwrap_or_default(),
+                    Transform::default(),
+                    ChildOf(entity),
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::{cell::RefCell, sync::Arc};
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_prototype_sdf::{dim3::Dim3, ExecutableSdfs, Sdf3d, SdfProcessed};
+
+use crate::{
+    primitives::LocalSdf, reload::SdfRecomputeQueue, scratch::with_scratch, SdfCollider,
+    SdfColliderKind, SdfContext,
+};
+
+const MAX_PATCH_DEPTH: u32 = 10;
+
+/// Bakes the surface of this collider's SDF asset into patches, meant for large static levels.
+///
+/// Contact generation then skips pairs that are nowhere near a patch without evaluating the
+/// SDF at all. Patches are shared by every collider using the same asset.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BakeSurfacePatches {
+    /// Patches are split until they are at most this large, in the SDF's local space
+    pub leaf_size: f32,
+}
+
+impl Default for BakeSurfacePatches {
+    fn default() -> Self {
+        Self { leaf_size: 1. }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub(crate) struct SdfPatchCache(HashMap<AssetId<Sdf3d>, Arc<SurfacePatches>>);
+
+impl SdfPatchCache {
+    pub fn get(&self, id: AssetId<Sdf3d>) -> Option<&SurfacePatches> {
+        self.0.get(&id).map(|patches| &**patches)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PatchRegion {
+    Outside,
+    Inside,
+    Surface,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PatchNode {
+    Leaf(PatchRegion),
+    // Index of the first of 8 consecutive children
+    Split(u32),
+}
+
+/// An octree over the local bounds of an SDF, split only where the surface passes through.
+#[derive(Debug)]
+pub(crate) struct SurfacePatches {
+    center: Vec3,
+    half_size: Vec3,
+    nodes: Vec<PatchNode>,
+}
+
+fn child_offset(i: usize) -> Vec3 {
+    Vec3::new(
+        if i & 1 == 0 { -1. } else { 1. },
+        if i & 2 == 0 { -1. } else { 1. },
+        if i & 4 == 0 { -1. } else { 1. },
+    )
+}
+
+impl SurfacePatches {
+    pub fn bake(sdf: &impl LocalSdf, min: Vec3, max: Vec3, leaf_size: f32) -> Self {
+        let center = (min + max) * 0.5;
+        // Cells are cubes, so splitting always reduces the largest dimension
+        let half_size = Vec3::splat((max - min).max_element() * 0.5 + leaf_size * 0.01);
+        let mut patches = Self {
+            center,
+            half_size,
+            nodes: vec![PatchNode::Leaf(PatchRegion::Outside)],
+        };
+
+        let mut queue = vec![(0usize, center, half_size, 0)];
+        while let Some((index, center, half_size, depth)) = queue.pop() {
+            let distance = sdf.distance(center);
+            let radius = half_size.length();
+            let region = if distance > radius {
+                PatchRegion::Outside
+            } else if distance < -radius {
+                PatchRegion::Inside
+            } else {
+                PatchRegion::Surface
+            };
+            if region != PatchRegion::Surface
+                || half_size.x * 2. <= leaf_size
+                || depth >= MAX_PATCH_DEPTH
+            {
+                patches.nodes[index] = PatchNode::Leaf(region);
+                continue;
+            }
+
+            let first_child = patches.nodes.len();
+            patches.nodes[index] = PatchNode::Split(first_child as u32);
+            patches
+                .nodes
+                .extend([PatchNode::Leaf(PatchRegion::Outside); 8]);
+            let child_half_size = half_size * 0.5;
+            for i in 0..8 {
+                queue.push((
+                    first_child + i,
+                    center + child_offset(i) * child_half_size,
+                    child_half_size,
+                    depth + 1,
+                ));
+            }
+        }
+
+        patches
+    }
+
+    /// Classifies the region of the SDF a local sphere overlaps.
+    pub fn classify(&self, center: Vec3, radius: f32) -> PatchRegion {
+        let mut inside = false;
+        let mut outside = false;
+        if (center - self.center)
+            .abs()
+            .cmpgt(self.half_size + radius)
+            .any()
+        {
+            return PatchRegion::Outside;
+        }
+        // Parts of the sphere outside the baked bounds are outside the surface
+        if (center - self.center)
+            .abs()
+            .cmpgt(self.half_size - radius)
+            .any()
+        {
+            outside = true;
+        }
+
+        thread_local! {
+            static NODES: RefCell<Vec<(usize, Vec3, Vec3)>> = const { RefCell::new(Vec::new()) };
+        }
+        let surface = with_scratch(&NODES, |stack| {
+            stack.push((0, self.center, self.half_size));
+            while let Some((index, node_center, node_half_size)) = stack.pop() {
+                let offset = (center - node_center).abs();
+                if offset.cmpgt(node_half_size + radius).any() {
+                    continue;
+                }
+                match self.nodes[index] {
+                    PatchNode::Leaf(PatchRegion::Surface) => return true,
+                    PatchNode::Leaf(PatchRegion::Inside) => inside = true,
+                    PatchNode::Leaf(PatchRegion::Outside) => outside = true,
+                    PatchNode::Split(first_child) => {
+                        let child_half_size = node_half_size * 0.5;
+                        for i in 0..8 {
+                            stack.push((
+                                first_child as usize + i,
+                                node_center + child_offset(i) * child_half_size,
+                                child_half_size,
+                            ));
+                        }
+                    }
+                }
+            }
+            false
+        });
+        if surface {
+            return PatchRegion::Surface;
+        }
+
+        match (inside, outside) {
+            (true, false) => PatchRegion::Inside,
+            (false, _) => PatchRegion::Outside,
+            // Leaves on both sides of the surface always have a surface leaf between them
+            (true, true) => PatchRegion::Surface,
+        }
+    }
+
+    /// The local bounds of every patch the surface passes through.
+    pub fn surface_leaves(&self) -> Vec<(Vec3, Vec3)> {
+        let mut leaves = Vec::new();
+        let mut stack = vec![(0usize, self.center, self.half_size)];
+        while let Some((index, center, half_size)) = stack.pop() {
+            match self.nodes[index] {
+                PatchNode::Leaf(PatchRegion::Surface) => leaves.push((center, half_size)),
+                PatchNode::Leaf(_) => {}
+                PatchNode::Split(first_child) => {
+                    let child_half_size = half_size * 0.5;
+                    for i in 0..8 {
+                        stack.push((
+                            first_child as usize + i,
+                            center + child_offset(i) * child_half_size,
+                            child_half_size,
+                        ));
+                    }
+                }
+            }
+        }
+        leaves
+    }
+}
+
+/// Bakes patches for assets missing from the cache, waiting while the collider is queued for the
+/// [`SdfRecomputeBudget`](crate::SdfRecomputeBudget).
+pub(crate) fn bake_surface_patches(
+    colliders: Query<(Entity, &SdfCollider, &BakeSurfacePatches)>,
+    sdfs: ExecutableSdfs<Dim3>,
+    queue: Res<SdfRecomputeQueue>,
+    mut cache: ResMut<SdfPatchCache>,
+) {
+    for (entity, collider, bake) in colliders.iter() {
+        let SdfColliderKind::Arbitrary(handle) = collider.collider() else {
+            continue;
+        };
+        if cache.0.contains_key(&handle.id()) || queue.0.contains(&entity) {
+            continue;
+        }
+        let Some((_, sdf)) = sdfs.get(handle.id()) else {
+            continue;
+        };
+
+        let aabb = sdf.aabb(Isometry3d::IDENTITY);
+        let patches = SurfacePatches::bake(&sdf, aabb.min.into(), aabb.max.into(), bake.leaf_size);
+        cache.0.insert(handle.id(), Arc::new(patches));
+    }
+}
+
+pub(crate) fn invalidate_surface_patches(
+    trigger: On<SdfProcessed>,
+    mut cache: ResMut<SdfPatchCache>,
+) {
+    let SdfProcessed(id) = trigger.event();
+    cache.0.remove(&AssetId::from(*id));
+}
+
+impl SdfContext<'_, '_> {
+    /// Whether the bounding sphere of `shape` is known to be away from the baked surface of
+    /// `sdf_collider`, in which case they can't be in contact.
+    pub(crate) fn separated_by_patches(
+        &self,
+        sdf_collider: &SdfCollider,
+        sdf_iso: Isometry3d,
+        shape: &SdfCollider,
+        shape_position: Vec3A,
+        margin: f32,
+    ) -> bool {
+        // Patches are baked for the plain SDF asset, not for a shell or parameters of it
+        if sdf_collider.shell.is_some() || sdf_collider.params.is_some() {
+            return false;
+        }
+        let SdfColliderKind::Arbitrary(handle) = sdf_collider.collider() else {
+            return false;
+        };
+        let Some(patches) = self.narrow_phase().patches.get(handle.id()) else {
+            return false;
+        };
+        let Some(radius) = shape.bounding_radius(self) else {
+            return false;
+        };
+
+        let local_center =
+            Vec3::from(sdf_iso.inverse().transform_point(shape_position)) / sdf_collider.scale;
+        let local_radius = (radius + margin.max(0.)) / sdf_collider.scale;
+        // Inverted colliders are solid outside the surface the patches were baked for
+        let empty = if sdf_collider.inverted {
+            PatchRegion::Inside
+        } else {
+            PatchRegion::Outside
+        };
+        patches.classify(local_center, local_radius) == empty
+    }
+}
+
+#[test]
+fn test_surface_patches() {
+    let sdf = crate::Ellipsoid::new(Vec3::new(2., 1., 2.));
+    let patches = SurfacePatches::bake(&sdf, Vec3::new(-2., -1., -2.), Vec3::new(2., 1., 2.), 0.25);
+
+    assert!(!patches.surface_leaves().is_empty());
+    assert_eq!(
+        patches.classify(Vec3::new(0., 5., 0.), 0.5),
+        PatchRegion::Outside
+    );
+    assert_eq!(
+        patches.classify(Vec3::new(2., 1., 2.), 0.1),
+        PatchRegion::Outside
+    );
+    assert_eq!(
+        patches.classify(Vec3::new(0., 1., 0.), 0.1),
+        PatchRegion::Surface
+    );
+    assert_eq!(patches.classify(Vec3::ZERO, 0.2), PatchRegion::Inside);
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+use bevy_prototype_sdf::{dim3::Dim3, ExecutableSdfs, Sdf3d};
+
+use crate::{SdfCollider, SdfColliderKind};
+
+/// How colliders behave while their SDF asset isn't loaded yet.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub enum MissingSdfPolicy {
+    /// The collider has no bounds, contacts or query hits until the asset is loaded
+    #[default]
+    Defer,
+    /// Like [`Defer`](Self::Defer), but also logs a warning and triggers [`MissingSdf`] once
+    Warn,
+    /// The collider acts as a sphere with this radius until the asset is loaded
+    Placeholder(f32),
+}
+
+/// Marks colliders whose SDF asset isn't loaded yet.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct PendingSdfCollider;
+
+/// Triggered when a collider's SDF asset is missing, if [`MissingSdfPolicy::Warn`] is used.
+#[derive(Event, Debug, Clone)]
+pub struct MissingSdf {
+    pub entity: Entity,
+    pub sdf: Handle<Sdf3d>,
+}
+
+pub(crate) fn track_pending_colliders(
+    mut commands: Commands,
+    colliders: Query<(Entity, &SdfCollider, Has<PendingSdfCollider>)>,
+    sdfs: ExecutableSdfs<Dim3>,
+    policy: Res<MissingSdfPolicy>,
+) {
+    for (entity, collider, pending) in colliders.iter() {
+        let missing = match collider.collider() {
+            SdfColliderKind::Arbitrary(handle) => sdfs.get(handle.id()).is_none(),
+            _ => false,
+        };
+        if missing == pending {
+            continue;
+        }
+        if !missing {
+            commands.entity(entity).remove::<PendingSdfCollider>();
+            continue;
+        }
+
+        commands.entity(entity).insert(PendingSdfCollider);
+        if *policy == MissingSdfPolicy::Warn {
+            let SdfColliderKind::Arbitrary(handle) = collider.collider() else {
+                continue;
+            };
+            warn!(
+                "SDF collider {entity} is waiting for asset {:?}",
+                handle.id()
+            );
+            commands.trigger(MissingSdf {
+                entity,
+                sdf: handle.clone(),
+            });
+        }
+    }
+}
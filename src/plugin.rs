@@ -0,0 +1,274 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use avian3d::prelude::*;
+use bevy::{
+    ecs::{intern::Interned, schedule::ScheduleLabel, system::SystemParamItem},
+    prelude::*,
+};
+
+use crate::{
+    anchors, casters, ccd, collider, compliance, contact_cache, context, diagnostics, filters,
+    impacts, interior, local_contacts, mass, motion, params, patches, pending, query_grid, reload,
+    rolling, scene, swept, tags, ContactStabilization, MissingSdfPolicy, NarrowPhaseLod,
+    OneWaySurface, SdfAssetPath, SdfCollider, SdfColliderConstructor,
+    SdfColliderConstructorHierarchy, SdfColliderKind, SdfColliderLod, SdfCollisionDiagnostics,
+    SdfContactCompliance, SdfContactFilter, SdfMarchQuality, SdfMassProperties, SdfParallelism,
+    SdfParams, SdfPredictionDistance, SdfQueryConfig, SdfQueryOnly, SlopeFriction,
+    UnsupportedPairs,
+};
+#[cfg(feature = "debug-gizmos")]
+use crate::{debug_contacts, SdfDebugContacts};
+
+/// Adds SDF colliders to avian, configured through a builder:
+///
+/// ```ignore
+/// SdfCollisionPlugin::<()>::new(FixedPostUpdate)
+///     .with_spatial_queries(false)
+///     .with_debug(true)
+/// ```
+pub struct SdfCollisionPlugin<H: CollisionHooks = ()> {
+    schedule: Interned<dyn ScheduleLabel>,
+    spatial_queries: bool,
+    narrow_phase: bool,
+    debug: bool,
+    unsupported_pairs: UnsupportedPairs,
+    contact_filters: Vec<Arc<dyn SdfContactFilter>>,
+    phantom: PhantomData<H>,
+}
+
+impl<H: CollisionHooks> SdfCollisionPlugin<H> {
+    /// Runs the SDF systems in `schedule`, which should be the schedule avian runs in.
+    pub fn new(schedule: impl ScheduleLabel) -> Self {
+        Self {
+            schedule: schedule.intern(),
+            spatial_queries: true,
+            narrow_phase: true,
+            debug: false,
+            unsupported_pairs: UnsupportedPairs::default(),
+            contact_filters: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Whether to add avian's spatial query pipeline and the SDF casters, enabled by default
+    pub fn with_spatial_queries(mut self, enabled: bool) -> Self {
+        self.spatial_queries = enabled;
+        self
+    }
+
+    /// Whether to generate contacts between SDF colliders, enabled by default
+    pub fn with_narrow_phase(mut self, enabled: bool) -> Self {
+        self.narrow_phase = enabled;
+        self
+    }
+
+    /// What to do with pairs of collider kinds without contact generation, warns by default
+    pub fn with_unsupported_pairs(mut self, unsupported_pairs: UnsupportedPairs) -> Self {
+        self.unsupported_pairs = unsupported_pairs;
+        self
+    }
+
+    /// Adds a filter that runs on every contact between SDF colliders, after the filters added
+    /// before it
+    pub fn with_contact_filter(mut self, filter: impl SdfContactFilter) -> Self {
+        self.contact_filters.push(Arc::new(filter));
+        self
+    }
+
+    /// Whether to report [`SdfCollisionDiagnostics`] to the diagnostics store, disabled by default.
+    ///
+    /// With the `debug-gizmos` feature this also draws the contacts of every step with gizmos.
+    pub fn with_debug(mut self, enabled: bool) -> Self {
+        self.debug = enabled;
+        self
+    }
+}
+
+impl<H: CollisionHooks> Default for SdfCollisionPlugin<H> {
+    fn default() -> Self {
+        Self::new(FixedPostUpdate)
+    }
+}
+
+impl<H: CollisionHooks + 'static> Plugin for SdfCollisionPlugin<H>
+where
+    for<'w, 's> SystemParamItem<'w, 's, H>: CollisionHooks,
+{
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SdfQueryPlugin>() {
+            app.add_plugins(SdfQueryPlugin {
+                schedule: self.schedule,
+                spatial_queries: self.spatial_queries,
+            });
+        }
+        app.init_resource::<SdfCollisionDiagnostics>();
+        if self.debug {
+            diagnostics::register_diagnostics(app);
+        }
+        app.add_systems(Last, diagnostics::flush_diagnostics);
+
+        if !self.narrow_phase {
+            return;
+        }
+        app.insert_resource(self.unsupported_pairs)
+            .insert_resource(filters::SdfContactFilters(self.contact_filters.clone()))
+            .init_resource::<NarrowPhaseLod>()
+            .init_resource::<ContactStabilization>()
+            .init_resource::<SdfPredictionDistance>()
+            .init_resource::<motion::SdfSurfaceMotion>()
+            .init_resource::<patches::SdfPatchCache>()
+            .init_resource::<contact_cache::SdfContactCache>()
+            .init_resource::<ccd::SweepStarts>()
+            .init_resource::<impacts::ImpactVelocities>()
+            .add_plugins(NarrowPhasePlugin::<SdfCollider, H>::default())
+            .add_systems(PreUpdate, patches::bake_surface_patches)
+            .add_observer(patches::invalidate_surface_patches)
+            .add_systems(
+                self.schedule,
+                (
+                    (
+                        context::advance_lod_tick,
+                        mass::apply_sdf_mass_properties,
+                        ccd::record_sweep_starts,
+                        impacts::record_impact_velocities,
+                        swept::update_swept_spheres,
+                        compliance::apply_compliant_springs,
+                    )
+                        .before(PhysicsSystems::StepSimulation),
+                    motion::record_surface_motion
+                        .after(PhysicsSystems::Prepare)
+                        .before(PhysicsSystems::StepSimulation),
+                    ccd::sweep_ccd_bodies
+                        .after(PhysicsSystems::StepSimulation)
+                        .before(PhysicsSystems::Writeback),
+                    (
+                        local_contacts::record_local_contacts,
+                        rolling::apply_rolling_resistance,
+                        tags::trigger_surface_tag_contacts,
+                        impacts::trigger_impact_events,
+                        interior::track_interiors,
+                        contact_cache::evict_stale_warm_starts,
+                    )
+                        .after(PhysicsSystems::StepSimulation),
+                ),
+            )
+            .add_systems(
+                SubstepSchedule,
+                motion::reproject_scaling_contacts.before(SubstepSolverSystems::SolveConstraints),
+            );
+
+        #[cfg(feature = "debug-gizmos")]
+        if self.debug {
+            app.init_resource::<SdfDebugContacts>()
+                .add_systems(
+                    self.schedule,
+                    debug_contacts::collect_debug_contacts.after(PhysicsSystems::StepSimulation),
+                )
+                .add_systems(PostUpdate, debug_contacts::draw_debug_contacts);
+        }
+    }
+}
+
+/// Registers SDF colliders for spatial queries only, without the narrow phase.
+///
+/// For projects that want SDF raycasts and overlap queries but handle collisions some other way.
+/// [`SdfCollisionPlugin`] adds this plugin itself.
+pub struct SdfQueryPlugin {
+    schedule: Interned<dyn ScheduleLabel>,
+    spatial_queries: bool,
+}
+
+impl SdfQueryPlugin {
+    pub fn new(schedule: impl ScheduleLabel) -> Self {
+        Self {
+            schedule: schedule.intern(),
+            spatial_queries: true,
+        }
+    }
+}
+
+impl Default for SdfQueryPlugin {
+    fn default() -> Self {
+        Self::new(FixedPostUpdate)
+    }
+}
+
+impl Plugin for SdfQueryPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SdfCollider>()
+            .register_type::<SdfColliderKind>()
+            .register_type::<SdfAssetPath>()
+            .register_type::<SdfColliderConstructor>()
+            .register_type::<SdfColliderConstructorHierarchy>()
+            .register_type::<OneWaySurface>()
+            .register_type::<SlopeFriction>()
+            .register_type::<SdfContactCompliance>()
+            .register_type::<SdfParams>()
+            .register_type::<SdfMassProperties>()
+            .register_type::<SdfColliderLod>()
+            .register_type::<SdfQueryOnly>()
+            .init_resource::<SdfQueryConfig>()
+            .init_resource::<SdfParallelism>()
+            .init_resource::<MissingSdfPolicy>()
+            .init_resource::<SdfMarchQuality>()
+            .init_resource::<reload::SdfRecomputeQueue>()
+            .add_plugins(ColliderBackendPlugin::<SdfCollider>::new(self.schedule))
+            .add_systems(
+                PreUpdate,
+                (
+                    (
+                        scene::resolve_sdf_asset_paths,
+                        scene::record_sdf_asset_paths,
+                    )
+                        .chain(),
+                    scene::construct_hierarchy_colliders,
+                    pending::track_pending_colliders,
+                ),
+            )
+            .add_observer(collider::add_embedded_sdfs)
+            .add_observer(scene::construct_sdf_colliders)
+            .add_observer(reload::invalidate_reloaded_colliders)
+            .add_observer(anchors::reproject_surface_anchors)
+            .add_systems(
+                self.schedule,
+                (
+                    (
+                        params::apply_sdf_params,
+                        reload::recompute_queued_colliders,
+                        context::simplify_distant_colliders,
+                        reload::refresh_reloaded_aabbs,
+                    )
+                        .chain()
+                        .before(PhysicsSystems::StepSimulation),
+                    reload::clear_reloaded.after(PhysicsSystems::StepSimulation),
+                    query_grid::rebuild_query_grid
+                        .run_if(resource_exists::<query_grid::SdfQueryGrid>)
+                        .after(PhysicsSystems::StepSimulation),
+                ),
+            );
+
+        #[cfg(feature = "parry")]
+        app.add_systems(
+            PreUpdate,
+            (
+                crate::parry::switch_collider_representations,
+                crate::parry::sync_interop_proxies,
+            ),
+        );
+
+        #[cfg(feature = "mesh")]
+        app.register_type::<crate::SdfColliderFrom>()
+            .add_observer(crate::mesh_colliders::fit_inserted_colliders)
+            .add_systems(PreUpdate, crate::mesh_colliders::refit_changed_colliders);
+
+        if self.spatial_queries {
+            app.add_plugins(SpatialQueryPlugin::<SdfCollider>::default())
+                .add_systems(
+                    self.schedule,
+                    (casters::update_ray_casters, casters::update_shape_casters)
+                        .after(PhysicsSystems::StepSimulation)
+                        .after(query_grid::rebuild_query_grid),
+                );
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use bevy::{
+    math::{bounding::Aabb3d, Isometry3d, Vec3, Vec3A},
+    reflect::Reflect,
+};
+
+use crate::{
+    adder::{Contact, ManifoldAdder},
+    primitives::{
+        capsule_between, capsule_sdf_collisions, distance_at, Collider, LineSdf, LocalSdf,
+        ScaledIsometry3d,
+    },
+};
+
+/// Segments in each leaf of the bounding volume hierarchy of a [`Polyline`]
+const LEAF_SEGMENTS: usize = 4;
+
+/// Deeper than the hierarchy of any polyline that fits in memory
+const MAX_DEPTH: usize = 64;
+
+/// A chain of capsules with a shared radius through a list of points, for rails, pipes and rope
+/// proxies.
+///
+/// Runs of consecutive segments are grouped in a bounding volume hierarchy, so distance queries
+/// and contacts only look at the segments close to them.
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+pub struct Polyline {
+    points: Vec<Vec3>,
+    pub radius: f32,
+    // Polylines restored through reflection have no hierarchy and check every segment
+    #[reflect(ignore)]
+    nodes: Vec<PolylineNode>,
+}
+
+/// Bounds of the segments `start..end`, without the radius of the polyline.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct PolylineNode {
+    pub min: Vec3,
+    pub max: Vec3,
+    start: u32,
+    end: u32,
+    /// Index of the second child, the first child directly follows the node. Zero for leaves
+    right: u32,
+}
+
+impl PolylineNode {
+    fn new(points: &[Vec3], start: usize, end: usize) -> Self {
+        let (min, max) = points[start..=end]
+            .iter()
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), &p| {
+                (min.min(p), max.max(p))
+            });
+        Self {
+            min,
+            max,
+            start: start as u32,
+            end: end as u32,
+            right: 0,
+        }
+    }
+
+    /// The segments in this node if it's a leaf.
+    pub fn leaf_segments(&self) -> Option<std::ops::Range<usize>> {
+        (self.right == 0).then_some(self.start as usize..self.end as usize)
+    }
+
+    fn distance_squared(&self, point: Vec3) -> f32 {
+        point.distance_squared(point.clamp(self.min, self.max))
+    }
+}
+
+impl Polyline {
+    pub fn new(points: impl IntoIterator<Item = Vec3>, radius: f32) -> Self {
+        let mut polyline = Self {
+            points: points.into_iter().collect(),
+            radius,
+            nodes: Vec::new(),
+        };
+        if !polyline.is_empty() {
+            polyline.build(0, polyline.len());
+        }
+        polyline
+    }
+
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(PolylineNode::new(&self.points, start, end));
+        if end - start > LEAF_SEGMENTS {
+            let mid = (start + end) / 2;
+            self.build(start, mid);
+            self.nodes[index].right = self.build(mid, end) as u32;
+        }
+        index
+    }
+
+    pub fn points(&self) -> &[Vec3] {
+        &self.points
+    }
+
+    /// Number of segments.
+    pub fn len(&self) -> usize {
+        self.points.len().saturating_sub(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The start and end of a segment.
+    pub fn segment(&self, index: usize) -> (Vec3, Vec3) {
+        (self.points[index], self.points[index + 1])
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+        self.points.windows(2).map(|pair| (pair[0], pair[1]))
+    }
+
+    pub fn length(&self) -> f32 {
+        self.segments().map(|(a, b)| a.distance(b)).sum()
+    }
+
+    /// Center of the segments, weighted by their length.
+    pub fn center_of_mass(&self) -> Vec3 {
+        let length = self.length();
+        if length <= f32::EPSILON {
+            return self.points.first().copied().unwrap_or(Vec3::ZERO);
+        }
+        self.segments()
+            .map(|(a, b)| (a + b) * 0.5 * a.distance(b))
+            .sum::<Vec3>()
+            / length
+    }
+
+    pub fn aabb_3d(&self, isometry: Isometry3d) -> Aabb3d {
+        let (min, max) = self
+            .points
+            .iter()
+            .map(|&p| isometry * Vec3A::from(p))
+            .fold((Vec3A::INFINITY, Vec3A::NEG_INFINITY), |(min, max), p| {
+                (min.min(p), max.max(p))
+            });
+        Aabb3d {
+            min: min - self.radius,
+            max: max + self.radius,
+        }
+    }
+
+    /// The segment closest to `point`, with the closest point on it.
+    pub fn closest_segment(&self, point: Vec3) -> Option<(usize, Vec3)> {
+        let mut closest = None;
+        let mut closest_sq = f32::INFINITY;
+        self.visit(|node| {
+            if node.distance_squared(point) >= closest_sq {
+                return false;
+            }
+            for index in node.leaf_segments().into_iter().flatten() {
+                let on_segment = self.line(index).closest_point(point);
+                let distance_sq = on_segment.distance_squared(point);
+                if distance_sq < closest_sq {
+                    closest_sq = distance_sq;
+                    closest = Some((index, on_segment));
+                }
+            }
+            true
+        });
+        closest
+    }
+
+    pub(crate) fn line(&self, index: usize) -> LineSdf {
+        let (start, end) = self.segment(index);
+        LineSdf {
+            start,
+            end,
+            radius: self.radius,
+        }
+    }
+
+    /// Walks the hierarchy depth first, only descending into nodes `enter` returns true for.
+    pub(crate) fn visit(&self, mut enter: impl FnMut(&PolylineNode) -> bool) {
+        if self.nodes.is_empty() {
+            if !self.is_empty() {
+                enter(&PolylineNode::new(&self.points, 0, self.len()));
+            }
+            return;
+        }
+
+        let mut stack = [0; MAX_DEPTH];
+        let mut len = 1;
+        while len > 0 {
+            len -= 1;
+            let index = stack[len];
+            let node = &self.nodes[index as usize];
+            if !enter(node) || node.right == 0 {
+                continue;
+            }
+            // The first child is pushed last so it's visited first
+            stack[len] = node.right;
+            stack[len + 1] = index + 1;
+            len += 2;
+        }
+    }
+}
+
+impl LocalSdf for Polyline {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        self.closest_segment(local_point)
+            .map_or(f32::INFINITY, |(_, closest)| {
+                closest.distance(local_point) - self.radius
+            })
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        self.closest_segment(local_point)
+            .map_or(Vec3::Y, |(_, closest)| {
+                (local_point - closest).normalize_or(Vec3::Y)
+            })
+    }
+}
+
+impl<S: LocalSdf> Collider<S> for Polyline {
+    fn get_collisions<T: From<Contact>>(
+        &self,
+        self_iso: ScaledIsometry3d,
+        sdf: &S,
+        sdf_iso: ScaledIsometry3d,
+        mut adder: ManifoldAdder<T>,
+        pred_dist: f32,
+    ) {
+        adder.set_frames(self_iso, sdf_iso);
+        let radius = self.radius * self_iso.scale;
+
+        self.visit(|node| {
+            // Skip runs of segments that are further from the SDF than they are long
+            let center = (node.min + node.max) * 0.5 * self_iso.scale;
+            let half_extent = (node.max - node.min).length() * 0.5 * self_iso.scale;
+            let sdf_local_center = sdf_iso.to_local(self_iso.transform_point(center));
+            let center_dist = distance_at(sdf, sdf_local_center) * sdf_iso.scale;
+            if center_dist > half_extent + radius + pred_dist {
+                return false;
+            }
+
+            for index in node.leaf_segments().into_iter().flatten() {
+                let (start, end) = self.segment(index);
+                let (capsule, offset, rotation) =
+                    capsule_between(start * self_iso.scale, end * self_iso.scale, radius);
+                let capsule_iso = self_iso.iso * Isometry3d::new(offset, rotation);
+                capsule_sdf_collisions(
+                    &capsule,
+                    capsule_iso,
+                    sdf,
+                    sdf_iso,
+                    adder.reborrow(),
+                    pred_dist,
+                    None,
+                );
+            }
+            true
+        });
+    }
+}
+
+#[test]
+fn test_polyline_closest_segment() {
+    // A helix, so segments far apart in the chain pass close to each other
+    let points = (0..200).map(|i| {
+        let t = i as f32 * 0.2;
+        Vec3::new(t.cos() * 3., t * 0.1, t.sin() * 3.)
+    });
+    let polyline = Polyline::new(points, 0.1);
+    assert_eq!(polyline.len(), 199);
+
+    for i in 0..50 {
+        let point = Vec3::new(
+            (i as f32 * 1.3).sin() * 5.,
+            i as f32 * 0.08,
+            (i as f32 * 0.7).cos() * 5.,
+        );
+        let brute_force = polyline
+            .segments()
+            .map(|(start, end)| {
+                LineSdf {
+                    start,
+                    end,
+                    radius: 0.1,
+                }
+                .distance(point)
+            })
+            .fold(f32::INFINITY, f32::min);
+        assert!(
+            (polyline.distance(point) - brute_force).abs() < 1e-5,
+            "{point}"
+        );
+    }
+}
+
+#[test]
+fn test_polyline_sdf() {
+    use crate::{adder::Manifolds, Ellipsoid};
+
+    // A rail bent into a V, resting with both ends in a wide flat ellipsoid
+    let polyline = Polyline::new(
+        [
+            Vec3::new(-2., 0., 0.),
+            Vec3::new(0., 1., 0.),
+            Vec3::new(2., 0., 0.),
+        ],
+        0.2,
+    );
+    let ground = Ellipsoid::new(Vec3::new(50., 1., 50.));
+    let ground_iso = ScaledIsometry3d::new(Isometry3d::from_translation(Vec3::NEG_Y), 1.);
+    let polyline_iso = ScaledIsometry3d::new(Isometry3d::from_translation(Vec3::Y * 0.1), 1.);
+
+    let mut contacts = Vec::<Contact>::default();
+    polyline.get_collisions(
+        polyline_iso,
+        &ground,
+        ground_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+
+    for contact in contacts.iter() {
+        assert!(contact.normal.abs_diff_eq(Vec3::NEG_Y, 0.05), "{contact:?}");
+        assert!(contact.penetration < 0.12, "{contact:?}");
+    }
+    // Both ends rest 0.1 deep, with shallower contacts where the rail rises out of the ground
+    for side in [-1., 1.] {
+        let deepest = contacts
+            .iter()
+            .filter(|c| c.point.x * side > 1.5)
+            .map(|c| c.penetration)
+            .fold(f32::NEG_INFINITY, f32::max);
+        assert!((deepest - 0.1).abs() < 0.02, "{contacts:?}");
+    }
+}
@@ -1,23 +1,80 @@
-use std::ops::{Add, Deref, DerefMut, Sub};
+use std::{
+    cell::RefCell,
+    ops::{Add, Deref, DerefMut, Sub},
+};
 
 use approx::ulps_eq;
-use bevy::math::{primitives::*, Isometry3d, Vec3, Vec3A};
+use bevy::{
+    math::{
+        bounding::{Aabb3d, Bounded3d, BoundingSphere},
+        primitives::*,
+        FloatExt, Isometry3d, Mat3, Quat, Vec3, Vec3A,
+    },
+    prelude::{Component, Resource},
+    reflect::Reflect,
+};
+use bevy_math::ops;
 use bevy_prototype_sdf::{ExecutableSdf3d, Isometry};
 
 #[cfg(test)]
 use crate::adder::Manifolds;
 #[cfg(test)]
-use bevy::math::Quat;
-#[cfg(test)]
 use std::f32::consts::PI;
 
-use crate::adder::{Contact, ManifoldAdder};
+use crate::{
+    adder::{Contact, ManifoldAdder},
+    scratch::with_scratch,
+};
 
+/// Placement of an SDF or shape whose local space is uniformly scaled by `scale`.
+#[derive(Clone, Copy, Debug)]
 pub struct ScaledIsometry3d {
     pub iso: Isometry3d,
     pub scale: f32,
 }
 
+impl ScaledIsometry3d {
+    pub const IDENTITY: Self = Self {
+        iso: Isometry3d::IDENTITY,
+        scale: 1.,
+    };
+
+    pub fn new(iso: Isometry3d, scale: f32) -> Self {
+        Self { iso, scale }
+    }
+
+    /// Moves a point into the unscaled local space.
+    pub fn local_point(&self, point: impl Into<Vec3A>) -> Vec3 {
+        self.to_local(point.into()).into()
+    }
+
+    /// Like [`local_point`](Self::local_point), staying in [`Vec3A`] for the contact math.
+    pub(crate) fn to_local(self, point: Vec3A) -> Vec3A {
+        self.rotation.inverse() * (point - self.translation) / self.scale
+    }
+}
+
+/// Distance of an SDF at a point kept in [`Vec3A`], like all points in the contact math.
+///
+/// SDFs are evaluated on [`Vec3`], so contacts only convert where they evaluate them, through this
+/// and [`normal_at`], and where they're pushed as a [`Contact`].
+#[inline]
+pub(crate) fn distance_at(sdf: &impl LocalSdf, local_point: Vec3A) -> f32 {
+    sdf.distance(local_point.into())
+}
+
+/// The normalized gradient of an SDF at a point, see [`distance_at`].
+#[inline]
+pub(crate) fn normal_at(sdf: &impl LocalSdf, local_point: Vec3A) -> Vec3A {
+    Vec3A::from(sdf.gradient(local_point.into())).normalize_or(Vec3A::Y)
+}
+
+impl From<Isometry3d> for ScaledIsometry3d {
+    fn from(iso: Isometry3d) -> Self {
+        Self { iso, scale: 1. }
+    }
+}
+
 impl Deref for ScaledIsometry3d {
     type Target = Isometry3d;
     fn deref(&self) -> &Self::Target {
@@ -31,352 +88,2341 @@ impl DerefMut for ScaledIsometry3d {
     }
 }
 
-pub trait Collidable {
-    type Isometry;
+/// Marching parameters for SDF asset colliders.
+///
+/// As a resource this applies to every collider, as a component it overrides the resource for the
+/// collider on that entity, like a coarse setting for a huge background SDF.
+#[derive(Resource, Component, Debug, Clone, Copy, PartialEq)]
+pub struct SdfMarchQuality {
+    /// Smallest step taken along a march, larger steps pass grazing surfaces faster
+    pub min_step: f32,
+    /// Smallest step as a fraction of the length of a march, so long casts skimming along a
+    /// surface take a bounded number of steps. These steps never pass the surface itself, and
+    /// hits after them are bisected back to within `min_step`
+    pub length_step: f32,
+    /// Extra distance within which a march counts as touching the surface
+    pub epsilon: f32,
+    /// A march gives up after this many steps, using the closest approach so far
+    pub max_iterations: u32,
+    /// How contact normals and hit normals are computed
+    pub gradient: SdfGradient,
+    /// Points further than this outside the bounds of an SDF asset use the distance to the bounds
+    /// instead of evaluating the SDF, in its local units. The bounds are never further than the
+    /// surface, so only contacts and hits beyond this distance become less precise. Disabled if
+    /// `None`
+    pub far_field: Option<f32>,
 }
 
-impl Collidable for Sphere {
-    type Isometry = Isometry3d;
+impl SdfMarchQuality {
+    pub const DEFAULT: Self = Self {
+        min_step: 0.001,
+        length_step: 0.,
+        epsilon: 0.,
+        max_iterations: u32::MAX,
+        gradient: SdfGradient::Analytic,
+        far_field: None,
+    };
 }
 
-impl Collidable for Capsule3d {
-    type Isometry = Isometry3d;
+/// How the gradient of an SDF is computed.
+///
+/// Sampling the distance around a point averages out the noise of baked grids and noisy
+/// procedural SDFs, which otherwise jitters the normals. Sampled gradients replace the normal
+/// smoothing of the collider.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SdfGradient {
+    /// The gradient the SDF computes itself
+    #[default]
+    Analytic,
+    /// Central differences along each axis, `epsilon` away from the point, using 6 samples
+    CentralDifferences { epsilon: f32 },
+    /// Differences between the corners of a tetrahedron `epsilon` away from the point, using 4
+    /// samples
+    Tetrahedral { epsilon: f32 },
 }
 
-impl Collidable for ExecutableSdf3d<'_> {
-    type Isometry = ScaledIsometry3d;
+impl SdfGradient {
+    pub fn estimate(&self, sdf: &impl LocalSdf, local_point: Vec3) -> Vec3 {
+        match *self {
+            Self::Analytic => sdf.gradient(local_point),
+            Self::CentralDifferences { epsilon } => {
+                let axis = |axis: Vec3| {
+                    sdf.distance(local_point + axis * epsilon)
+                        - sdf.distance(local_point - axis * epsilon)
+                };
+                Vec3::new(axis(Vec3::X), axis(Vec3::Y), axis(Vec3::Z)) / (2. * epsilon)
+            }
+            Self::Tetrahedral { epsilon } => {
+                const CORNERS: [Vec3; 4] = [
+                    Vec3::new(1., -1., -1.),
+                    Vec3::new(-1., -1., 1.),
+                    Vec3::new(-1., 1., -1.),
+                    Vec3::new(1., 1., 1.),
+                ];
+                CORNERS
+                    .into_iter()
+                    .map(|corner| corner * sdf.distance(local_point + corner * epsilon))
+                    .sum::<Vec3>()
+                    / (4. * epsilon)
+            }
+        }
+    }
 }
 
-pub trait Collider<Target: Collidable>: Collidable {
-    fn get_collisions<T: From<Contact>>(
-        &self,
-        self_iso: Self::Isometry,
-        other: &Target,
-        other_iso: Target::Isometry,
-        adder: ManifoldAdder<T>,
-        pred_dist: f32,
-    );
+impl Default for SdfMarchQuality {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
 }
 
-impl Collider<Sphere> for Sphere {
-    fn get_collisions<T: From<Contact>>(
-        &self,
-        self_iso: Isometry3d,
-        other: &Self,
-        other_iso: Isometry3d,
-        mut adder: ManifoldAdder<T>,
-        pred_dist: f32,
-    ) {
-        let offset = self_iso.translation.distance(other_iso.translation);
-        let dist = offset - self.radius - other.radius;
-        if dist > pred_dist {
-            return;
+/// A signed distance function in the local space of a collider.
+pub trait LocalSdf {
+    fn distance(&self, local_point: Vec3) -> f32;
+    fn gradient(&self, local_point: Vec3) -> Vec3;
+
+    /// Called after marching along this SDF, used to collect diagnostics
+    fn record_march_iterations(&self, _iterations: u32) {}
+
+    /// Parameters used when marching along this SDF
+    fn march_quality(&self) -> SdfMarchQuality {
+        SdfMarchQuality::DEFAULT
+    }
+}
+
+impl<S: LocalSdf> LocalSdf for &S {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        (**self).distance(local_point)
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        (**self).gradient(local_point)
+    }
+
+    fn record_march_iterations(&self, iterations: u32) {
+        (**self).record_march_iterations(iterations);
+    }
+
+    fn march_quality(&self) -> SdfMarchQuality {
+        (**self).march_quality()
+    }
+}
+
+impl LocalSdf for ExecutableSdf3d<'_> {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        ExecutableSdf3d::distance(self, local_point)
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        ExecutableSdf3d::gradient(self, local_point)
+    }
+}
+
+/// Blends the gradients of samples within `radius`, so normals change gradually over the width
+/// of an edge instead of flipping between faces.
+pub(crate) struct SmoothedNormals<'a, S> {
+    sdf: &'a S,
+    radius: f32,
+}
+
+impl<'a, S: LocalSdf> SmoothedNormals<'a, S> {
+    pub fn new(sdf: &'a S, radius: f32) -> Self {
+        Self { sdf, radius }
+    }
+}
+
+impl<S: LocalSdf> LocalSdf for SmoothedNormals<'_, S> {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        self.sdf.distance(local_point)
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        let gradient = self.sdf.gradient(local_point);
+        if self.radius <= 0. {
+            return gradient;
         }
+        let offsets = [
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Vec3::Z,
+            Vec3::NEG_Z,
+        ];
+        offsets
+            .into_iter()
+            .map(|offset| self.sdf.gradient(local_point + offset * self.radius))
+            .fold(gradient, Add::add)
+            .normalize_or(gradient)
+    }
 
-        let mut self_to_other = (other_iso.translation - self_iso.translation) / offset;
-        if self_to_other == Vec3A::NAN {
-            self_to_other = Vec3A::Y;
+    fn record_march_iterations(&self, iterations: u32) {
+        self.sdf.record_march_iterations(iterations);
+    }
+
+    fn march_quality(&self) -> SdfMarchQuality {
+        self.sdf.march_quality()
+    }
+}
+
+/// Marches the wrapped SDF with the given quality instead of the default.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WithMarchQuality<S> {
+    pub sdf: S,
+    quality: SdfMarchQuality,
+    /// Bounds of the surface used for [`SdfMarchQuality::far_field`]
+    pub bounds: Option<Aabb3d>,
+}
+
+impl<S: LocalSdf> WithMarchQuality<S> {
+    pub fn new(sdf: S, quality: SdfMarchQuality) -> Self {
+        Self {
+            sdf,
+            quality,
+            bounds: None,
         }
+    }
 
-        let half_dist = dist * 0.5;
-        let anchor1 = self_to_other * (self.radius + half_dist);
-        let world_point = self_iso.translation + anchor1;
-        let anchor2 = world_point - other_iso.translation;
+    pub fn with_bounds(mut self, bounds: Option<Aabb3d>) -> Self {
+        self.bounds = bounds;
+        self
+    }
 
-        adder.push(world_point, anchor1, anchor2, self_to_other, -dist);
+    /// The point on the bounds closest to `local_point`, if it's in the far field.
+    fn far_field_point(&self, local_point: Vec3) -> Option<Vec3> {
+        let far_field = self.quality.far_field?;
+        let bounds = self.bounds?;
+        let closest = local_point.clamp(bounds.min.into(), bounds.max.into());
+        (closest.distance_squared(local_point) > far_field * far_field).then_some(closest)
+    }
+}
+
+impl<S: LocalSdf> LocalSdf for WithMarchQuality<S> {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        match self.far_field_point(local_point) {
+            Some(closest) => closest.distance(local_point),
+            None => self.sdf.distance(local_point),
+        }
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        match self.far_field_point(local_point) {
+            Some(closest) => (local_point - closest).normalize_or(Vec3::Y),
+            None => self.quality.gradient.estimate(&self.sdf, local_point),
+        }
+    }
+
+    fn record_march_iterations(&self, iterations: u32) {
+        self.sdf.record_march_iterations(iterations);
+    }
+
+    fn march_quality(&self) -> SdfMarchQuality {
+        self.quality
+    }
+}
+
+const SDF_SDF_ITERATIONS: u32 = 32;
+
+/// Finds the deepest point inside both SDFs, or the closest approach if they don't overlap, by
+/// descending the larger of both distances from a few seeds.
+///
+/// Only a single contact is generated, which is enough for overlap queries.
+pub(crate) fn sdf_sdf_contact<T: From<Contact>>(
+    sdf1: &impl LocalSdf,
+    iso1: ScaledIsometry3d,
+    sdf2: &impl LocalSdf,
+    iso2: ScaledIsometry3d,
+    mut adder: ManifoldAdder<T>,
+    pred_dist: f32,
+) {
+    adder.set_frames(iso1, iso2);
+    // World space distance and gradient of both SDFs
+    let eval = |point: Vec3A| {
+        let local1 = iso1.to_local(point);
+        let local2 = iso2.to_local(point);
+        (
+            (
+                distance_at(sdf1, local1) * iso1.scale,
+                iso1.rotation * Vec3A::from(sdf1.gradient(local1.into())),
+            ),
+            (
+                distance_at(sdf2, local2) * iso2.scale,
+                iso2.rotation * Vec3A::from(sdf2.gradient(local2.into())),
+            ),
+        )
+    };
+
+    let origin1 = iso1.translation;
+    let origin2 = iso2.translation;
+    let mut best: Option<(f32, Vec3A)> = None;
+    for seed in [(origin1 + origin2) * 0.5, origin2, origin1] {
+        let mut point = seed;
+        for _ in 0..SDF_SDF_ITERATIONS {
+            let ((d1, g1), (d2, g2)) = eval(point);
+            let (max, min) = (d1.max(d2), d1.min(d2));
+            if best.is_none_or(|(best_max, _)| max < best_max) {
+                best = Some((max, point));
+            }
+            // Move towards where both distances are equal, which is halfway between both
+            // surfaces for shapes that are close, and skip ahead while outside both
+            let step = (max - min) * 0.5 + min.max(0.);
+            if step < MINIMUM_STEP * 0.1 {
+                break;
+            }
+            let gradient = if d1 >= d2 { g1 } else { g2 };
+            point -= gradient.normalize_or_zero() * step;
+        }
     }
+
+    let Some((_, point)) = best else {
+        return;
+    };
+    let ((d1, g1), (d2, g2)) = eval(point);
+    let penetration = -(d1 + d2);
+    if penetration < -pred_dist {
+        return;
+    }
+    let normal = (g1.normalize_or_zero() - g2.normalize_or_zero()).normalize_or(Vec3A::Y);
+    adder.push(
+        point,
+        point - iso1.translation,
+        point - iso2.translation,
+        normal,
+        penetration,
+    );
 }
 
 #[test]
-fn test_sphere_sphere() {
-    let s1 = Sphere { radius: 1.2 };
-    let s1_iso = Isometry3d {
-        translation: Vec3A::new(-1., 0.3, -0.4),
-        rotation: Quat::from_rotation_x(PI / 2.),
+fn test_sdf_sdf_contact() {
+    let sdf1 = Ellipsoid::new(Vec3::new(2., 0.5, 2.));
+    let sdf2 = Ellipsoid::new(Vec3::new(1., 0.3, 0.3));
+    let iso1 = || ScaledIsometry3d {
+        iso: Isometry3d::IDENTITY,
+        scale: 1.,
     };
-    let s2 = Sphere { radius: 0.8 };
-    let s2_iso = Isometry3d {
-        translation: Vec3A::new(1., 0.5, 0.1),
-        rotation: Quat::from_rotation_z(-PI / 2.),
+    // A rotated ellipsoid standing on its tip, dipping 0.1 into the top of the other
+    let iso2 = |height: f32| ScaledIsometry3d {
+        iso: Isometry3d::new(Vec3::new(0., height, 0.), Quat::from_rotation_z(PI / 2.)),
+        scale: 1.,
     };
+
     let mut contacts = Vec::<Contact>::default();
-    let manifolds = Manifolds(&mut contacts);
-    let adder = ManifoldAdder::normal(manifolds);
-    s1.get_collisions(s1_iso, &s2, s2_iso, adder, 0.);
-    panic!("{:?}", contacts);
+    sdf_sdf_contact(
+        &sdf1,
+        iso1(),
+        &sdf2,
+        iso2(1.4),
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+    assert_eq!(contacts.len(), 1);
+    assert!((contacts[0].penetration - 0.1).abs() < 0.02, "{contacts:?}");
+    assert!(
+        contacts[0].normal.abs_diff_eq(Vec3::Y, 0.05),
+        "{contacts:?}"
+    );
+
+    contacts.clear();
+    sdf_sdf_contact(
+        &sdf1,
+        iso1(),
+        &sdf2,
+        iso2(1.8),
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+    assert!(contacts.is_empty());
 }
 
-impl Collider<ExecutableSdf3d<'_>> for Sphere {
-    fn get_collisions<T: From<Contact>>(
-        &self,
-        self_iso: Isometry3d,
-        sdf: &ExecutableSdf3d,
-        sdf_iso: ScaledIsometry3d,
-        mut adder: ManifoldAdder<T>,
-        pred_dist: f32,
-    ) {
-        let sdf_local_pos = sdf_iso.rotation.inverse()
-            * (self_iso.translation - sdf_iso.translation)
-            / sdf_iso.scale;
-        let distance = sdf.distance(Vec3::from(sdf_local_pos)) * sdf_iso.scale;
-        if distance < self.radius + pred_dist {
-            let gradient = Vec3A::from(sdf.gradient(Vec3::from(sdf_local_pos)));
-            let world_normal = sdf_iso.rotation * -gradient;
-
-            let pen = self.radius - distance;
-            let anchor1 = world_normal * (self.radius - pen * 0.5);
-            let world_point = self_iso.translation + anchor1;
-            let anchor2 = world_point - sdf_iso.translation;
+/// Blends the wrapped SDF towards a target SDF and offsets its surface, driven per entity by
+/// [`SdfParams`](crate::SdfParams).
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Parameterized<S, T> {
+    pub sdf: S,
+    target: Option<T>,
+    blend: f32,
+    inflate: f32,
+}
 
-            adder.push(world_point, anchor1, anchor2, world_normal, pen);
+impl<S: LocalSdf, T: LocalSdf> Parameterized<S, T> {
+    pub fn new(sdf: S, target: Option<T>, blend: f32, inflate: f32) -> Self {
+        Self {
+            sdf,
+            target: target.filter(|_| blend != 0.),
+            blend,
+            inflate,
         }
     }
 }
 
-impl Collider<Capsule3d> for Sphere {
-    fn get_collisions<T: From<Contact>>(
-        &self,
-        self_iso: Isometry3d,
-        other: &Capsule3d,
-        other_iso: Isometry3d,
-        adder: ManifoldAdder<T>,
-        pred_dist: f32,
-    ) {
-        let capsule_up = other_iso.rotation * Vec3A::Y;
-        let t = (self_iso.translation - other_iso.translation)
-            .dot(capsule_up)
-            .clamp(-other.half_length, other.half_length);
-        let capsule_sphere = Sphere {
-            radius: other.radius,
+impl<S: LocalSdf, T: LocalSdf> LocalSdf for Parameterized<S, T> {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        let distance = self.sdf.distance(local_point);
+        let distance = match &self.target {
+            Some(target) => distance.lerp(target.distance(local_point), self.blend),
+            None => distance,
         };
-        let capsule_sphere_iso = other_iso.translate(Vec3::new(0., t, 0.));
-        self.get_collisions(
-            self_iso,
-            &capsule_sphere,
-            capsule_sphere_iso,
-            adder,
-            pred_dist,
-        );
+        distance - self.inflate
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        let gradient = self.sdf.gradient(local_point);
+        match &self.target {
+            Some(target) => gradient.lerp(target.gradient(local_point), self.blend),
+            None => gradient,
+        }
+    }
+
+    fn record_march_iterations(&self, iterations: u32) {
+        self.sdf.record_march_iterations(iterations);
+    }
+
+    fn march_quality(&self) -> SdfMarchQuality {
+        self.sdf.march_quality()
     }
 }
 
-impl Collider<Capsule3d> for Capsule3d {
-    fn get_collisions<T: From<Contact>>(
-        &self,
-        self_iso: Isometry3d,
-        other: &Self,
-        other_iso: Isometry3d,
-        mut adder: ManifoldAdder<T>,
-        pred_dist: f32,
-    ) {
-        let up1 = self_iso.rotation * Vec3::Y;
-        let up2 = other_iso.rotation * Vec3::Y;
-        let bottom1 = Vec3::from(self_iso.translation) - up1 * self.half_length;
-        let bottom2 = Vec3::from(other_iso.translation) - up2 * other.half_length;
-        let to_end1 = up1 * self.half_length * 2.;
-        let to_end2 = up2 * other.half_length * 2.;
-        let seg1 = (bottom1, to_end1);
-        let seg2 = (bottom2, to_end2);
-        let (p1, p2) = closest_points_on_segments(seg1, seg2);
+#[test]
+fn test_parameterized_sdf() {
+    let sdf = Parameterized::new(BoxSdf(Vec3::ONE), Some(BoxSdf(Vec3::splat(3.))), 0.5, 0.25);
+    // Halfway between both boxes, grown by the inflation
+    assert!((sdf.distance(Vec3::new(4., 0., 0.)) - 1.75).abs() < 1e-5);
+    assert!(sdf
+        .gradient(Vec3::new(4., 0., 0.))
+        .abs_diff_eq(Vec3::X, 1e-5));
 
-        let wp1 = bottom1 + to_end1 * p1;
-        let wp2 = bottom2 + to_end2 * p2;
-        let offset = wp1.distance(wp2);
-        let dist = offset - self.radius - other.radius;
-        if dist > pred_dist {
-            return;
+    let unblended = Parameterized::new(BoxSdf(Vec3::ONE), Some(BoxSdf(Vec3::splat(3.))), 0., 0.);
+    assert!((unblended.distance(Vec3::new(4., 0., 0.)) - 3.).abs() < 1e-5);
+}
+
+/// A band of an SDF treated as solid, in the SDF's own units, like a hollow container with walls
+/// from `inner` to `outer`.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct SdfShell {
+    pub inner: f32,
+    pub outer: f32,
+}
+
+impl SdfShell {
+    pub fn new(inner: f32, outer: f32) -> Self {
+        Self {
+            inner: inner.min(outer),
+            outer: inner.max(outer),
+        }
+    }
+
+    fn center(&self) -> f32 {
+        (self.inner + self.outer) * 0.5
+    }
+
+    fn half_thickness(&self) -> f32 {
+        (self.outer - self.inner) * 0.5
+    }
+}
+
+/// Turns the wrapped SDF inside out if `inverted`, then only treats its [`SdfShell`] as solid,
+/// if any. Outside its region, if any, the SDF is only sampled on the border of the region.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Shelled<S> {
+    pub sdf: S,
+    shell: Option<SdfShell>,
+    inverted: bool,
+    region: Option<Aabb3d>,
+}
+
+impl<S: LocalSdf> Shelled<S> {
+    pub fn new(sdf: S, shell: Option<SdfShell>, inverted: bool) -> Self {
+        Self {
+            sdf,
+            shell,
+            inverted,
+            region: None,
+        }
+    }
+
+    pub fn with_region(mut self, region: Option<Aabb3d>) -> Self {
+        self.region = region;
+        self
+    }
+
+    // The point closest to `local_point` in the region, where the SDF is sampled instead
+    fn sample_point(&self, local_point: Vec3) -> Vec3 {
+        match self.region {
+            Some(region) => local_point.clamp(region.min.into(), region.max.into()),
+            None => local_point,
+        }
+    }
+
+    fn shelled_distance(&self, local_point: Vec3) -> f32 {
+        let distance = self.sdf.distance(local_point) * self.sign();
+        match self.shell {
+            Some(shell) => (distance - shell.center()).abs() - shell.half_thickness(),
+            None => distance,
+        }
+    }
+
+    fn shelled_gradient(&self, local_point: Vec3) -> Vec3 {
+        let gradient = self.sdf.gradient(local_point) * self.sign();
+        match self.shell {
+            // Points inside the inner wall push further in
+            Some(shell) if self.sdf.distance(local_point) * self.sign() < shell.center() => {
+                -gradient
+            }
+            _ => gradient,
+        }
+    }
+
+    fn sign(&self) -> f32 {
+        if self.inverted {
+            -1.
+        } else {
+            1.
+        }
+    }
+}
+
+impl<S: LocalSdf> LocalSdf for Shelled<S> {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        let sample = self.sample_point(local_point);
+        let distance = self.shelled_distance(sample);
+        let outside = sample.distance(local_point);
+        // The surface in the region is at least as far as its closest point is from the border
+        if distance >= 0. {
+            ops::hypot(distance, outside)
+        } else {
+            distance + outside
+        }
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        let sample = self.sample_point(local_point);
+        let gradient = self.shelled_gradient(sample);
+        if sample == local_point {
+            return gradient;
+        }
+        // Moving along a clamped axis only changes the distance to the region
+        let unclamped = Vec3::select(sample.cmpeq(local_point), gradient, Vec3::ZERO);
+        let offset = local_point - sample;
+        let distance = self.shelled_distance(sample);
+        if distance >= 0. {
+            (unclamped * distance + offset).normalize_or(gradient)
+        } else {
+            (unclamped + offset.normalize()).normalize_or(gradient)
+        }
+    }
+
+    fn record_march_iterations(&self, iterations: u32) {
+        self.sdf.record_march_iterations(iterations);
+    }
+
+    fn march_quality(&self) -> SdfMarchQuality {
+        self.sdf.march_quality()
+    }
+}
+
+#[test]
+fn test_region_sdf() {
+    let sdf = Shelled::new(BoxSdf(Vec3::new(100., 1., 100.)), None, false)
+        .with_region(Some(Aabb3d::new(Vec3::ZERO, Vec3::splat(5.))));
+
+    // Inside the region the SDF is unchanged
+    assert!((sdf.distance(Vec3::new(1., 3., 2.)) - 2.).abs() < 1e-5);
+    assert!(sdf
+        .gradient(Vec3::new(1., 3., 2.))
+        .abs_diff_eq(Vec3::Y, 1e-4));
+    // Outside it only the surface in the region counts, sampled on the border
+    let outside = Vec3::new(8., 3., 0.);
+    assert!((sdf.distance(outside) - 13f32.sqrt()).abs() < 1e-5);
+    assert!(sdf
+        .gradient(outside)
+        .abs_diff_eq(Vec3::new(3., 2., 0.).normalize(), 1e-4));
+    let beside = Vec3::new(8., 0., 0.);
+    assert!((sdf.distance(beside) - 2.).abs() < 1e-5);
+    assert!(sdf
+        .gradient(beside)
+        .abs_diff_eq(Vec3::new(1., 1., 0.).normalize(), 1e-4));
+}
+
+#[test]
+fn test_shelled_sdf() {
+    let sdf = Shelled::new(
+        BoxSdf(Vec3::splat(2.)),
+        Some(SdfShell::new(-0.5, 0.)),
+        false,
+    );
+
+    assert!(sdf.distance(Vec3::ZERO) > 0.);
+    assert!((sdf.distance(Vec3::ZERO) - 1.5).abs() < 1e-5);
+    assert!(sdf.distance(Vec3::new(1.8, 0., 0.)) < 0.);
+    assert!((sdf.distance(Vec3::new(3., 0., 0.)) - 1.).abs() < 1e-5);
+    assert!(sdf
+        .gradient(Vec3::new(1., 0., 0.))
+        .abs_diff_eq(Vec3::NEG_X, 1e-4));
+    assert!(sdf
+        .gradient(Vec3::new(3., 0., 0.))
+        .abs_diff_eq(Vec3::X, 1e-4));
+
+    // A sphere resting on the inner floor of the container
+    let sphere = Sphere::new(0.5);
+    let mut contacts = Vec::<Contact>::default();
+    sphere.get_collisions(
+        Isometry3d::from_translation(Vec3::new(0., -0.95, 0.)),
+        &sdf,
+        ScaledIsometry3d {
+            iso: Isometry3d::IDENTITY,
+            scale: 1.,
+        },
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.1,
+    );
+    assert_eq!(contacts.len(), 1);
+    assert!(contacts[0].normal.abs_diff_eq(Vec3::NEG_Y, 1e-3));
+}
+
+#[test]
+fn test_inverted_sdf() {
+    let sdf = Shelled::new(BoxSdf(Vec3::splat(2.)), None, true);
+
+    assert!((sdf.distance(Vec3::ZERO) - 2.).abs() < 1e-5);
+    assert!(sdf.distance(Vec3::new(3., 0., 0.)) < 0.);
+    assert!(sdf
+        .gradient(Vec3::new(1.5, 0., 0.))
+        .abs_diff_eq(Vec3::NEG_X, 1e-4));
+
+    // A sphere resting on the floor inside the inverted box
+    let sphere = Sphere::new(0.5);
+    let mut contacts = Vec::<Contact>::default();
+    sphere.get_collisions(
+        Isometry3d::from_translation(Vec3::new(0., -1.55, 0.)),
+        &sdf,
+        ScaledIsometry3d {
+            iso: Isometry3d::IDENTITY,
+            scale: 1.,
+        },
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+    assert_eq!(contacts.len(), 1);
+    assert!(contacts[0].normal.abs_diff_eq(Vec3::NEG_Y, 1e-3));
+    assert!((contacts[0].penetration - 0.05).abs() < 1e-4);
+}
+
+#[cfg(test)]
+struct BoxSdf(Vec3);
+
+#[cfg(test)]
+impl LocalSdf for BoxSdf {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        let q = local_point.abs() - self.0;
+        q.max(Vec3::ZERO).length() + q.max_element().min(0.)
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        let q = local_point.abs() - self.0;
+        let outside = q.max(Vec3::ZERO);
+        let dir = if outside != Vec3::ZERO {
+            outside.normalize()
+        } else if q.x >= q.y && q.x >= q.z {
+            Vec3::X
+        } else if q.y >= q.z {
+            Vec3::Y
+        } else {
+            Vec3::Z
+        };
+        dir * local_point.signum()
+    }
+}
+
+#[test]
+fn test_smoothed_normals_round_edges() {
+    let sdf = BoxSdf(Vec3::ONE);
+    // Just inside the top face, near the edge with the +X face
+    let point = Vec3::new(0.99, 0.999, 0.);
+    assert_eq!(sdf.gradient(point), Vec3::Y);
+
+    let smoothed = SmoothedNormals::new(&sdf, 0.1);
+    let normal = smoothed.gradient(point);
+    assert!(normal.x > 0.05 && normal.y > 0.5);
+    assert!((smoothed.distance(point) - sdf.distance(point)).abs() < 1e-6);
+
+    // Far from edges the normal is unchanged
+    let center = Vec3::new(0., 0.999, 0.);
+    assert!(smoothed.gradient(center).abs_diff_eq(Vec3::Y, 1e-5));
+}
+
+#[test]
+fn test_sampled_gradients_ignore_noise() {
+    // A unit sphere with a tiny high frequency ripple, like the noise of a baked grid
+    struct NoisySphere;
+    impl LocalSdf for NoisySphere {
+        fn distance(&self, p: Vec3) -> f32 {
+            p.length() - 1. + 0.001 * (p.x * 1000.).sin()
+        }
+
+        fn gradient(&self, p: Vec3) -> Vec3 {
+            p.normalize() + Vec3::X * (p.x * 1000.).cos()
+        }
+    }
+
+    let point = Vec3::new(0.6, 0.8, 0.);
+    let analytic = SdfGradient::Analytic.estimate(&NoisySphere, point);
+    assert!(analytic.normalize().dot(point) < 0.9);
+    for gradient in [
+        SdfGradient::CentralDifferences { epsilon: 0.05 },
+        SdfGradient::Tetrahedral { epsilon: 0.05 },
+    ] {
+        let normal = gradient.estimate(&NoisySphere, point).normalize();
+        assert!(normal.abs_diff_eq(point, 0.05), "{gradient:?}: {normal}");
+    }
+}
+
+/// An axis-aligned ellipsoid centered on the origin.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct Ellipsoid {
+    pub half_size: Vec3,
+}
+
+impl Default for Ellipsoid {
+    fn default() -> Self {
+        Self {
+            half_size: Vec3::splat(0.5),
+        }
+    }
+}
+
+impl Ellipsoid {
+    pub fn new(half_size: Vec3) -> Self {
+        Self { half_size }
+    }
+
+    /// The point on the surface furthest along `local_direction`
+    pub fn support_point(&self, local_direction: Vec3) -> Vec3 {
+        let scaled = self.half_size * local_direction;
+        let length = scaled.length();
+        if length == 0. {
+            return Vec3::ZERO;
+        }
+        self.half_size * scaled / length
+    }
+
+    pub fn volume(&self) -> f32 {
+        4. / 3. * std::f32::consts::PI * self.half_size.x * self.half_size.y * self.half_size.z
+    }
+}
+
+impl Bounded3d for Ellipsoid {
+    fn aabb_3d(&self, isometry: impl Into<Isometry3d>) -> Aabb3d {
+        let isometry = isometry.into();
+        let rot = Mat3::from_quat(isometry.rotation);
+        let x = rot.x_axis * self.half_size.x;
+        let y = rot.y_axis * self.half_size.y;
+        let z = rot.z_axis * self.half_size.z;
+        let half_size = (x * x + y * y + z * z).map(ops::sqrt);
+        Aabb3d::new(isometry.translation, half_size)
+    }
+
+    fn bounding_sphere(&self, isometry: impl Into<Isometry3d>) -> BoundingSphere {
+        BoundingSphere::new(isometry.into().translation, self.half_size.max_element())
+    }
+}
+
+impl LocalSdf for Ellipsoid {
+    // There is no closed form distance to an ellipsoid, this is the bounded approximation from
+    // https://iquilezles.org/articles/ellipsoids/ which is exact on the surface
+    fn distance(&self, local_point: Vec3) -> f32 {
+        let k0 = (local_point / self.half_size).length();
+        let k1 = (local_point / (self.half_size * self.half_size)).length();
+        if k1 == 0. {
+            return -self.half_size.min_element();
+        }
+        k0 * (k0 - 1.) / k1
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        (local_point / (self.half_size * self.half_size)).normalize_or(Vec3::Y)
+    }
+}
+
+/// A capsule with the given radius around the line from `start` to `end`, with the position of
+/// its center and its rotation.
+pub(crate) fn capsule_between(start: Vec3, end: Vec3, radius: f32) -> (Capsule3d, Vec3, Quat) {
+    let segment = end - start;
+    let length = segment.length();
+    let rotation = if length > f32::EPSILON {
+        Quat::from_rotation_arc(Vec3::Y, segment / length)
+    } else {
+        Quat::IDENTITY
+    };
+    let capsule = Capsule3d {
+        radius,
+        half_length: length / 2.,
+    };
+    (capsule, (start + end) / 2., rotation)
+}
+
+/// A capsule between two arbitrary points, used to query thin segments.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LineSdf {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub radius: f32,
+}
+
+impl LineSdf {
+    pub(crate) fn closest_point(&self, local_point: Vec3) -> Vec3 {
+        let segment = self.end - self.start;
+        let t =
+            (local_point - self.start).dot(segment) / segment.length_squared().max(f32::EPSILON);
+        self.start + segment * t.clamp(0., 1.)
+    }
+}
+
+impl LocalSdf for LineSdf {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        local_point.distance(self.closest_point(local_point)) - self.radius
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        (local_point - self.closest_point(local_point)).normalize_or(Vec3::Y)
+    }
+}
+
+pub trait Collidable {
+    type Isometry;
+}
+
+impl Collidable for Sphere {
+    type Isometry = Isometry3d;
+}
+
+impl Collidable for Capsule3d {
+    type Isometry = Isometry3d;
+}
+
+impl<S: LocalSdf> Collidable for S {
+    type Isometry = ScaledIsometry3d;
+}
+
+pub trait Collider<Target: Collidable>: Collidable {
+    fn get_collisions<T: From<Contact>>(
+        &self,
+        self_iso: Self::Isometry,
+        other: &Target,
+        other_iso: Target::Isometry,
+        adder: ManifoldAdder<T>,
+        pred_dist: f32,
+    );
+}
+
+impl Collider<Sphere> for Sphere {
+    fn get_collisions<T: From<Contact>>(
+        &self,
+        self_iso: Isometry3d,
+        other: &Self,
+        other_iso: Isometry3d,
+        mut adder: ManifoldAdder<T>,
+        pred_dist: f32,
+    ) {
+        adder.set_frames(self_iso, other_iso);
+        let offset = self_iso.translation.distance(other_iso.translation);
+        let dist = offset - self.radius - other.radius;
+        if dist > pred_dist {
+            return;
+        }
+
+        let mut self_to_other = (other_iso.translation - self_iso.translation) / offset;
+        if self_to_other == Vec3A::NAN {
+            self_to_other = Vec3A::Y;
+        }
+
+        let half_dist = dist * 0.5;
+        let anchor1 = self_to_other * (self.radius + half_dist);
+        let world_point = self_iso.translation + anchor1;
+        let anchor2 = world_point - other_iso.translation;
+
+        adder.push(world_point, anchor1, anchor2, self_to_other, -dist);
+    }
+}
+
+#[test]
+fn test_sphere_sphere() {
+    let s1 = Sphere { radius: 1.2 };
+    let s1_iso = Isometry3d {
+        translation: Vec3A::new(-1., 0.3, -0.4),
+        rotation: Quat::from_rotation_x(PI / 2.),
+    };
+    let s2 = Sphere { radius: 0.8 };
+    let s2_iso = Isometry3d {
+        translation: Vec3A::new(1., 0.5, 0.1),
+        rotation: Quat::from_rotation_z(-PI / 2.),
+    };
+    let mut contacts = Vec::<Contact>::default();
+    s1.get_collisions(
+        s1_iso,
+        &s2,
+        s2_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+    // The spheres are slightly apart
+    assert!(contacts.is_empty());
+
+    s1.get_collisions(
+        s1_iso,
+        &s2,
+        s2_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.1,
+    );
+    assert_eq!(contacts.len(), 1);
+    let offset = Vec3::new(2., 0.2, 0.5);
+    assert!((contacts[0].penetration + offset.length() - 2.).abs() < 1e-5);
+    assert!(contacts[0].normal.abs_diff_eq(offset.normalize(), 1e-5));
+}
+
+impl<S: LocalSdf> Collider<S> for Sphere {
+    fn get_collisions<T: From<Contact>>(
+        &self,
+        self_iso: Isometry3d,
+        sdf: &S,
+        sdf_iso: ScaledIsometry3d,
+        mut adder: ManifoldAdder<T>,
+        pred_dist: f32,
+    ) {
+        adder.set_frames(self_iso, sdf_iso);
+        let sdf_local_pos = sdf_iso.to_local(self_iso.translation);
+        let distance = distance_at(sdf, sdf_local_pos) * sdf_iso.scale;
+        if distance >= self.radius + pred_dist {
+            return;
+        }
+
+        let world_normal = sdf_iso.rotation * -normal_at(sdf, sdf_local_pos);
+
+        if distance < -self.radius {
+            // The whole sphere is inside the surface, resolving the full depth in one step would
+            // launch it. The contact is placed on the sphere and pushes it out by a constant
+            // depth, the same as where the shallow overlap ends, until it is out of the surface
+            let pen = self.radius * CONTAINED_PENETRATION;
+            let anchor1 = world_normal * self.radius;
+            let world_point = self_iso.translation + anchor1;
+            let anchor2 = world_point - sdf_iso.translation;
+
+            adder.push(world_point, anchor1, anchor2, world_normal, pen);
+            return;
+        }
+
+        let pen = self.radius - distance;
+        let anchor1 = world_normal * (self.radius - pen * 0.5);
+        let world_point = self_iso.translation + anchor1;
+        let anchor2 = world_point - sdf_iso.translation;
+
+        adder.push(world_point, anchor1, anchor2, world_normal, pen);
+    }
+}
+
+/// Penetration reported for shapes fully inside an SDF, relative to their radius, whatever their
+/// actual depth
+const CONTAINED_PENETRATION: f32 = 2.;
+
+#[test]
+fn test_sphere_sdf_shallow() {
+    let sphere = Sphere { radius: 0.5 };
+    let sdf = Ellipsoid::new(Vec3::splat(2.));
+    let sdf_iso = ScaledIsometry3d {
+        iso: Isometry3d::IDENTITY,
+        scale: 1.,
+    };
+
+    let mut contacts = Vec::<Contact>::default();
+    sphere.get_collisions(
+        Isometry3d::from_translation(Vec3::new(0., 2.3, 0.)),
+        &sdf,
+        sdf_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+
+    assert_eq!(contacts.len(), 1);
+    assert!((contacts[0].penetration - 0.2).abs() < 1e-4);
+    assert!(contacts[0].normal.abs_diff_eq(Vec3::NEG_Y, 1e-4));
+    assert!((contacts[0].point.y - 1.9).abs() < 1e-4);
+}
+
+#[test]
+fn test_sphere_contained_in_sdf() {
+    let sphere = Sphere { radius: 0.5 };
+    let sdf = Ellipsoid::new(Vec3::splat(10.));
+    let sdf_iso = ScaledIsometry3d {
+        iso: Isometry3d::IDENTITY,
+        scale: 1.,
+    };
+
+    let mut contacts = Vec::<Contact>::default();
+    sphere.get_collisions(
+        Isometry3d::from_translation(Vec3::new(0., 4., 0.)),
+        &sdf,
+        sdf_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+
+    assert_eq!(contacts.len(), 1);
+    let contact = &contacts[0];
+    assert!((contact.penetration - 1.).abs() < 1e-4);
+    assert!(contact.normal.abs_diff_eq(Vec3::NEG_Y, 1e-4));
+    assert!(contact.anchor1.length() <= sphere.radius + 1e-4);
+
+    // Deeper inside, the push stays the same
+    contacts.clear();
+    sphere.get_collisions(
+        Isometry3d::from_translation(Vec3::new(0., 1., 0.)),
+        &sdf,
+        sdf_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+    assert_eq!(contacts.len(), 1);
+    assert!((contacts[0].penetration - 1.).abs() < 1e-4);
+
+    // The sphere center sits exactly on the SDF's center, where there is no gradient
+    contacts.clear();
+    sphere.get_collisions(
+        Isometry3d::IDENTITY,
+        &sdf,
+        ScaledIsometry3d {
+            iso: Isometry3d::IDENTITY,
+            scale: 1.,
+        },
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+    assert_eq!(contacts.len(), 1);
+    assert!(contacts[0].normal.is_finite());
+}
+
+impl Collider<Capsule3d> for Sphere {
+    fn get_collisions<T: From<Contact>>(
+        &self,
+        self_iso: Isometry3d,
+        other: &Capsule3d,
+        other_iso: Isometry3d,
+        mut adder: ManifoldAdder<T>,
+        pred_dist: f32,
+    ) {
+        adder.set_frames(self_iso, other_iso);
+        let capsule_up = other_iso.rotation * Vec3A::Y;
+        let t = (self_iso.translation - other_iso.translation)
+            .dot(capsule_up)
+            .clamp(-other.half_length, other.half_length);
+        let capsule_sphere = Sphere {
+            radius: other.radius,
+        };
+        let capsule_sphere_iso = other_iso.translate(Vec3::new(0., t, 0.));
+        self.get_collisions(
+            self_iso,
+            &capsule_sphere,
+            capsule_sphere_iso,
+            adder,
+            pred_dist,
+        );
+    }
+}
+
+impl Collider<Capsule3d> for Capsule3d {
+    fn get_collisions<T: From<Contact>>(
+        &self,
+        self_iso: Isometry3d,
+        other: &Self,
+        other_iso: Isometry3d,
+        mut adder: ManifoldAdder<T>,
+        pred_dist: f32,
+    ) {
+        adder.set_frames(self_iso, other_iso);
+        let up1 = self_iso.rotation * Vec3A::Y;
+        let up2 = other_iso.rotation * Vec3A::Y;
+        let bottom1 = self_iso.translation - up1 * self.half_length;
+        let bottom2 = other_iso.translation - up2 * other.half_length;
+        let to_end1 = up1 * self.half_length * 2.;
+        let to_end2 = up2 * other.half_length * 2.;
+        let seg1 = (bottom1, to_end1);
+        let seg2 = (bottom2, to_end2);
+        let (p1, p2) = closest_points_on_segments(seg1, seg2);
+
+        let wp1 = bottom1 + to_end1 * p1;
+        let wp2 = bottom2 + to_end2 * p2;
+        let offset = wp1.distance(wp2);
+        let dist = offset - self.radius - other.radius;
+        if dist > pred_dist {
+            return;
+        }
+
+        let world_normal = if offset == 0. {
+            Vec3A::Y
+        } else {
+            (wp2 - wp1) / offset
+        };
+
+        // The contact point lies halfway between both surfaces along the normal
+        let world_point = wp1 + world_normal * (self.radius + dist * 0.5);
+        let anchor1 = world_point - self_iso.translation;
+        let anchor2 = world_point - other_iso.translation;
+
+        adder.push(world_point, anchor1, anchor2, world_normal, -dist);
+    }
+}
+
+trait Point: Add<Output = Self> + Sub<Output = Self> + Copy {
+    fn length_squared(self) -> f32;
+    fn dot(self, rhs: Self) -> f32;
+}
+
+impl Point for Vec3A {
+    fn length_squared(self) -> f32 {
+        Vec3A::length_squared(self)
+    }
+
+    fn dot(self, rhs: Self) -> f32 {
+        Vec3A::dot(self, rhs)
+    }
+}
+
+fn closest_points_on_segments<P: Point>(
+    (origin1, to_end1): (P, P),
+    (origin2, to_end2): (P, P),
+) -> (f32, f32) {
+    // Based on `closest_points_segment_segment_with_locations_nD` from parry which is
+    // in turn inspired by RealField-time collision detection by Christer Ericson.
+    let r = origin1 - origin2;
+
+    let a = to_end1.length_squared();
+    let e = to_end2.length_squared();
+    let c = to_end1.dot(r);
+    let f = to_end2.dot(r);
+
+    let mut s;
+    let mut t;
+
+    let eps = f32::EPSILON;
+    if a <= eps && e <= eps {
+        s = 0.0;
+        t = 0.0;
+    } else if a <= eps {
+        s = 0.0;
+        t = (f / e).clamp(0., 1.);
+    } else if e <= eps {
+        t = 0.0;
+        s = (-c / a).clamp(0., 1.);
+    } else {
+        let b = to_end1.dot(to_end2);
+        let ae = a * e;
+        let bb = b * b;
+        let denom = ae - bb;
+
+        // Use absolute and ulps error to test collinearity.
+        if denom > eps && !ulps_eq!(ae, bb) {
+            s = ((b * f - c * e) / denom).clamp(0.0, 1.0);
+        } else {
+            s = 0.0;
+        }
+
+        t = (b * s + f) / e;
+
+        if t < 0.0 {
+            t = 0.0;
+            s = (-c / a).clamp(0.0, 1.0);
+        } else if t > 1.0 {
+            t = 1.0;
+            s = ((b - c) / a).clamp(0.0, 1.0);
+        }
+    }
+
+    (s, t)
+}
+
+#[test]
+fn test_capsule_capsule() {
+    let c1 = Capsule3d {
+        radius: 0.2,
+        half_length: 1.,
+    };
+    let c1_iso = Isometry3d {
+        translation: Vec3A::ZERO,
+        rotation: Quat::from_rotation_y(PI),
+    };
+
+    let c2 = Capsule3d {
+        radius: 0.3,
+        half_length: 2.,
+    };
+    let c2_iso = Isometry3d {
+        translation: Vec3A::new(0., 0.25, 0.),
+        rotation: Quat::from_rotation_z(PI / 2.),
+    };
+
+    let mut contacts = Vec::<Contact>::default();
+    c1.get_collisions(
+        c1_iso,
+        &c2,
+        c2_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+
+    // The axes cross, so the normal is arbitrary but the depth is the sum of the radii
+    assert_eq!(contacts.len(), 1);
+    let contact = &contacts[0];
+    assert!((contact.penetration - 0.5).abs() < 1e-5);
+    assert!((contact.normal.length() - 1.).abs() < 1e-5);
+    assert!(contact.point.distance(Vec3::new(0., 0.25, 0.)) < 0.05 + 1e-5);
+}
+
+#[test]
+fn test_capsule_capsule_anchors() {
+    let c1 = Capsule3d {
+        radius: 0.2,
+        half_length: 1.,
+    };
+    let c1_iso = Isometry3d::from_rotation(Quat::from_rotation_y(PI));
+    let c2 = Capsule3d {
+        radius: 0.3,
+        half_length: 2.,
+    };
+    let c2_iso = Isometry3d {
+        translation: Vec3A::new(0., 0.25, 0.4),
+        rotation: Quat::from_rotation_z(PI / 2.),
+    };
+
+    let mut contacts = Vec::<Contact>::default();
+    c1.get_collisions(
+        c1_iso,
+        &c2,
+        c2_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+
+    assert_eq!(contacts.len(), 1);
+    let contact = &contacts[0];
+    assert!((contact.penetration - 0.1).abs() < 1e-5);
+    assert!(contact.normal.abs_diff_eq(Vec3::Z, 1e-5));
+    // Both anchors end halfway between the surfaces, measured from each capsule's axis
+    assert!(contact.anchor1.abs_diff_eq(Vec3::new(0., 0.25, 0.15), 1e-5));
+    assert!(contact.anchor2.abs_diff_eq(Vec3::new(0., 0., -0.25), 1e-5));
+}
+
+#[test]
+fn test_capsule_sdf_anchors() {
+    let capsule = Capsule3d {
+        radius: 0.3,
+        half_length: 1.,
+    };
+    let capsule_iso = Isometry3d {
+        translation: Vec3A::new(0., 1.2, 0.),
+        rotation: Quat::from_rotation_z(PI / 2.),
+    };
+    // A rotated SDF must not affect anchors, which are relative to the capsule in world space
+    let sdf = BoxSdf(Vec3::new(10., 1., 10.));
+    let sdf_iso = ScaledIsometry3d {
+        iso: Isometry3d::from_rotation(Quat::from_rotation_y(PI / 2.)),
+        scale: 1.,
+    };
+
+    let mut contacts = Vec::<Contact>::default();
+    capsule.get_collisions(
+        capsule_iso,
+        &sdf,
+        sdf_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+
+    assert_eq!(contacts.len(), 2);
+    for contact in &contacts {
+        assert!((contact.penetration - 0.1).abs() < 1e-4);
+        assert!(contact.normal.abs_diff_eq(Vec3::NEG_Y, 1e-4));
+        assert!((contact.anchor1.y + 0.25).abs() < 1e-4);
+        assert!(contact.anchor1.z.abs() < 1e-4);
+        assert!((contact.anchor1.x.abs() - capsule.half_length).abs() < 1e-4);
+        assert!((contact.point.y - 0.95).abs() < 1e-4);
+    }
+}
+
+/// Prints a checksum of a fixed set of contacts and marches. Builds compare it by running this test
+/// in one profile or on one platform, then in another with `SDF_PECK_CONTACT_CHECKSUM` set to the
+/// printed value:
+///
+/// ```text
+/// cargo test --features deterministic --lib contact_checksum -- --nocapture
+/// SDF_PECK_CONTACT_CHECKSUM=0x... cargo test --release --features deterministic --lib contact_checksum
+/// ```
+#[cfg(feature = "deterministic")]
+#[test]
+fn test_contact_checksum_matches_across_builds() {
+    let mut contacts = Vec::<Contact>::default();
+    for i in 0..16 {
+        let angle = i as f32 * PI / 8.;
+        let iso1 = Isometry3d {
+            translation: Vec3A::new(0.1 * i as f32, 0.3, -0.2),
+            rotation: Quat::from_rotation_x(angle),
+        };
+        let iso2 = Isometry3d {
+            translation: Vec3A::new(0.4, 0.5 + 0.05 * i as f32, 0.1),
+            rotation: Quat::from_rotation_z(-angle),
+        };
+        let sphere = Sphere { radius: 0.7 };
+        let capsule = Capsule3d {
+            radius: 0.3,
+            half_length: 0.9,
+        };
+        sphere.get_collisions(
+            iso1,
+            &sphere,
+            iso2,
+            ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+            0.1,
+        );
+        sphere.get_collisions(
+            iso1,
+            &capsule,
+            iso2,
+            ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+            0.1,
+        );
+        capsule.get_collisions(
+            iso1,
+            &capsule,
+            iso2,
+            ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+            0.1,
+        );
+
+        // A marched SDF, through a shell and a region
+        let sdf = Shelled::new(
+            Ellipsoid::new(Vec3::new(1.2, 0.6, 0.9)),
+            Some(SdfShell::new(-0.1, 0.1)),
+            false,
+        )
+        .with_region(Some(Aabb3d::new(Vec3::ZERO, Vec3::new(1., 1., 0.5))));
+        let sdf_iso = ScaledIsometry3d {
+            iso: iso2,
+            scale: 1.5,
+        };
+        sphere.get_collisions(
+            iso1,
+            &sdf,
+            sdf_iso,
+            ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+            0.1,
+        );
+        capsule.get_collisions(
+            iso1,
+            &sdf,
+            sdf_iso,
+            ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+            0.1,
+        );
+    }
+    assert!(!contacts.is_empty());
+
+    // FNV-1a, unlike `DefaultHasher` it doesn't change between Rust versions
+    let mut checksum = 0xcbf29ce484222325_u64;
+    let mut hash = |bits: u32| {
+        for byte in bits.to_le_bytes() {
+            checksum = (checksum ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    };
+    for c in &contacts {
+        let vectors = [c.point, c.anchor1, c.anchor2, c.normal];
+        let floats = vectors.iter().flat_map(|v| v.to_array());
+        floats
+            .chain([c.penetration])
+            .for_each(|x| hash(x.to_bits()));
+    }
+
+    // Marches must take the same steps too, including ones that skim along the surface
+    let sdf = Shelled::new(Ellipsoid::new(Vec3::new(2., 0.5, 1.)), None, false);
+    for i in 0..16 {
+        let start = Vec3::new(-4., 0.6 + 0.02 * i as f32, 0.1 * i as f32 - 0.8);
+        let (result, iterations) = march_edge_counted(&sdf, start, Vec3::X, 0.1, 8.);
+        let (toi, distance) = result.either();
+        hash(toi.to_bits());
+        hash(distance.to_bits());
+        hash(iterations);
+    }
+    println!("contact checksum: {checksum:#x}");
+    if let Ok(expected) = std::env::var("SDF_PECK_CONTACT_CHECKSUM") {
+        let expected = u64::from_str_radix(expected.trim_start_matches("0x"), 16)
+            .expect("SDF_PECK_CONTACT_CHECKSUM should be a hexadecimal checksum");
+        assert_eq!(checksum, expected, "contacts differ from the other build");
+    }
+}
+
+impl<S: LocalSdf> Collider<S> for Capsule3d {
+    fn get_collisions<T: From<Contact>>(
+        &self,
+        self_iso: Isometry3d,
+        sdf: &S,
+        sdf_iso: ScaledIsometry3d,
+        adder: ManifoldAdder<T>,
+        pred_dist: f32,
+    ) {
+        capsule_sdf_collisions(self, self_iso, sdf, sdf_iso, adder, pred_dist, None);
+    }
+}
+
+/// Where the contacts of a capsule against an SDF were along its segment, reused while the
+/// capsule barely moves relative to the SDF so resting contacts only cost a distance evaluation
+/// per contact.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct SegmentWarmStart {
+    /// Center and up axis of the capsule in the local space of the SDF when the contacts were found
+    local_center: Vec3A,
+    local_up: Vec3A,
+    /// Position of each contact along the segment from the bottom end, with its local gradient
+    points: [(f32, Vec3A); 3],
+    len: usize,
+    valid: bool,
+}
+
+impl SegmentWarmStart {
+    fn matches(
+        &self,
+        local_center: Vec3A,
+        local_up: Vec3A,
+        half_length: f32,
+        tolerance: f32,
+    ) -> bool {
+        // The furthest any point on the segment moved since the contacts were found
+        let moved = self.local_center.distance(local_center)
+            + self.local_up.distance(local_up) * half_length;
+        self.valid && moved < tolerance
+    }
+
+    fn reset(&mut self, local_center: Vec3A, local_up: Vec3A) {
+        *self = Self {
+            local_center,
+            local_up,
+            valid: true,
+            ..Default::default()
+        };
+    }
+
+    fn record(&mut self, at: f32, gradient: Vec3A) {
+        if self.len < self.points.len() {
+            self.points[self.len] = (at, gradient);
+            self.len += 1;
+        }
+    }
+}
+
+/// Contacts of a capsule against an SDF, reusing the contact positions in `warm` if the capsule
+/// moved less than its tolerance relative to the SDF since they were found.
+pub(crate) fn capsule_sdf_collisions<T: From<Contact>>(
+    capsule: &Capsule3d,
+    self_iso: Isometry3d,
+    sdf: &impl LocalSdf,
+    sdf_iso: ScaledIsometry3d,
+    mut adder: ManifoldAdder<T>,
+    pred_dist: f32,
+    mut warm: Option<(&mut SegmentWarmStart, f32)>,
+) {
+    adder.set_frames(self_iso, sdf_iso);
+    let sdf_local_center = sdf_iso.to_local(self_iso.translation);
+
+    let center_dist = distance_at(sdf, sdf_local_center) * sdf_iso.scale;
+    if center_dist > capsule.radius + capsule.half_length + pred_dist {
+        return;
+    }
+
+    let world_up = self_iso.rotation * Vec3A::Y;
+
+    // When the center is this deep the whole segment is inside the SDF, so marching from the
+    // ends only finds the ends themselves. Push the capsule out along the gradient instead, by
+    // the same constant depth as a contained sphere so it isn't launched out in one step.
+    if center_dist < -capsule.half_length {
+        if let Some((warm, _)) = warm {
+            warm.valid = false;
+        }
+        let world_normal = sdf_iso.rotation * -normal_at(sdf, sdf_local_center);
+        let along = world_up.dot(world_normal);
+
+        let pen = capsule.radius * CONTAINED_PENETRATION;
+        let deepest_end = world_up * capsule.half_length * along.signum();
+        let anchor1 = deepest_end + world_normal * capsule.radius;
+        let world_point = self_iso.translation + anchor1;
+        let anchor2 = world_point - sdf_iso.translation;
+
+        adder.push(world_point, anchor1, anchor2, world_normal, pen);
+        return;
+    }
+
+    // March in the local space of the SDF, where distances and lengths are divided by its scale
+    let scale = sdf_iso.scale;
+    let sdf_local_up = sdf_iso.rotation.inverse() * world_up;
+    let local_radius = capsule.radius / scale;
+    let local_half_length = capsule.half_length / scale;
+    let bottom = sdf_local_center - sdf_local_up * local_half_length;
+    let max_local_dist = (capsule.radius + pred_dist) / scale;
+    let point_at = |at: f32| bottom + sdf_local_up * at;
+
+    // Pushes a contact for the point `at` along the segment from the bottom end, in local units
+    let mut push_contact = |at: f32, local_dist: f32, gradient: Vec3A| {
+        let world_normal = sdf_iso.rotation * -gradient;
+
+        let pen = capsule.radius - local_dist * scale;
+        let anchor1 = world_up * (at * scale - capsule.half_length)
+            + world_normal * (capsule.radius - pen * 0.5);
+        let world_point = self_iso.translation + anchor1;
+        let anchor2 = world_point - sdf_iso.translation;
+
+        adder.push(world_point, anchor1, anchor2, world_normal, pen);
+    };
+
+    if let Some((warm, tolerance)) = warm.as_mut() {
+        if warm.matches(
+            sdf_local_center,
+            sdf_local_up,
+            local_half_length,
+            *tolerance / scale,
+        ) {
+            for &(at, gradient) in &warm.points[..warm.len] {
+                let dist = distance_at(sdf, point_at(at));
+                if dist < max_local_dist {
+                    push_contact(at, dist, gradient);
+                }
+            }
+            return;
+        }
+        warm.reset(sdf_local_center, sdf_local_up);
+    }
+    let mut add_contact = |at: f32, local_dist: f32| {
+        let gradient = normal_at(sdf, point_at(at));
+        push_contact(at, local_dist, gradient);
+        if let Some((warm, _)) = warm.as_mut() {
+            warm.record(at, gradient);
+        }
+    };
+
+    let mut total = local_half_length * 2.;
+    let res = march_edge(sdf, bottom.into(), sdf_local_up.into(), local_radius, total);
+
+    let (at, dist) = match res {
+        MarchResult::Hit(toi, dist) => {
+            total = total - *toi;
+            (toi, dist)
+        }
+        MarchResult::Closest(toi, dist) => {
+            total = 0.;
+            (toi, dist)
+        }
+    };
+    let (bottom_at, bottom_dist) = (*at, dist);
+    if dist < max_local_dist {
+        add_contact(*at, dist);
+    }
+
+    let top = sdf_local_center + sdf_local_up * local_half_length;
+    let res = march_edge(sdf, top.into(), (-sdf_local_up).into(), local_radius, total);
+    let (at, dist) = res.either();
+    let top_at = local_half_length * 2. - *at;
+    if dist < max_local_dist {
+        add_contact(top_at, dist);
+    }
+
+    // Both marches stop at the first touch from their end, so a ridge under the middle of a
+    // long capsule can be deeper than either contact. Only points deeper than both ends get a
+    // contact, a flat surface is already held up by the ends.
+    if total <= 0. {
+        return;
+    }
+    if let Some((at, dist)) = deepest_on_segment(
+        sdf,
+        bottom.into(),
+        sdf_local_up.into(),
+        (bottom_at + local_radius, top_at - local_radius),
+        max_local_dist.min(bottom_dist.min(dist) - INTERIOR_CONTACT_MARGIN / scale),
+    ) {
+        add_contact(at, dist);
+    }
+}
+
+#[test]
+fn test_capsule_warm_start() {
+    struct Counting(BoxSdf, std::cell::Cell<u32>);
+    impl LocalSdf for Counting {
+        fn distance(&self, p: Vec3) -> f32 {
+            self.1.set(self.1.get() + 1);
+            self.0.distance(p)
+        }
+        fn gradient(&self, p: Vec3) -> Vec3 {
+            self.1.set(self.1.get() + 1);
+            self.0.gradient(p)
+        }
+    }
+
+    let capsule = Capsule3d {
+        radius: 0.3,
+        half_length: 1.,
+    };
+    let sdf = Counting(BoxSdf(Vec3::new(10., 1., 10.)), Default::default());
+    let sdf_iso = ScaledIsometry3d::new(Isometry3d::IDENTITY, 1.);
+    let mut warm = SegmentWarmStart::default();
+    let mut generate = |height: f32| {
+        let capsule_iso = Isometry3d {
+            translation: Vec3A::new(0., height, 0.),
+            rotation: Quat::from_rotation_z(PI / 2.),
+        };
+        let mut contacts = Vec::<Contact>::default();
+        sdf.1.set(0);
+        capsule_sdf_collisions(
+            &capsule,
+            capsule_iso,
+            &sdf,
+            sdf_iso,
+            ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+            0.,
+            Some((&mut warm, 0.01)),
+        );
+        (contacts, sdf.1.get())
+    };
+
+    let (cold, cold_evaluations) = generate(1.2);
+    // Sinking slightly deeper keeps the contacts in place, only updating their depth
+    let (warm_contacts, warm_evaluations) = generate(1.195);
+    assert_eq!(cold.len(), 2);
+    assert_eq!(warm_contacts.len(), 2);
+    assert!(warm_evaluations < cold_evaluations);
+    for (cold, warm) in cold.iter().zip(&warm_contacts) {
+        assert!((warm.penetration - cold.penetration - 0.005).abs() < 1e-4);
+        assert_eq!(warm.normal, cold.normal);
+        assert!((warm.anchor1 - cold.anchor1).abs().max_element() < 0.01);
+    }
+
+    // Moving further than the tolerance generates the contacts from scratch
+    let (_, evaluations) = generate(1.15);
+    assert!(evaluations > warm_evaluations);
+}
+
+const SEGMENT_SUBDIVISIONS: u32 = 6;
+const INTERIOR_CONTACT_MARGIN: f32 = 0.001;
+
+/// Finds the deepest point along a segment within `range`, if it's closer than `max_distance`.
+///
+/// The segment is split recursively, skipping the parts where the SDF proves nothing along them
+/// can be closer than `max_distance`. Returns the position along the segment and the distance.
+fn deepest_on_segment(
+    sdf: &impl LocalSdf,
+    start: Vec3,
+    direction: Vec3,
+    range: (f32, f32),
+    max_distance: f32,
+) -> Option<(f32, f32)> {
+    if range.1 <= range.0 {
+        return None;
+    }
+    thread_local! {
+        static SEGMENTS: RefCell<Vec<(f32, f32, u32)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    let mut deepest: Option<(f32, f32)> = None;
+    with_scratch(&SEGMENTS, |stack| {
+        stack.push((range.0, range.1, 0));
+        while let Some((from, to, depth)) = stack.pop() {
+            let middle = (from + to) * 0.5;
+            let distance = sdf.distance(start + direction * middle);
+            if deepest.is_none_or(|(_, d)| distance < d) {
+                deepest = Some((middle, distance));
+            }
+            if distance - (to - from) * 0.5 > max_distance || depth >= SEGMENT_SUBDIVISIONS {
+                continue;
+            }
+            stack.push((from, middle, depth + 1));
+            stack.push((middle, to, depth + 1));
+        }
+    });
+    deepest.filter(|&(_, distance)| distance < max_distance)
+}
+
+#[test]
+fn test_capsule_over_ridge() {
+    // A long capsule resting on a floor with both ends, with a narrow ridge under its middle
+    struct Ridge;
+    impl Ridge {
+        const RIDGE: BoxSdf = BoxSdf(Vec3::new(0.1, 0.1, 5.));
+    }
+    impl LocalSdf for Ridge {
+        fn distance(&self, p: Vec3) -> f32 {
+            p.y.min(Self::RIDGE.distance(p))
+        }
+        fn gradient(&self, p: Vec3) -> Vec3 {
+            if Self::RIDGE.distance(p) < p.y {
+                Self::RIDGE.gradient(p)
+            } else {
+                Vec3::Y
+            }
+        }
+    }
+
+    let capsule = Capsule3d {
+        radius: 0.2,
+        half_length: 2.,
+    };
+    let capsule_iso = Isometry3d {
+        translation: Vec3A::new(0., 0.19, 0.),
+        rotation: Quat::from_rotation_z(PI / 2.),
+    };
+    let sdf_iso = ScaledIsometry3d {
+        iso: Isometry3d::IDENTITY,
+        scale: 1.,
+    };
+
+    let mut contacts = Vec::<Contact>::default();
+    capsule.get_collisions(
+        capsule_iso,
+        &Ridge,
+        sdf_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+
+    let ridge = contacts
+        .iter()
+        .find(|c| c.point.x.abs() < 0.2)
+        .expect("no contact on the ridge");
+    assert!((ridge.penetration - 0.11).abs() < 0.01, "{contacts:?}");
+    assert!(ridge.normal.abs_diff_eq(Vec3::NEG_Y, 1e-3));
+}
+
+#[test]
+fn test_scaled_sdf_contacts() {
+    // An SDF at scale 2 gives the same contacts as the same shape built twice as large
+    let small = Ellipsoid::new(Vec3::new(2., 1., 3.));
+    let large = Ellipsoid::new(Vec3::new(4., 2., 6.));
+    let sdf_iso = |scale| ScaledIsometry3d {
+        iso: Isometry3d::new(Vec3::new(0.5, -1., 0.), Quat::from_rotation_x(0.3)),
+        scale,
+    };
+    let sphere = Sphere::new(0.4);
+    let sphere_iso = Isometry3d::from_translation(Vec3::new(1., 1.1, 0.5));
+    let capsule = Capsule3d {
+        radius: 0.3,
+        half_length: 1.,
+    };
+    let capsule_iso = Isometry3d::new(Vec3::new(0.5, 1.2, 0.3), Quat::from_rotation_z(1.2));
+
+    let mut scaled = Vec::<Contact>::default();
+    let mut unscaled = Vec::<Contact>::default();
+    for (sdf, scale, contacts) in [(&small, 2., &mut scaled), (&large, 1., &mut unscaled)] {
+        sphere.get_collisions(
+            sphere_iso,
+            sdf,
+            sdf_iso(scale),
+            ManifoldAdder::normal(Manifolds::new(&mut *contacts)),
+            0.,
+        );
+        capsule.get_collisions(
+            capsule_iso,
+            sdf,
+            sdf_iso(scale),
+            ManifoldAdder::normal(Manifolds::new(&mut *contacts)),
+            0.,
+        );
+    }
+
+    assert!(scaled.len() >= 2, "{scaled:?}");
+    assert_eq!(scaled.len(), unscaled.len());
+    for (a, b) in scaled.iter().zip(&unscaled) {
+        assert!(a.point.abs_diff_eq(b.point, 0.01), "{a:?} {b:?}");
+        assert!(a.anchor1.abs_diff_eq(b.anchor1, 0.01), "{a:?} {b:?}");
+        assert!(a.normal.abs_diff_eq(b.normal, 1e-3), "{a:?} {b:?}");
+        assert!((a.normal.length() - 1.).abs() < 1e-4);
+        assert!((a.penetration - b.penetration).abs() < 0.01, "{a:?} {b:?}");
+    }
+}
+
+#[test]
+fn test_contact_local_points() {
+    let ellipsoid = Ellipsoid::new(Vec3::new(2., 1., 3.));
+    let sdf_iso = ScaledIsometry3d {
+        iso: Isometry3d::new(Vec3::new(0.5, -1., 0.), Quat::from_rotation_x(0.3)),
+        scale: 2.,
+    };
+    let sphere = Sphere::new(0.4);
+    let sphere_iso = Isometry3d::from_translation(Vec3::new(1., 1.1, 0.5));
+
+    let mut contacts = Vec::<Contact>::default();
+    sphere.get_collisions(
+        sphere_iso,
+        &ellipsoid,
+        sdf_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+
+    assert_eq!(contacts.len(), 1, "{contacts:?}");
+    let contact = contacts[0];
+    let local2 = sdf_iso.local_point(contact.point);
+    assert!(
+        contact.local_point2().abs_diff_eq(local2, 1e-4),
+        "{contact:?}"
+    );
+    assert!(ellipsoid.distance(contact.local_point2()).abs() < 0.1);
+    let local1 = Vec3::from(sphere_iso.inverse_transform_point(contact.point));
+    assert!(
+        contact.local_point1().abs_diff_eq(local1, 1e-4),
+        "{contact:?}"
+    );
+}
+
+const ELLIPSOID_ITERATIONS: usize = 4;
+
+impl<S: LocalSdf> Collider<S> for Ellipsoid {
+    fn get_collisions<T: From<Contact>>(
+        &self,
+        self_iso: ScaledIsometry3d,
+        sdf: &S,
+        sdf_iso: ScaledIsometry3d,
+        mut adder: ManifoldAdder<T>,
+        pred_dist: f32,
+    ) {
+        adder.set_frames(self_iso, sdf_iso);
+        let ellipsoid = Ellipsoid::new(self.half_size * self_iso.scale);
+        let center_dist = distance_at(sdf, sdf_iso.to_local(self_iso.translation)) * sdf_iso.scale;
+        if center_dist > ellipsoid.half_size.max_element() + pred_dist {
+            return;
+        }
+
+        // Move the support point of the ellipsoid towards the deepest point in the other SDF,
+        // each step uses the SDF gradient at the previous support point as the new direction
+        let self_inv_rot = self_iso.rotation.inverse();
+        let mut deepest = None::<(Vec3A, Vec3A, f32)>;
+        let mut query_point = self_iso.translation;
+        for _ in 0..ELLIPSOID_ITERATIONS {
+            let world_normal = sdf_iso.rotation * -normal_at(sdf, sdf_iso.to_local(query_point));
+            let support = self_iso.translation
+                + self_iso.rotation
+                    * Vec3A::from(ellipsoid.support_point((self_inv_rot * world_normal).into()));
+            let distance = distance_at(sdf, sdf_iso.to_local(support)) * sdf_iso.scale;
+            if deepest.is_none_or(|(_, _, d)| distance < d) {
+                deepest = Some((support, world_normal, distance));
+            }
+            query_point = support;
+        }
+
+        let Some((support, world_normal, distance)) = deepest else {
+            return;
+        };
+        if distance > pred_dist {
+            return;
+        }
+
+        let world_point = support + world_normal * (distance * 0.5);
+        let anchor1 = world_point - self_iso.translation;
+        let anchor2 = world_point - sdf_iso.translation;
+        adder.push(world_point, anchor1, anchor2, world_normal, -distance);
+    }
+}
+
+#[test]
+fn test_ellipsoid_distance() {
+    let ellipsoid = Ellipsoid::new(Vec3::new(2., 1., 0.5));
+    for point in [Vec3::X * 2., Vec3::Y, Vec3::Z * 0.5, Vec3::NEG_X * 2.] {
+        assert!(ellipsoid.distance(point).abs() < 1e-5);
+    }
+    assert!((ellipsoid.distance(Vec3::Y * 3.) - 2.).abs() < 1e-5);
+    assert!(ellipsoid.distance(Vec3::ZERO) < 0.);
+    assert!(ellipsoid.gradient(Vec3::X * 3.).abs_diff_eq(Vec3::X, 1e-5));
+}
+
+#[test]
+fn test_ellipsoid_sdf() {
+    let ellipsoid = Ellipsoid::new(Vec3::new(2., 0.5, 0.5));
+    let ellipsoid_iso = ScaledIsometry3d {
+        iso: Isometry3d::from_rotation(Quat::from_rotation_z(PI / 2.)),
+        scale: 1.,
+    };
+    let other = Ellipsoid::new(Vec3::splat(1.));
+    let other_iso = ScaledIsometry3d {
+        iso: Isometry3d::from_translation(Vec3::new(0., 2.9, 0.)),
+        scale: 1.,
+    };
+
+    let mut contacts = Vec::<Contact>::default();
+    let manifolds = Manifolds::new(&mut contacts);
+    ellipsoid.get_collisions(
+        ellipsoid_iso,
+        &other,
+        other_iso,
+        ManifoldAdder::normal(manifolds),
+        0.,
+    );
+
+    assert_eq!(contacts.len(), 1);
+    assert!((contacts[0].penetration - 0.1).abs() < 1e-3);
+    assert!(contacts[0].normal.abs_diff_eq(Vec3::Y, 1e-3));
+}
+
+#[test]
+fn test_capsule_deep_inside_sdf() {
+    let capsule = Capsule3d {
+        radius: 0.5,
+        half_length: 1.,
+    };
+    let capsule_iso = Isometry3d::from_translation(Vec3::new(0., 5., 0.));
+    let sdf = Ellipsoid::new(Vec3::splat(10.));
+    let sdf_iso = ScaledIsometry3d {
+        iso: Isometry3d::IDENTITY,
+        scale: 1.,
+    };
+
+    let mut contacts = Vec::<Contact>::default();
+    let manifolds = Manifolds::new(&mut contacts);
+    capsule.get_collisions(
+        capsule_iso,
+        &sdf,
+        sdf_iso,
+        ManifoldAdder::normal(manifolds),
+        0.,
+    );
+
+    assert_eq!(contacts.len(), 1);
+    assert!((contacts[0].penetration - 1.).abs() < 1e-4);
+    assert!(contacts[0].normal.abs_diff_eq(Vec3::NEG_Y, 1e-4));
+
+    // Deeper inside, the push stays the same
+    contacts.clear();
+    capsule.get_collisions(
+        Isometry3d::from_translation(Vec3::new(0., 1., 0.)),
+        &sdf,
+        sdf_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
+    assert_eq!(contacts.len(), 1);
+    assert!((contacts[0].penetration - 1.).abs() < 1e-4);
+}
+
+#[cfg(feature = "tight-aabb")]
+const TIGHT_AABB_DEPTH: u32 = 3;
+
+/// Computes the world-aligned bounds of a rotated SDF from the cells of an octree over its local
+/// bounds that may contain part of the surface.
+///
+/// Cells are skipped when the SDF proves they are empty, so the result is always conservative.
+#[cfg(feature = "tight-aabb")]
+pub(crate) fn tight_aabb(sdf: &impl LocalSdf, local_aabb: Aabb3d, rotation: Quat) -> Aabb3d {
+    let rot = Mat3::from_quat(rotation);
+    let abs_rot = Mat3::from_cols(rot.x_axis.abs(), rot.y_axis.abs(), rot.z_axis.abs());
+    let mut min = Vec3::INFINITY;
+    let mut max = Vec3::NEG_INFINITY;
+
+    let center = Vec3::from(local_aabb.min + local_aabb.max) * 0.5;
+    let half_size = Vec3::from(local_aabb.max - local_aabb.min) * 0.5;
+    let mut cells = vec![(center, half_size, 0)];
+    while let Some((center, half_size, depth)) = cells.pop() {
+        let distance = sdf.distance(center);
+        let radius = half_size.length();
+        if distance > radius {
+            continue;
+        }
+        // Cells entirely inside the surface can't get any tighter by splitting them
+        if depth < TIGHT_AABB_DEPTH && distance > -radius {
+            let child_half_size = half_size * 0.5;
+            for i in 0..8 {
+                let sign = Vec3::new(
+                    if i & 1 == 0 { -1. } else { 1. },
+                    if i & 2 == 0 { -1. } else { 1. },
+                    if i & 4 == 0 { -1. } else { 1. },
+                );
+                cells.push((center + sign * child_half_size, child_half_size, depth + 1));
+            }
+            continue;
         }
 
-        let world_normal = if offset == 0. {
-            Vec3A::Y
-        } else {
-            Vec3A::from((wp2 - wp1) / offset)
+        let world_center = rot * center;
+        let world_half_size = abs_rot * half_size;
+        min = min.min(world_center - world_half_size);
+        max = max.max(world_center + world_half_size);
+    }
+
+    if min.cmpgt(max).any() {
+        return Aabb3d {
+            min: Vec3A::ZERO,
+            max: Vec3A::ZERO,
         };
+    }
+    Aabb3d {
+        min: min.into(),
+        max: max.into(),
+    }
+}
 
-        // TODO: This point might not be on the surface of the capsules even when it should be
-        let anchor1 =
-            Vec3A::from(up1) * (p1 - self.half_length) + world_normal * (self.radius + dist * 0.5);
-        let world_point = self_iso.translation + anchor1;
-        let anchor2 = world_point - other_iso.translation;
+#[cfg(feature = "tight-aabb")]
+#[test]
+fn test_tight_aabb() {
+    let sphere = Ellipsoid::new(Vec3::ONE);
+    let local_aabb = Aabb3d::new(Vec3::ZERO, Vec3::ONE);
+    let rotation = Quat::from_rotation_z(PI / 4.);
 
-        adder.push(world_point, anchor1, anchor2, world_normal, -dist);
-    }
+    // Rotating the local bounds would give a half size of sqrt(2) on X and Y
+    let tight = tight_aabb(&sphere, local_aabb, rotation);
+    assert!(tight.max.x < 1.3);
+    assert!(tight.max.x >= 1. && tight.min.y <= -1. && tight.max.z >= 1.);
 }
 
-trait Point: Add<Output = Self> + Sub<Output = Self> + Copy {
-    fn length_squared(self) -> f32;
-    fn dot(self, rhs: Self) -> f32;
+/// Many small spheres with a shared radius, stored as separate coordinate arrays so they can be
+/// moved into the space of an SDF together.
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+pub struct SphereCluster {
+    x: Vec<f32>,
+    y: Vec<f32>,
+    z: Vec<f32>,
+    pub radius: f32,
+    /// Only the deepest contacts against each collider are kept
+    pub max_contacts: usize,
 }
 
-impl Point for Vec3 {
-    fn length_squared(self) -> f32 {
-        Vec3::length_squared(self)
+impl SphereCluster {
+    pub fn new(centers: impl IntoIterator<Item = Vec3>, radius: f32) -> Self {
+        let mut cluster = Self {
+            radius,
+            max_contacts: 8,
+            ..Default::default()
+        };
+        for center in centers {
+            cluster.x.push(center.x);
+            cluster.y.push(center.y);
+            cluster.z.push(center.z);
+        }
+        cluster
     }
 
-    fn dot(self, rhs: Self) -> f32 {
-        Vec3::dot(self, rhs)
+    pub fn with_max_contacts(mut self, max_contacts: usize) -> Self {
+        self.max_contacts = max_contacts;
+        self
     }
-}
-
-fn closest_points_on_segments<P: Point>(
-    (origin1, to_end1): (P, P),
-    (origin2, to_end2): (P, P),
-) -> (f32, f32) {
-    // Based on `closest_points_segment_segment_with_locations_nD` from parry which is
-    // in turn inspired by RealField-time collision detection by Christer Ericson.
-    let r = origin1 - origin2;
 
-    let a = to_end1.length_squared();
-    let e = to_end2.length_squared();
-    let c = to_end1.dot(r);
-    let f = to_end2.dot(r);
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
 
-    let mut s;
-    let mut t;
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
 
-    let eps = f32::EPSILON;
-    if a <= eps && e <= eps {
-        s = 0.0;
-        t = 0.0;
-    } else if a <= eps {
-        s = 0.0;
-        t = (f / e).clamp(0., 1.);
-    } else if e <= eps {
-        t = 0.0;
-        s = (-c / a).clamp(0., 1.);
-    } else {
-        let b = to_end1.dot(to_end2);
-        let ae = a * e;
-        let bb = b * b;
-        let denom = ae - bb;
+    pub fn centers(&self) -> impl Iterator<Item = Vec3> + '_ {
+        (0..self.len()).map(|i| Vec3::new(self.x[i], self.y[i], self.z[i]))
+    }
 
-        // Use absolute and ulps error to test collinearity.
-        if denom > eps && !ulps_eq!(ae, bb) {
-            s = ((b * f - c * e) / denom).clamp(0.0, 1.0);
-        } else {
-            s = 0.0;
+    pub fn center_of_mass(&self) -> Vec3 {
+        if self.is_empty() {
+            return Vec3::ZERO;
         }
+        self.centers().sum::<Vec3>() / self.len() as f32
+    }
 
-        t = (b * s + f) / e;
-
-        if t < 0.0 {
-            t = 0.0;
-            s = (-c / a).clamp(0.0, 1.0);
-        } else if t > 1.0 {
-            t = 1.0;
-            s = ((b - c) / a).clamp(0.0, 1.0);
+    pub fn aabb_3d(&self, isometry: Isometry3d) -> Aabb3d {
+        let (min, max) = self
+            .centers()
+            .map(|c| isometry * Vec3A::from(c))
+            .fold((Vec3A::INFINITY, Vec3A::NEG_INFINITY), |(min, max), c| {
+                (min.min(c), max.max(c))
+            });
+        Aabb3d {
+            min: min - self.radius,
+            max: max + self.radius,
         }
     }
-
-    (s, t)
 }
 
-#[test]
-fn test_capsule_capsule() {
-    let c1 = Capsule3d {
-        radius: 0.2,
-        half_length: 1.,
-    };
-    let c1_iso = Isometry3d {
-        translation: Vec3A::ZERO,
-        rotation: Quat::from_rotation_y(PI),
-    };
-
-    let c2 = Capsule3d {
-        radius: 0.3,
-        half_length: 2.,
-    };
-    let c2_iso = Isometry3d {
-        translation: Vec3A::new(0., 0.25, 0.),
-        rotation: Quat::from_rotation_z(PI / 2.),
-    };
-
-    let mut contacts = Vec::<Contact>::default();
-    let manifolds = Manifolds(&mut contacts);
-    let adder = ManifoldAdder::normal(manifolds);
-    c1.get_collisions(c1_iso, &c2, c2_iso, adder, 0.);
+impl LocalSdf for SphereCluster {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        self.centers()
+            .map(|c| c.distance(local_point))
+            .fold(f32::INFINITY, f32::min)
+            - self.radius
+    }
 
-    panic!("{:?}", contacts);
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        let closest = self
+            .centers()
+            .min_by(|a, b| {
+                a.distance_squared(local_point)
+                    .total_cmp(&b.distance_squared(local_point))
+            })
+            .unwrap_or(Vec3::ZERO);
+        (local_point - closest).normalize_or(Vec3::Y)
+    }
 }
 
-impl Collider<ExecutableSdf3d<'_>> for Capsule3d {
+impl<S: LocalSdf> Collider<S> for SphereCluster {
     fn get_collisions<T: From<Contact>>(
         &self,
-        self_iso: Isometry3d,
-        sdf: &ExecutableSdf3d,
+        self_iso: ScaledIsometry3d,
+        sdf: &S,
         sdf_iso: ScaledIsometry3d,
         mut adder: ManifoldAdder<T>,
         pred_dist: f32,
     ) {
-        let sdf_local_center = sdf_iso.rotation.inverse()
-            * (self_iso.translation - sdf_iso.translation)
-            / sdf_iso.scale;
+        adder.set_frames(self_iso, sdf_iso);
+        // Moves every sphere into the local space of the SDF with a single combined transform
+        let inv_sdf_rotation = sdf_iso.rotation.inverse();
+        let rotation = inv_sdf_rotation * self_iso.rotation;
+        let translation = sdf_iso.to_local(self_iso.translation);
+        let scale = self_iso.scale / sdf_iso.scale;
+        let radius = self.radius * self_iso.scale;
 
-        let center_dist = sdf.distance(sdf_local_center.into());
-        if center_dist > self.radius + self.half_length + pred_dist {
-            return;
+        thread_local! {
+            static HITS: RefCell<Vec<(Vec3A, Vec3A, f32)>> = const { RefCell::new(Vec::new()) };
         }
 
-        let sdf_local_up =
-            sdf_iso.rotation.inverse() * self_iso.rotation * Vec3A::Y / sdf_iso.scale;
+        with_scratch(&HITS, |hits| {
+            for i in 0..self.len() {
+                let local_center = Vec3A::new(self.x[i], self.y[i], self.z[i]);
+                let sdf_local_pos = rotation * local_center * scale + translation;
+                let distance = distance_at(sdf, sdf_local_pos) * sdf_iso.scale;
+                if distance < radius + pred_dist {
+                    hits.push((local_center, sdf_local_pos, distance));
+                }
+            }
+            hits.sort_by(|a, b| a.2.total_cmp(&b.2));
+            hits.truncate(self.max_contacts);
+
+            for &(local_center, sdf_local_pos, distance) in hits.iter() {
+                let world_normal = sdf_iso.rotation * -normal_at(sdf, sdf_local_pos);
+                let center = self_iso.rotation * local_center * self_iso.scale;
 
-        let mut total = self.half_length * 2.;
-        let start = sdf_local_center - sdf_local_up * self.half_length;
-        let res = march_edge(sdf, start.into(), sdf_local_up.into(), self.radius, total);
+                // Spheres fully inside are pushed out gradually, like single spheres
+                let pen = (radius - distance).min(radius * CONTAINED_PENETRATION);
+                let anchor1 = center + world_normal * (radius - pen * 0.5);
+                let world_point = self_iso.translation + anchor1;
+                let anchor2 = world_point - sdf_iso.translation;
 
-        let (at, dist) = match res {
-            MarchResult::Hit(toi, dist) => {
-                total = total - *toi;
-                (toi, dist)
-            }
-            MarchResult::Closest(toi, dist) => {
-                total = 0.;
-                (toi, dist)
+                adder.push(world_point, anchor1, anchor2, world_normal, pen);
             }
-        };
+        });
+    }
+}
 
-        if dist < self.radius + pred_dist {
-            let sdf_local_min_point = (start + sdf_local_up * *at).into();
-            let gradient = Vec3A::from(sdf.gradient(sdf_local_min_point));
-            let world_normal = sdf_iso.rotation * -gradient;
+#[test]
+fn test_sphere_cluster_sdf() {
+    let cluster = SphereCluster::new(
+        [
+            Vec3::new(-1., 0., 0.),
+            Vec3::new(1., 0., 0.),
+            Vec3::new(0., 2., 0.),
+        ],
+        0.5,
+    )
+    .with_max_contacts(1);
+    let ground = BoxSdf(Vec3::new(10., 1., 10.));
+    let ground_iso = ScaledIsometry3d {
+        iso: Isometry3d::IDENTITY,
+        scale: 1.,
+    };
+    // Tilted so the sphere at -X is the deepest
+    let cluster_iso = ScaledIsometry3d {
+        iso: Isometry3d::new(Vec3::new(0., 1.4, 0.), Quat::from_rotation_z(0.1)),
+        scale: 1.,
+    };
 
-            let pen = self.radius - dist;
-            let anchor1 =
-                sdf_local_up * (*at - self.half_length) + world_normal * (self.radius - pen * 0.5);
-            let world_point = self_iso.translation + anchor1;
-            let anchor2 = world_point - sdf_iso.translation;
+    let mut contacts = Vec::<Contact>::default();
+    cluster.get_collisions(
+        cluster_iso,
+        &ground,
+        ground_iso,
+        ManifoldAdder::normal(Manifolds::new(&mut contacts)),
+        0.,
+    );
 
-            adder.push(world_point, anchor1, anchor2, world_normal, pen);
+    assert_eq!(contacts.len(), 1);
+    let contact = &contacts[0];
+    assert!(contact.point.x < 0., "{contact:?}");
+    assert!(contact.normal.abs_diff_eq(Vec3::NEG_Y, 1e-4));
+    assert!(contact.penetration > 0.1);
+    assert!((cluster.distance(Vec3::new(0., 3., 0.)) - 0.5).abs() < 1e-5);
+}
+
+const CURVATURE_STEP: f32 = 0.05;
+
+/// Estimates the sum of the principal curvatures of the surface near a point.
+///
+/// This is the Laplacian of the distance field from second-order central differences, which is
+/// positive on convex surfaces, negative in concave ones, and zero on flat ground.
+pub(crate) fn mean_curvature(sdf: &impl LocalSdf, local_point: Vec3) -> f32 {
+    let h = CURVATURE_STEP;
+    let center = sdf.distance(local_point) * 6.;
+    let neighbours = [Vec3::X, Vec3::Y, Vec3::Z]
+        .into_iter()
+        .map(|axis| sdf.distance(local_point + axis * h) + sdf.distance(local_point - axis * h))
+        .sum::<f32>();
+    (neighbours - center) / (h * h)
+}
+
+#[test]
+fn test_mean_curvature() {
+    let sphere = Ellipsoid::new(Vec3::splat(2.));
+    let curvature = mean_curvature(&sphere, Vec3::new(0., 2., 0.));
+    assert!((curvature - 1.).abs() < 0.05, "{curvature}");
+
+    let ground = BoxSdf(Vec3::new(10., 1., 10.));
+    assert!(mean_curvature(&ground, Vec3::new(0.5, 1., -2.)).abs() < 1e-3);
+}
+
+const SAMPLE_PROJECTION_STEPS: usize = 3;
+
+/// Samples points on the surface roughly `spacing` apart, returning each point with its normal.
+///
+/// The cube is subdivided only where the surface passes through, and `keep` can reject cells by
+/// their center and half size before they are subdivided further. Every leaf cell projects its
+/// center onto the surface and keeps the point if it stays inside the cell, so each cell
+/// contributes at most one sample.
+pub(crate) fn sample_surface(
+    sdf: &impl LocalSdf,
+    center: Vec3,
+    half_size: f32,
+    spacing: f32,
+    keep: impl Fn(Vec3, f32) -> bool,
+) -> Vec<(Vec3, Vec3)> {
+    let mut samples = Vec::new();
+    let mut stack = vec![(center, half_size)];
+    let sqrt_3 = 3f32.sqrt();
+    while let Some((center, half_size)) = stack.pop() {
+        if !keep(center, half_size) {
+            continue;
+        }
+        let distance = sdf.distance(center);
+        if distance.abs() > half_size * sqrt_3 {
+            continue;
+        }
+
+        if half_size * 2. > spacing {
+            let child_half_size = half_size * 0.5;
+            for i in 0..8 {
+                let offset = Vec3::new(
+                    if i & 1 == 0 { -1. } else { 1. },
+                    if i & 2 == 0 { -1. } else { 1. },
+                    if i & 4 == 0 { -1. } else { 1. },
+                );
+                stack.push((center + offset * child_half_size, child_half_size));
+            }
+            continue;
+        }
+
+        let mut point = center;
+        let mut distance = distance;
+        for _ in 0..SAMPLE_PROJECTION_STEPS {
+            point -= sdf.gradient(point).normalize_or_zero() * distance;
+            distance = sdf.distance(point);
+        }
+        if (point - center).abs().max_element() > half_size {
+            continue;
         }
+        let normal = sdf.gradient(point).normalize_or_zero();
+        if normal != Vec3::ZERO {
+            samples.push((point, normal));
+        }
+    }
+    samples
+}
+
+#[test]
+fn test_march_quality_iteration_limit() {
+    let sdf = BoxSdf(Vec3::ONE);
+    let fine = march_edge(&sdf, Vec3::new(-5., 0.99, 0.), Vec3::X, 0., 10.);
+    assert!(matches!(fine, MarchResult::Hit(..)));
+
+    let coarse = WithMarchQuality::new(
+        BoxSdf(Vec3::ONE),
+        SdfMarchQuality {
+            max_iterations: 2,
+            ..SdfMarchQuality::DEFAULT
+        },
+    );
+    // Grazing the top face needs many small steps to reach the box
+    let res = march_edge(&coarse, Vec3::new(-5., 1.01, 0.), Vec3::X, 0., 10.);
+    assert!(matches!(res, MarchResult::Closest(..)));
+
+    let tolerant = WithMarchQuality::new(
+        BoxSdf(Vec3::ONE),
+        SdfMarchQuality {
+            epsilon: 0.05,
+            ..SdfMarchQuality::DEFAULT
+        },
+    );
+    let res = march_edge(&tolerant, Vec3::new(-5., 1.01, 0.), Vec3::X, 0., 10.);
+    assert!(matches!(res, MarchResult::Hit(..)));
+}
 
-        let start = sdf_local_center + sdf_local_up * self.half_length;
-        let res = march_edge(
+#[test]
+fn test_march_length_step() {
+    let scaled = |sdf| {
+        WithMarchQuality::new(
             sdf,
-            start.into(),
-            (-sdf_local_up).into(),
-            self.radius,
-            total,
-        );
-        let (at, dist) = res.either();
+            SdfMarchQuality {
+                length_step: 0.01,
+                ..SdfMarchQuality::DEFAULT
+            },
+        )
+    };
 
-        if dist < self.radius + pred_dist {
-            let sdf_local_min_point = (start - sdf_local_up * *at).into();
-            let gradient = Vec3A::from(sdf.gradient(sdf_local_min_point));
-            let world_normal = sdf_iso.rotation * -gradient;
+    // Skimming along a long floor takes steps of the radius instead of the minimum step
+    let floor = BoxSdf(Vec3::new(100., 1., 100.));
+    let start = Vec3::new(-50., 1.5005, 0.);
+    let (fine, fine_iterations) = march_edge_counted(&floor, start, Vec3::X, 0.5, 200.);
+    let (coarse, coarse_iterations) = march_edge_counted(&scaled(floor), start, Vec3::X, 0.5, 200.);
+    assert!(matches!(fine, MarchResult::Closest(..)), "{fine:?}");
+    assert!(matches!(coarse, MarchResult::Closest(..)), "{coarse:?}");
+    assert!(fine_iterations > 100_000, "{fine_iterations}");
+    assert!(coarse_iterations < 1_000, "{coarse_iterations}");
 
-            let pen = self.radius - dist;
-            let anchor1 =
-                sdf_local_up * (self.half_length - *at) + world_normal * (self.radius - pen * 0.5);
-            let world_point = self_iso.translation + anchor1;
-            let anchor2 = world_point - sdf_iso.translation;
+    // Grazing hits are bisected back to where the fine march finds them
+    let direction = Vec3::new(1., -0.02, 0.).normalize();
+    let start = Vec3::new(-5., 1.6, 0.);
+    let box_sdf = BoxSdf(Vec3::ONE);
+    let MarchResult::Hit(fine, _) = march_edge(&box_sdf, start, direction, 0.5, 10.) else {
+        panic!("the fine march missed the box");
+    };
+    let MarchResult::Hit(coarse, _) = march_edge(&scaled(box_sdf), start, direction, 0.5, 10.)
+    else {
+        panic!("the coarse march missed the box");
+    };
+    assert!((*fine - *coarse).abs() < 0.002, "{fine:?} {coarse:?}");
 
-            adder.push(world_point, anchor1, anchor2, world_normal, pen);
-        }
+    // Walls thinner than the scaled step are still hit
+    let wall = BoxSdf(Vec3::new(0.01, 5., 5.));
+    let start = Vec3::new(-20., 0., 0.);
+    let MarchResult::Hit(toi, _) = march_edge(&scaled(wall), start, Vec3::X, 0., 100.) else {
+        panic!("the march passed through the wall");
+    };
+    assert!((*toi - 19.99).abs() < 0.002, "{toi:?}");
+}
+
+#[test]
+fn test_sample_surface() {
+    let sphere = Ellipsoid::new(Vec3::ONE);
+    let samples = sample_surface(&sphere, Vec3::ZERO, 1.1, 0.1, |_, _| true);
+
+    // Roughly one sample per spacing squared of surface area
+    let expected = 4. * PI / (0.1 * 0.1);
+    assert!(
+        (expected * 0.5..expected * 3.).contains(&(samples.len() as f32)),
+        "{}",
+        samples.len()
+    );
+    for (point, normal) in samples {
+        assert!(sphere.distance(point).abs() < 1e-3, "{point}");
+        assert!(normal.dot(point.normalize()) > 0.99, "{point} {normal}");
     }
+
+    let top = sample_surface(&sphere, Vec3::ZERO, 1.1, 0.1, |center, half_size| {
+        center.y + half_size > 0.5
+    });
+    assert!(top.iter().all(|(point, _)| point.y > 0.5 - 0.1));
 }
 
+/// Distance traveled along a march.
 #[derive(Clone, Copy, Debug)]
-pub(crate) struct TimeOfImpact(f32);
+pub struct TimeOfImpact(f32);
 impl Deref for TimeOfImpact {
     type Target = f32;
     fn deref(&self) -> &Self::Target {
@@ -384,9 +2430,12 @@ impl Deref for TimeOfImpact {
     }
 }
 
+/// The result of marching along a line, along with the distance to the surface at that point.
 #[derive(Clone, Copy, Debug)]
-pub(crate) enum MarchResult {
+pub enum MarchResult {
+    /// The march touched the surface
     Hit(TimeOfImpact, f32),
+    /// The march never touched the surface, this is where it got closest
     Closest(TimeOfImpact, f32),
 }
 
@@ -401,31 +2450,351 @@ impl MarchResult {
 
 const MINIMUM_STEP: f32 = 0.001;
 
-pub(crate) fn march_edge(
-    sdf: &ExecutableSdf3d,
+/// Marches along a line in the local space of an SDF until a sphere of `radius` moving along it
+/// touches the surface, or returns the closest approach if it never does.
+pub fn march_edge(
+    sdf: &impl LocalSdf,
     local_start: Vec3,
     local_direction: Vec3,
     radius: f32,
     length: f32,
 ) -> MarchResult {
+    march_edge_counted(sdf, local_start, local_direction, radius, length).0
+}
+
+pub(crate) fn march_edge_counted(
+    sdf: &impl LocalSdf,
+    local_start: Vec3,
+    local_direction: Vec3,
+    radius: f32,
+    length: f32,
+) -> (MarchResult, u32) {
+    let (result, iterations, _) =
+        march_edge_bracketed(sdf, local_start, local_direction, radius, length);
+    (result, iterations)
+}
+
+// Also returns the last distance traveled before the hit, where the sphere was still clear
+fn march_edge_bracketed(
+    sdf: &impl LocalSdf,
+    local_start: Vec3,
+    local_direction: Vec3,
+    radius: f32,
+    length: f32,
+) -> (MarchResult, u32, f32) {
+    let quality = sdf.march_quality();
+    let length_step = length * quality.length_step;
     let mut traveled = 0.;
+    let mut last_clear = 0.;
+    let mut overshot = false;
     let mut closest = (0., f32::INFINITY);
+    let mut iterations = 0;
 
     // Iterate over the line until we find a very small distance or get a contact
-    while traveled < length {
+    while traveled < length && iterations < quality.max_iterations {
+        iterations += 1;
         let sdf_local_pos = local_start + local_direction * traveled;
-        let distance = sdf.distance(sdf_local_pos);
+        let mut distance = sdf.distance(sdf_local_pos);
         // TODO: Improve behavior for ghost surfaces from subtract/intersect ops by continuing
         //    until we find a negative distance, then picking the zero surface at the sign change
-        if distance <= radius {
-            return MarchResult::Hit(TimeOfImpact(traveled), distance);
+        if distance <= radius + quality.epsilon {
+            // Steps scaled by the length may have moved the sphere well into the surface
+            if overshot {
+                let mut hit = traveled;
+                while hit - last_clear > quality.min_step && iterations < quality.max_iterations {
+                    iterations += 1;
+                    let middle = (last_clear + hit) * 0.5;
+                    let middle_distance = sdf.distance(local_start + local_direction * middle);
+                    if middle_distance > radius + quality.epsilon {
+                        last_clear = middle;
+                    } else {
+                        (hit, distance) = (middle, middle_distance);
+                    }
+                }
+                traveled = hit;
+            }
+            sdf.record_march_iterations(iterations);
+            return (
+                MarchResult::Hit(TimeOfImpact(traveled), distance),
+                iterations,
+                last_clear,
+            );
         }
         if distance < closest.1 {
             closest = (traveled, distance);
         }
 
-        traveled += (distance - radius).max(MINIMUM_STEP);
+        last_clear = traveled;
+        let step = (distance - radius).max(quality.min_step);
+        // Never step past the surface, so thin walls can't be skipped
+        let scaled_step = length_step.min(distance);
+        overshot = scaled_step > step;
+        traveled += step.max(scaled_step);
+    }
+    sdf.record_march_iterations(iterations);
+
+    (
+        MarchResult::Closest(TimeOfImpact(closest.0), closest.1),
+        iterations,
+        last_clear,
+    )
+}
+
+const MAX_REFINE_ITERATIONS: u32 = 64;
+
+/// Like [`march_edge`], but refines hits by bisection so the time of impact is within `epsilon`
+/// of where the distance crosses `radius`, instead of wherever the march first got close enough.
+pub fn march_edge_refined(
+    sdf: &impl LocalSdf,
+    local_start: Vec3,
+    local_direction: Vec3,
+    radius: f32,
+    length: f32,
+    epsilon: f32,
+) -> MarchResult {
+    march_edge_refined_counted(sdf, local_start, local_direction, radius, length, epsilon).0
+}
+
+pub(crate) fn march_edge_refined_counted(
+    sdf: &impl LocalSdf,
+    local_start: Vec3,
+    local_direction: Vec3,
+    radius: f32,
+    length: f32,
+    epsilon: f32,
+) -> (MarchResult, u32) {
+    let (result, march_iterations, mut clear) =
+        march_edge_bracketed(sdf, local_start, local_direction, radius, length);
+    let MarchResult::Hit(TimeOfImpact(mut hit), _) = result else {
+        return (result, march_iterations);
+    };
+    let distance_at = |t: f32| sdf.distance(local_start + local_direction * t);
+    if hit == 0. {
+        return (result, march_iterations);
+    }
+
+    // Hits within the march epsilon can still be short of the surface, push them through first
+    let mut step = epsilon.max(f32::EPSILON);
+    let mut iterations = 0;
+    while distance_at(hit) > radius && iterations < MAX_REFINE_ITERATIONS {
+        clear = hit;
+        hit = (hit + step).min(length);
+        step *= 2.;
+        iterations += 1;
+    }
+
+    while hit - clear > epsilon && iterations < MAX_REFINE_ITERATIONS {
+        let middle = (clear + hit) * 0.5;
+        if distance_at(middle) > radius {
+            clear = middle;
+        } else {
+            hit = middle;
+        }
+        iterations += 1;
+    }
+    sdf.record_march_iterations(iterations);
+
+    let toi = (clear + hit) * 0.5;
+    (
+        MarchResult::Hit(TimeOfImpact(toi), distance_at(toi)),
+        march_iterations + iterations,
+    )
+}
+
+/// How close to the surface of an SDF asset ray hits are refined.
+const RAY_SURFACE_EPSILON: f32 = 1e-4;
+
+/// Marches a ray until it hits the surface, refining the hit onto it so the gradient at the hit
+/// point is the normal of the surface. Also returns the number of SDF evaluations used.
+pub(crate) fn march_ray(
+    sdf: &impl LocalSdf,
+    local_start: Vec3,
+    local_direction: Vec3,
+    length: f32,
+) -> (Option<TimeOfImpact>, u32) {
+    let (result, iterations) = march_edge_refined_counted(
+        sdf,
+        local_start,
+        local_direction,
+        0.,
+        length,
+        RAY_SURFACE_EPSILON,
+    );
+    match result {
+        MarchResult::Hit(toi, _) => (Some(toi), iterations),
+        MarchResult::Closest(..) => (None, iterations),
+    }
+}
+
+#[test]
+fn test_far_field_bounds() {
+    let quality = SdfMarchQuality {
+        far_field: Some(1.),
+        ..SdfMarchQuality::DEFAULT
+    };
+    let unit_sphere = Ellipsoid::new(Vec3::ONE);
+    let sdf = WithMarchQuality::new(unit_sphere, quality)
+        .with_bounds(Some(Aabb3d::new(Vec3::ZERO, Vec3::ONE)));
+
+    // Far away the distance to the bounds is used, which never overestimates
+    let far = Vec3::new(3., 3., 0.);
+    assert!((sdf.distance(far) - 8f32.sqrt()).abs() < 1e-5);
+    assert!(sdf.distance(far) <= unit_sphere.distance(far));
+    assert!(sdf
+        .gradient(far)
+        .abs_diff_eq(Vec3::new(1., 1., 0.).normalize(), 1e-5));
+    // Close by the SDF itself is evaluated
+    let near = Vec3::new(0., 1.5, 0.);
+    assert_eq!(sdf.distance(near), unit_sphere.distance(near));
+
+    let MarchResult::Hit(toi, _) = march_edge(&sdf, Vec3::new(5., 0.5, 0.), Vec3::NEG_X, 0., 10.)
+    else {
+        panic!("march missed the sphere");
+    };
+    assert!((*toi - (5. - 0.75f32.sqrt())).abs() < 0.01, "{toi:?}");
+}
+
+#[test]
+fn test_march_edge_refined() {
+    let sdf = WithMarchQuality::new(
+        BoxSdf(Vec3::ONE),
+        SdfMarchQuality {
+            epsilon: 0.05,
+            ..SdfMarchQuality::DEFAULT
+        },
+    );
+    // The coarse march stops up to the epsilon before the surface, it only lands on it exactly
+    // when approaching it head on
+    let start = Vec3::new(-5., 0.5, 0.3);
+    let direction = Vec3::new(1., -0.1, 0.).normalize();
+    let expected = 4. * 1.01_f32.sqrt();
+    let coarse = march_edge(&sdf, start, direction, 0., 10.);
+    let refined = march_edge_refined(&sdf, start, direction, 0., 10., 1e-4);
+    let MarchResult::Hit(refined_toi, _) = refined else {
+        panic!("{refined:?}");
+    };
+    assert!((*refined_toi - expected).abs() < 1e-4, "{}", *refined_toi);
+    assert!((*coarse.either().0 - expected).abs() > 1e-3, "{coarse:?}");
+}
+
+#[test]
+fn test_march_ray_lands_on_surface() {
+    let sdf = WithMarchQuality::new(
+        Ellipsoid::new(Vec3::ONE),
+        SdfMarchQuality {
+            epsilon: 0.01,
+            ..SdfMarchQuality::DEFAULT
+        },
+    );
+    // A glancing ray, where stopping short of the surface tilts the normal the most
+    let start = Vec3::new(-5., 0.9, 0.);
+    let (toi, iterations) = march_ray(&sdf, start, Vec3::X, 10.);
+    let point = start + Vec3::X * *toi.unwrap();
+    assert!(sdf.distance(point).abs() < 1e-3, "{point}");
+    assert!(point.normalize().angle_between(sdf.gradient(point)) < 0.01);
+    assert!(iterations > 0);
+}
+
+/// Marches from a point inside the SDF to where the line leaves the surface.
+pub(crate) fn march_exit(
+    sdf: &impl LocalSdf,
+    local_start: Vec3,
+    local_direction: Vec3,
+    length: f32,
+) -> Option<TimeOfImpact> {
+    let quality = sdf.march_quality();
+    let mut traveled = 0.;
+    let mut iterations = 0;
+    while traveled < length && iterations < quality.max_iterations {
+        iterations += 1;
+        let distance = sdf.distance(local_start + local_direction * traveled);
+        if distance >= -quality.min_step - quality.epsilon {
+            sdf.record_march_iterations(iterations);
+            return Some(TimeOfImpact(traveled));
+        }
+        traveled += (-distance).max(quality.min_step);
+    }
+    sdf.record_march_iterations(iterations);
+    None
+}
+
+/// Marches a sphere of `radius` along a line through the whole SDF, returning each distance where
+/// it starts touching the surface and each where it's clear of it again, with whether it entered.
+///
+/// A march starting in contact with the surface enters at distance zero.
+pub(crate) fn march_crossings(
+    sdf: &impl LocalSdf,
+    local_start: Vec3,
+    local_direction: Vec3,
+    radius: f32,
+    length: f32,
+) -> Vec<(TimeOfImpact, bool)> {
+    let quality = sdf.march_quality();
+    let mut crossings = Vec::new();
+    let mut traveled = 0.;
+    let mut iterations = 0;
+    let mut inside = false;
+    while traveled <= length && iterations < quality.max_iterations {
+        iterations += 1;
+        let distance = sdf.distance(local_start + local_direction * traveled) - radius;
+        // Only leaving beyond the epsilon keeps grazing marches from flickering in and out
+        if inside == (distance > quality.epsilon) {
+            inside = !inside;
+            crossings.push((TimeOfImpact(traveled), inside));
+        }
+        traveled += distance.abs().max(quality.min_step);
+    }
+    sdf.record_march_iterations(iterations);
+    crossings
+}
+
+#[test]
+fn test_march_crossings() {
+    // A shell from 2 to 3 around the origin, crossed four times by a line through its center
+    struct Shell;
+    impl LocalSdf for Shell {
+        fn distance(&self, local_point: Vec3) -> f32 {
+            (local_point.length() - 2.5).abs() - 0.5
+        }
+        fn gradient(&self, local_point: Vec3) -> Vec3 {
+            local_point.normalize_or(Vec3::Y)
+        }
+    }
+
+    let crossings = march_crossings(&Shell, Vec3::new(-5., 0., 0.), Vec3::X, 0.1, 10.);
+    let expected = [(1.9, true), (3.1, false), (6.9, true), (8.1, false)];
+    assert_eq!(crossings.len(), expected.len(), "{crossings:?}");
+    for ((toi, entering), (distance, expected_entering)) in crossings.iter().zip(expected) {
+        assert_eq!(*entering, expected_entering, "{crossings:?}");
+        assert!((**toi - distance).abs() < 0.002, "{crossings:?}");
+    }
+
+    // Starting in contact enters immediately
+    let crossings = march_crossings(&Shell, Vec3::new(-2.5, 0., 0.), Vec3::X, 0., 1.);
+    assert_eq!(crossings.len(), 2, "{crossings:?}");
+    assert_eq!(*crossings[0].0, 0.);
+    assert!((*crossings[1].0 - 0.5).abs() < 0.002, "{crossings:?}");
+}
+
+pub(crate) fn solid_length(
+    distance: impl Fn(Vec3) -> f32,
+    local_start: Vec3,
+    local_direction: Vec3,
+    length: f32,
+) -> f32 {
+    let mut traveled = 0.;
+    let mut solid = 0.;
+
+    // The distance is a safe step both outside and inside the surface, so each step is either
+    // entirely solid or entirely empty
+    while traveled < length {
+        let distance = distance(local_start + local_direction * traveled);
+        let step = distance.abs().max(MINIMUM_STEP).min(length - traveled);
+        if distance < 0. {
+            solid += step;
+        }
+        traveled += step;
     }
 
-    MarchResult::Closest(TimeOfImpact(closest.0), closest.1)
+    solid
 }
@@ -0,0 +1,1133 @@
+use std::{cell::RefCell, ops::Add};
+
+use avian3d::{collision::collider::BoundedShape, prelude::*};
+use bevy::{
+    ecs::{
+        entity::EntityHashSet,
+        system::{SystemParam, SystemState},
+    },
+    math::{
+        bounding::{Aabb3d, BoundingVolume, IntersectsVolume},
+        FloatPow, Vec3A,
+    },
+    prelude::*,
+    tasks::{ComputeTaskPool, ParallelSlice},
+};
+use bevy_math::ops;
+
+use crate::{
+    adder::Contact,
+    anchors::SdfSurfaceAnchor,
+    collider::ColliderSdf,
+    context::{SdfContext, SdfParallelism},
+    navigation::{rasterize_walkable, WalkableHeightfield, WalkableSettings},
+    primitives::{
+        march_crossings, march_edge_counted, march_ray, sample_surface, solid_length, LocalSdf,
+        MarchResult, SdfMarchQuality, WithMarchQuality,
+    },
+    query_grid::SdfQueryGrid,
+    ColliderShape, RayHitDetails, SdfCollider,
+};
+
+/// Smallest radius of the sphere marched for casts, so they touch the surface before stalling on it
+const MIN_RAY_RADIUS: f32 = 0.001;
+
+/// Steps an angular cast takes before giving up
+const MAX_ANGULAR_STEPS: u32 = 1024;
+
+#[derive(SystemParam)]
+pub struct SdfSpatialQuery<'w, 's> {
+    colliders: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Position,
+            &'static Rotation,
+            &'static SdfCollider,
+            Option<&'static CollisionLayers>,
+        ),
+    >,
+    aabbs: Query<'w, 's, &'static ColliderAabb>,
+    context: SdfContext<'w, 's>,
+    parallelism: Res<'w, SdfParallelism>,
+    grid: Option<Res<'w, SdfQueryGrid>>,
+}
+
+impl SdfSpatialQuery<'_, '_> {
+    /// Sweeps a sphere along `direction` and returns every collider it hits, sorted by distance.
+    pub fn shape_hits(
+        &self,
+        shape: &Sphere,
+        origin: Vec3,
+        direction: Dir3,
+        max_hits: u32,
+        config: &ShapeCastConfig,
+        filter: &SpatialQueryFilter,
+    ) -> Vec<ShapeHitData> {
+        let nearby = self.nearby_sweep(origin, direction, shape.radius, config.max_distance);
+        let mut hits = Vec::new();
+        for (entity, pos, rot, collider, layers) in self.colliders.iter() {
+            if !filter.test(entity, layers.copied().unwrap_or_default())
+                || nearby
+                    .as_ref()
+                    .is_some_and(|nearby| !nearby.contains(&entity))
+            {
+                continue;
+            }
+
+            let inv_rot = rot.0.inverse();
+            let local_origin = inv_rot * (origin - pos.0);
+            let local_dir = Dir3::new_unchecked(inv_rot * *direction);
+            let Some(hit) = collider.local_shape_cast(
+                shape,
+                local_origin,
+                local_dir,
+                (0., config.max_distance),
+                &self.context,
+            ) else {
+                continue;
+            };
+            if config.ignore_origin_penetration && hit.distance <= 0. {
+                continue;
+            }
+
+            let normal1 = rot.0 * hit.normal;
+            let center = origin + direction * hit.distance;
+            hits.push(ShapeHitData {
+                entity,
+                distance: hit.distance,
+                point1: pos.0 + rot.0 * hit.point,
+                point2: center - normal1 * shape.radius,
+                normal1,
+                normal2: -normal1,
+            });
+        }
+
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits.truncate(max_hits as usize);
+        hits
+    }
+
+    /// Sweeps a sphere along `direction` and returns every surface it crosses, sorted by distance,
+    /// for piercing projectiles and placement previews through thin walls.
+    ///
+    /// Unlike [`shape_hits`](Self::shape_hits), a collider is reported each time the sphere starts
+    /// or stops touching it, so thin walls of the same SDF each add their own crossings.
+    pub fn shape_cast_all(
+        &self,
+        shape: &Sphere,
+        origin: Vec3,
+        direction: Dir3,
+        max_hits: u32,
+        config: &ShapeCastConfig,
+        filter: &SpatialQueryFilter,
+    ) -> Vec<ShapeCrossing> {
+        let nearby = self.nearby_sweep(origin, direction, shape.radius, config.max_distance);
+        let mut crossings = Vec::new();
+        for candidate in self.ray_candidates(filter, nearby.as_ref()) {
+            let inv_rot = candidate.rotation.inverse();
+            let local_origin = inv_rot * (origin - candidate.position) / candidate.scale;
+            let local_dir = inv_rot * *direction;
+            for (toi, entering) in march_crossings(
+                &candidate.sdf,
+                local_origin,
+                local_dir,
+                shape.radius / candidate.scale,
+                config.max_distance / candidate.scale,
+            ) {
+                if config.ignore_origin_penetration && *toi <= 0. {
+                    continue;
+                }
+                let local_center = local_origin + local_dir * *toi;
+                let local_normal = candidate
+                    .sdf
+                    .gradient(local_center)
+                    .normalize_or(-local_dir);
+                let local_point =
+                    local_center - local_normal * candidate.sdf.distance(local_center);
+                crossings.push(ShapeCrossing {
+                    entity: candidate.entity,
+                    distance: *toi * candidate.scale,
+                    point: candidate.position + candidate.rotation * local_point * candidate.scale,
+                    normal: candidate.rotation * local_normal,
+                    entering,
+                });
+            }
+        }
+
+        crossings.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        crossings.truncate(max_hits as usize);
+        crossings
+    }
+
+    /// Sweeps a sphere of `radius` along a ray, zero for a raycast, and returns the closest hit
+    /// while marching each collider at most `max_iterations` steps.
+    ///
+    /// A march that runs out of steps counts as a hit at its closest approach, flagged as
+    /// exhausted, so grazing rays against detailed SDFs stay cheap and err on the side of a hit.
+    pub fn cast_budgeted(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        radius: f32,
+        max_distance: f32,
+        max_iterations: u32,
+        filter: &SpatialQueryFilter,
+    ) -> Option<BudgetedCastHit> {
+        let nearby = self.nearby_sweep(origin, direction, radius, max_distance);
+        let mut closest: Option<BudgetedCastHit> = None;
+        for candidate in self.ray_candidates(filter, nearby.as_ref()) {
+            let inv_rot = candidate.rotation.inverse();
+            let local_origin = inv_rot * (origin - candidate.position) / candidate.scale;
+            let local_dir = inv_rot * *direction;
+            let local_max = closest.map_or(max_distance, |hit| hit.distance) / candidate.scale;
+            let quality = candidate.sdf.march_quality();
+            let quality = SdfMarchQuality {
+                max_iterations: quality.max_iterations.min(max_iterations),
+                ..quality
+            };
+            let (result, iterations) = march_edge_counted(
+                &WithMarchQuality::new(&candidate.sdf, quality),
+                local_origin,
+                local_dir,
+                (radius / candidate.scale).max(MIN_RAY_RADIUS),
+                local_max,
+            );
+            let (toi, exhausted) = match result {
+                MarchResult::Hit(toi, _) => (*toi, false),
+                MarchResult::Closest(toi, _) if iterations >= quality.max_iterations => {
+                    (*toi, true)
+                }
+                MarchResult::Closest(..) => continue,
+            };
+
+            let local_normal = candidate.sdf.gradient(local_origin + local_dir * toi);
+            closest = Some(BudgetedCastHit {
+                entity: candidate.entity,
+                distance: toi * candidate.scale,
+                normal: (candidate.rotation * local_normal).normalize_or(-*direction),
+                exhausted,
+            });
+        }
+        closest
+    }
+
+    /// Sweeps a sphere of `radius` along a ray and returns the closest hit, like a padded raycast.
+    pub fn sphere_cast(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        radius: f32,
+        max_distance: f32,
+        filter: &SpatialQueryFilter,
+    ) -> Option<SphereCastHit> {
+        let hit = self
+            .shape_hits(
+                &Sphere::new(radius),
+                origin,
+                direction,
+                1,
+                &ShapeCastConfig::from_max_distance(max_distance),
+                filter,
+            )
+            .into_iter()
+            .next()?;
+        Some(SphereCastHit {
+            entity: hit.entity,
+            distance: hit.distance,
+            center: origin + direction * hit.distance,
+            point: hit.point1,
+            normal: hit.normal1,
+        })
+    }
+
+    /// Swings a sphere starting at `origin` around `axis` through `pivot`, and returns the first
+    /// angle it touches a collider at, for doors, melee swings and hatches.
+    ///
+    /// `max_angle` is in radians, counterclockwise around the axis and negative for clockwise
+    /// swings. Each step turns the sphere by its distance to the closest collider divided by its
+    /// distance to the axis, which never moves it past a surface. A sphere touching a collider at
+    /// the start hits at angle zero, and swings skimming along a surface for more than 1024 steps
+    /// find no hit.
+    pub fn angular_cast(
+        &self,
+        shape: &Sphere,
+        origin: Vec3,
+        pivot: Vec3,
+        axis: Dir3,
+        max_angle: f32,
+        filter: &SpatialQueryFilter,
+    ) -> Option<AngularCastHit> {
+        let offset = origin - pivot;
+        let lever = offset.reject_from_normalized(*axis).length();
+        let reach = offset.length() + shape.radius;
+        let nearby = self.nearby(Aabb3d::new(pivot, Vec3::splat(reach)));
+        let candidates = self.ray_candidates(filter, nearby.as_ref());
+
+        let mut angle = 0.;
+        for _ in 0..MAX_ANGULAR_STEPS {
+            let rotation = Quat::from_axis_angle(*axis, angle * max_angle.signum());
+            let center = pivot + rotation * offset;
+            let (candidate, local_center, distance) = candidates
+                .iter()
+                .map(|candidate| {
+                    let local_center = candidate.rotation.inverse() * (center - candidate.position)
+                        / candidate.scale;
+                    let distance = candidate.sdf.distance(local_center) * candidate.scale;
+                    (candidate, local_center, distance)
+                })
+                .min_by(|a, b| a.2.total_cmp(&b.2))?;
+
+            let gap = distance - shape.radius;
+            if gap <= MIN_RAY_RADIUS {
+                let normal = (candidate.rotation * candidate.sdf.gradient(local_center))
+                    .normalize_or(Vec3::Y);
+                return Some(AngularCastHit {
+                    entity: candidate.entity,
+                    angle: angle * max_angle.signum(),
+                    center,
+                    point: center - normal * distance,
+                    normal,
+                });
+            }
+            // A sphere on the axis only turns in place
+            if lever <= f32::EPSILON || angle >= max_angle.abs() {
+                return None;
+            }
+            angle = (angle + gap.max(MIN_RAY_RADIUS) / lever).min(max_angle.abs());
+        }
+        None
+    }
+
+    /// Returns the total length of solid SDF geometry along the segment between two points.
+    pub fn solid_thickness(&self, from: Vec3, to: Vec3, filter: &SpatialQueryFilter) -> f32 {
+        let length = from.distance(to);
+        let Ok(direction) = Dir3::new(to - from) else {
+            return 0.;
+        };
+
+        let mut thickness = 0.;
+        for (entity, pos, rot, collider, layers) in self.colliders.iter() {
+            if !filter.test(entity, layers.copied().unwrap_or_default()) {
+                continue;
+            }
+            let Some(sdf) = collider.local_sdf(&self.context) else {
+                continue;
+            };
+
+            let inv_rot = rot.0.inverse();
+            let local_start = inv_rot * (from - pos.0) / collider.scale;
+            let local_dir = inv_rot * *direction;
+            let local_length = length / collider.scale;
+            thickness += solid_length(|p| sdf.distance(p), local_start, local_dir, local_length)
+                * collider.scale;
+        }
+        thickness
+    }
+
+    /// Estimates how much sound passes between two points, from 1 (unobstructed) towards 0.
+    ///
+    /// `absorption` is the fraction of energy lost per unit of solid thickness, on a log scale.
+    pub fn transmission(
+        &self,
+        from: Vec3,
+        to: Vec3,
+        absorption: f32,
+        filter: &SpatialQueryFilter,
+    ) -> f32 {
+        ops::exp(-absorption * self.solid_thickness(from, to, filter))
+    }
+
+    /// Casts many rays at once, returning the closest hit for each ray in the same order.
+    ///
+    /// The SDFs of all colliders are looked up once for the whole batch, and the rays are split
+    /// over the compute task pool according to [`SdfParallelism`]. Marches against SDF assets
+    /// skip through the space the previous ray of the batch found empty, so rays fanning out from
+    /// the same point, like a view cone, should be next to each other.
+    pub fn cast_rays(
+        &self,
+        rays: &[(Vec3, Dir3)],
+        max_distance: f32,
+        solid: bool,
+        filter: &SpatialQueryFilter,
+    ) -> Vec<Option<RayHitData>> {
+        let candidates = self.ray_candidates(filter, None);
+        let grid = self.grid.as_deref();
+        let world_offset = self.context.world_offset();
+        let cast_chunk = |chunk: &[(Vec3, Dir3)]| {
+            let mut warm_starts = candidates
+                .iter()
+                .map(|_| RayWarmStart::default())
+                .collect::<Vec<_>>();
+            chunk
+                .iter()
+                .map(|&(origin, direction)| {
+                    let nearby = grid.map(|grid| {
+                        grid.entities_along_ray(origin, direction, max_distance, world_offset)
+                    });
+                    closest_warm_ray_hit(
+                        candidates
+                            .iter()
+                            .zip(warm_starts.iter_mut())
+                            .filter(|(candidate, _)| {
+                                nearby
+                                    .as_ref()
+                                    .is_none_or(|nearby| nearby.contains(&candidate.entity))
+                            })
+                            .map(|(candidate, warm)| (candidate, Some(warm))),
+                        origin,
+                        direction,
+                        max_distance,
+                        solid,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        if !self.parallelism.should_parallelize(rays.len()) {
+            return cast_chunk(rays);
+        }
+
+        rays.par_splat_map(ComputeTaskPool::get(), None, |_, chunk| cast_chunk(chunk))
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Casts a ray and returns the hit on every collider along it, sorted by distance.
+    pub fn ray_hits(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        max_distance: f32,
+        max_hits: u32,
+        solid: bool,
+        filter: &SpatialQueryFilter,
+    ) -> Vec<RayHitData> {
+        let nearby = self.nearby_ray(origin, direction, max_distance);
+        let mut hits = self
+            .ray_candidates(filter, nearby.as_ref())
+            .iter()
+            .filter_map(|candidate| {
+                closest_ray_hit([candidate], origin, direction, max_distance, solid)
+            })
+            .collect::<Vec<_>>();
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits.truncate(max_hits as usize);
+        hits
+    }
+
+    /// Casts a ray and returns the closest hit along with its world-space point and normal,
+    /// whether the ray started inside the collider, and how many march iterations were used.
+    pub fn cast_ray_detailed(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        max_distance: f32,
+        solid: bool,
+        filter: &SpatialQueryFilter,
+    ) -> Option<(Entity, RayHitDetails)> {
+        let nearby = self.nearby_ray(origin, direction, max_distance);
+        let mut closest: Option<(Entity, RayHitDetails)> = None;
+        for candidate in self.ray_candidates(filter, nearby.as_ref()) {
+            let inv_rot = candidate.rotation.inverse();
+            let local_origin = inv_rot * (origin - candidate.position) / candidate.scale;
+            let local_dir = Dir3::new_unchecked(inv_rot * *direction);
+            let max = closest.map_or(max_distance, |(_, hit)| hit.distance);
+            let Some(hit) = candidate.sdf.ray_hit_detailed(
+                local_origin,
+                local_dir,
+                max / candidate.scale,
+                solid,
+            ) else {
+                continue;
+            };
+
+            closest = Some((
+                candidate.entity,
+                RayHitDetails {
+                    distance: hit.distance * candidate.scale,
+                    point: candidate.position + candidate.rotation * hit.point * candidate.scale,
+                    normal: candidate.rotation * hit.normal,
+                    ..hit
+                },
+            ));
+        }
+        closest
+    }
+
+    /// Casts a ray and returns the closest hit along with where the ray leaves that collider
+    /// again, for penetration depths and effects passing through thin geometry.
+    ///
+    /// Rays starting inside a collider enter it at distance zero.
+    pub fn cast_ray_through(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        max_distance: f32,
+        filter: &SpatialQueryFilter,
+    ) -> Option<RayPassHit> {
+        let nearby = self.nearby_ray(origin, direction, max_distance);
+        let candidates = self.ray_candidates(filter, nearby.as_ref());
+        let entry = closest_ray_hit(&candidates, origin, direction, max_distance, true)?;
+        let candidate = candidates
+            .iter()
+            .find(|candidate| candidate.entity == entry.entity)?;
+
+        let inv_rot = candidate.rotation.inverse();
+        let local_dir = Dir3::new_unchecked(inv_rot * *direction);
+        // Step just past the surface, so the march starts inside and stops where it leaves again
+        let local_entry = entry.distance / candidate.scale + 2. * MIN_RAY_RADIUS;
+        let local_start =
+            inv_rot * (origin - candidate.position) / candidate.scale + local_dir * local_entry;
+        let local_remaining = max_distance / candidate.scale - local_entry;
+        let exit = if candidate.sdf.distance(local_start) >= 0. {
+            // Grazing hits leave the surface where they touch it
+            Some((entry.distance, -entry.normal))
+        } else if local_remaining > 0. {
+            candidate
+                .sdf
+                .ray_hit(local_start, local_dir, local_remaining, false)
+                .map(|distance| {
+                    let local_point = local_start + local_dir * distance;
+                    (
+                        (local_entry + distance) * candidate.scale,
+                        (candidate.rotation * candidate.sdf.gradient(local_point))
+                            .normalize_or(*direction),
+                    )
+                })
+        } else {
+            None
+        };
+
+        Some(RayPassHit {
+            entity: entry.entity,
+            entry: entry.distance,
+            entry_normal: entry.normal,
+            exit,
+        })
+    }
+
+    /// Finds the closest point on the surface of any collider, for snapping things onto surfaces.
+    pub fn project_onto_surface(
+        &self,
+        point: Vec3,
+        filter: &SpatialQueryFilter,
+    ) -> Option<SurfaceProjection> {
+        let mut closest: Option<SurfaceProjection> = None;
+        for (entity, pos, rot, collider, layers) in self.colliders.iter() {
+            if !filter.test(entity, layers.copied().unwrap_or_default()) {
+                continue;
+            }
+            let Some(sdf) = collider.local_sdf(&self.context) else {
+                continue;
+            };
+
+            let local_point = rot.0.inverse() * (point - pos.0) / collider.scale;
+            let distance = sdf.distance(local_point) * collider.scale;
+            if closest.is_some_and(|c| c.distance.abs() <= distance.abs()) {
+                continue;
+            }
+
+            let local_surface = sdf.closest_point(local_point, false);
+            closest = Some(SurfaceProjection {
+                entity,
+                point: pos.0 + rot.0 * local_surface * collider.scale,
+                normal: rot.0 * sdf.gradient(local_surface).normalize_or(Vec3::Y),
+                distance,
+            });
+        }
+        closest
+    }
+
+    /// Projects `point` onto the surface of the collider on `entity`, giving an anchor for joints
+    /// attached to it like ropes or grappling hooks.
+    ///
+    /// Returns nothing if the entity has no collider with a loaded SDF.
+    pub fn surface_anchor(&self, entity: Entity, point: Vec3) -> Option<SdfSurfaceAnchor> {
+        let (_, pos, rot, collider, _) = self.colliders.get(entity).ok()?;
+        let sdf = collider.full_sdf(&self.context)?;
+        let local_point = rot.0.inverse() * (point - pos.0);
+        Some(SdfSurfaceAnchor::project(
+            entity,
+            collider,
+            &sdf,
+            local_point,
+        ))
+    }
+
+    /// Returns the smallest signed distance from `point` to any collider within `max_distance`,
+    /// negative if the point is inside one.
+    ///
+    /// Colliders are visited in order of the distance to their AABB, so only the ones that could
+    /// be closer than the best distance so far are evaluated.
+    pub fn distance(
+        &self,
+        point: Vec3,
+        max_distance: f32,
+        filter: &SpatialQueryFilter,
+    ) -> Option<SceneDistance> {
+        let mut candidates = self
+            .colliders
+            .iter()
+            .filter(|(entity, _, _, _, layers)| {
+                filter.test(*entity, layers.copied().unwrap_or_default())
+            })
+            .filter_map(|(entity, pos, rot, collider, _)| {
+                // Points inside the AABB can be anywhere in the collider, so those go first
+                let outside = self
+                    .aabbs
+                    .get(entity)
+                    .map_or(Vec3::ZERO, |aabb| (aabb.min - point).max(point - aabb.max));
+                let bound = if outside.max_element() > 0. {
+                    outside.max(Vec3::ZERO).length()
+                } else {
+                    f32::NEG_INFINITY
+                };
+                (bound <= max_distance).then_some((bound, entity, pos, rot, collider))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut closest: Option<SceneDistance> = None;
+        for (bound, entity, pos, rot, collider) in candidates {
+            if closest.is_some_and(|c| c.distance < bound) {
+                break;
+            }
+            let Some(sdf) = collider.local_sdf(&self.context) else {
+                continue;
+            };
+            let local_point = rot.0.inverse() * (point - pos.0) / collider.scale;
+            let distance = sdf.distance(local_point) * collider.scale;
+            if distance <= max_distance && closest.is_none_or(|c| distance < c.distance) {
+                closest = Some(SceneDistance { entity, distance });
+            }
+        }
+        closest
+    }
+
+    /// Returns how deep `point` is inside the deepest collider containing it, and which way is out.
+    pub fn escape(&self, point: Vec3, filter: &SpatialQueryFilter) -> Option<SdfEscape> {
+        let deepest = self.distance(point, 0., filter)?;
+        if deepest.distance >= 0. {
+            return None;
+        }
+        let (_, pos, rot, collider, _) = self.colliders.get(deepest.entity).ok()?;
+        let sdf = collider.local_sdf(&self.context)?;
+        let local_point = rot.0.inverse() * (point - pos.0) / collider.scale;
+        Some(SdfEscape {
+            entity: deepest.entity,
+            depth: -deepest.distance,
+            direction: Dir3::new(rot.0 * sdf.gradient(local_point)).unwrap_or(Dir3::Y),
+        })
+    }
+
+    /// Returns a repulsion vector for each agent, blended from the gradients of every collider
+    /// within `radius` of it, for avoidance and flocking.
+    ///
+    /// Each gradient is weighted from one at the surface to zero at `radius`, so the length of
+    /// the result grows as agents get closer to obstacles. Colliders are only looked up once for
+    /// the whole batch, and agents are split over the compute task pool like
+    /// [`cast_rays`](Self::cast_rays).
+    pub fn avoidance(
+        &self,
+        agents: &[Vec3],
+        radius: f32,
+        filter: &SpatialQueryFilter,
+    ) -> Vec<Vec3> {
+        if agents.is_empty() {
+            return Vec::new();
+        }
+        let bounds = Aabb3d::from_point_cloud(Isometry3d::IDENTITY, agents.iter().copied())
+            .grow(Vec3A::splat(radius));
+        let mut candidates = self.ray_candidates(filter, self.nearby(bounds).as_ref());
+        candidates.retain(|candidate| {
+            self.aabbs.get(candidate.entity).is_none_or(|aabb| {
+                let aabb = Aabb3d::new((aabb.min + aabb.max) * 0.5, (aabb.max - aabb.min) * 0.5);
+                aabb.intersects(&bounds)
+            })
+        });
+
+        let repulsion = |&agent: &Vec3| {
+            candidates
+                .iter()
+                .filter_map(|candidate| {
+                    let inv_rot = candidate.rotation.inverse();
+                    let local_point = inv_rot * (agent - candidate.position) / candidate.scale;
+                    let distance = candidate.sdf.distance(local_point) * candidate.scale;
+                    if distance >= radius {
+                        return None;
+                    }
+                    let weight = (1. - distance.max(0.) / radius).squared();
+                    let gradient = candidate.sdf.gradient(local_point).normalize_or_zero();
+                    Some(candidate.rotation * gradient * weight)
+                })
+                .fold(Vec3::ZERO, Add::add)
+        };
+
+        if !self.parallelism.should_parallelize(agents.len()) {
+            return agents.iter().map(repulsion).collect();
+        }
+        agents
+            .par_splat_map(ComputeTaskPool::get(), None, |_, chunk| {
+                chunk.iter().map(repulsion).collect::<Vec<_>>()
+            })
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Returns the contacts between a shape and every collider it overlaps, in world space.
+    ///
+    /// Contact normals point from the hit collider towards the shape.
+    pub fn shape_contacts(
+        &self,
+        shape: &ColliderShape,
+        origin: Vec3,
+        rotation: Quat,
+        filter: &SpatialQueryFilter,
+    ) -> Vec<(Entity, Vec<Contact>)> {
+        let aabb = shape.shape_aabb(origin, rotation, &self.context);
+        let nearby = self.nearby(Aabb3d {
+            min: aabb.min.into(),
+            max: aabb.max.into(),
+        });
+        let mut hits = Vec::new();
+        // Most colliders aren't hit, so their contacts go into a shared buffer first
+        let mut contacts = Vec::new();
+        for (entity, pos, rot, collider, layers) in self.colliders.iter() {
+            if !filter.test(entity, layers.copied().unwrap_or_default())
+                || nearby
+                    .as_ref()
+                    .is_some_and(|nearby| !nearby.contains(&entity))
+            {
+                continue;
+            }
+
+            let inv_rot = rot.0.inverse();
+            collider.local_shape_contacts(
+                shape,
+                inv_rot * rotation,
+                inv_rot * (origin - pos.0),
+                0.,
+                &self.context,
+                &mut contacts,
+            );
+            contacts.retain(|c| c.penetration >= 0.);
+            if contacts.is_empty() {
+                continue;
+            }
+
+            let world_contacts = contacts
+                .iter()
+                .map(|contact| contact.transformed(Isometry3d::new(pos.0, rot.0)))
+                .collect();
+            hits.push((entity, world_contacts));
+        }
+        hits
+    }
+
+    /// Samples points roughly `spacing` apart on the surface of the collider on `entity`.
+    ///
+    /// Only the part of the surface inside `region` is sampled if given, which keeps large
+    /// terrains tractable. Returns nothing if the entity has no collider with a loaded SDF.
+    pub fn sample_surface(
+        &self,
+        entity: Entity,
+        spacing: f32,
+        region: Option<Aabb3d>,
+    ) -> Vec<SurfaceSample> {
+        let Ok((_, pos, rot, collider, _)) = self.colliders.get(entity) else {
+            return Vec::new();
+        };
+        let (Some(sdf), Some(radius)) = (
+            collider.local_sdf(&self.context),
+            collider.bounding_radius(&self.context),
+        ) else {
+            return Vec::new();
+        };
+
+        let scale = collider.scale;
+        let to_world = |local_point: Vec3| pos.0 + rot.0 * local_point * scale;
+        let in_region = |center: Vec3, half_size: f32| {
+            region.is_none_or(|region| {
+                let center = to_world(center);
+                let radius = half_size * scale * 3f32.sqrt();
+                Vec3::from(region.closest_point(center)).distance_squared(center) <= radius * radius
+            })
+        };
+        sample_surface(&sdf, Vec3::ZERO, radius / scale, spacing / scale, in_region)
+            .into_iter()
+            .map(|(point, normal)| SurfaceSample {
+                point: to_world(point),
+                normal: rot.0 * normal,
+            })
+            .collect()
+    }
+
+    /// Rasterizes the floors of every collider within `bounds` into a heightfield for navmesh
+    /// generation, with floors that are too steep or too low to stand under marked unwalkable.
+    pub fn walkable_heightfield(
+        &self,
+        bounds: Aabb3d,
+        settings: &WalkableSettings,
+        filter: &SpatialQueryFilter,
+    ) -> WalkableHeightfield {
+        let candidates = self.ray_candidates(filter, None);
+        let distance = |point: Vec3| {
+            candidates
+                .iter()
+                .map(|candidate| {
+                    let local_point = candidate.rotation.inverse() * (point - candidate.position)
+                        / candidate.scale;
+                    candidate.sdf.distance(local_point) * candidate.scale
+                })
+                .fold(f32::INFINITY, f32::min)
+        };
+        rasterize_walkable(distance, bounds, settings)
+    }
+
+    /// Colliders the [`SdfQueryGrid`] places near a sphere swept along a ray.
+    fn nearby_sweep(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        radius: f32,
+        max_distance: f32,
+    ) -> Option<EntityHashSet> {
+        let end = origin + direction * max_distance;
+        if !end.is_finite() {
+            return None;
+        }
+        self.nearby(Aabb3d::new(
+            (origin + end) / 2.,
+            (end - origin).abs() / 2. + radius,
+        ))
+    }
+
+    /// Colliders the [`SdfQueryGrid`] places near `aabb`, or `None` if every collider is checked.
+    fn nearby(&self, aabb: Aabb3d) -> Option<EntityHashSet> {
+        let world_offset = self.context.world_offset();
+        self.grid
+            .as_ref()
+            .map(|grid| grid.entities_in(aabb, world_offset))
+    }
+
+    /// Colliders the [`SdfQueryGrid`] places along a ray, or `None` if every collider is checked.
+    fn nearby_ray(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        max_distance: f32,
+    ) -> Option<EntityHashSet> {
+        let world_offset = self.context.world_offset();
+        self.grid
+            .as_ref()
+            .map(|grid| grid.entities_along_ray(origin, direction, max_distance, world_offset))
+    }
+
+    fn ray_candidates(
+        &self,
+        filter: &SpatialQueryFilter,
+        nearby: Option<&EntityHashSet>,
+    ) -> Vec<RayCandidate<'_>> {
+        self.colliders
+            .iter()
+            .filter(|(entity, _, _, _, layers)| {
+                filter.test(*entity, layers.copied().unwrap_or_default())
+                    && nearby.is_none_or(|nearby| nearby.contains(entity))
+            })
+            .filter_map(|(entity, pos, rot, collider, _)| {
+                Some(RayCandidate {
+                    entity,
+                    position: pos.0,
+                    rotation: rot.0,
+                    scale: collider.scale,
+                    sdf: collider
+                        .local_sdf(&self.context)?
+                        .with_march_quality(self.context.march_quality(entity)),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Runs [`SdfSpatialQuery`] queries from outside the schedule, for editor tools, tests and
+/// turn-based games that don't step physics every frame.
+///
+/// The state is created once from a `&mut World`, after which queries only need a `&World`.
+/// Colliders are placed at their current [`Position`] and [`Rotation`], which avian only syncs from
+/// transforms when physics runs, so entities spawned since should be given both. The
+/// [`SdfQueryGrid`] is also only updated by physics, remove it to check newly spawned colliders.
+pub struct SdfWorldQuery {
+    state: SystemState<SdfSpatialQuery<'static, 'static>>,
+}
+
+impl SdfWorldQuery {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            state: SystemState::new(world),
+        }
+    }
+
+    /// The spatial queries of `world`, reflecting it at the time of the call.
+    pub fn get<'w, 's>(&'s mut self, world: &'w World) -> SdfSpatialQuery<'w, 's> {
+        self.state.get(world)
+    }
+}
+
+/// A ray passing through a collider, see [`SdfSpatialQuery::cast_ray_through`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayPassHit {
+    pub entity: Entity,
+    /// Distance along the ray where it enters the collider
+    pub entry: f32,
+    pub entry_normal: Vec3,
+    /// Distance and outward normal where the ray leaves the collider, `None` if it's still inside
+    /// at the max distance
+    pub exit: Option<(f32, Vec3)>,
+}
+
+impl RayPassHit {
+    /// Length of the ray inside the collider, up to the max distance if it doesn't leave it.
+    pub fn thickness(&self, max_distance: f32) -> f32 {
+        self.exit.map_or(max_distance, |(exit, _)| exit) - self.entry
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceProjection {
+    pub entity: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Signed distance from the projected point to the surface, negative inside colliders
+    pub distance: f32,
+}
+
+/// The way out of a collider, see [`SdfSpatialQuery::escape`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SdfEscape {
+    pub entity: Entity,
+    /// Distance to the surface of the collider, positive inside it
+    pub depth: f32,
+    /// Direction to move in to leave the collider the shortest way
+    pub direction: Dir3,
+}
+
+impl SdfEscape {
+    /// The point on the surface reached by moving `depth` along `direction`.
+    pub fn exit_point(&self, point: Vec3) -> Vec3 {
+        point + self.direction * self.depth
+    }
+}
+
+/// A surface crossed by [`SdfSpatialQuery::shape_cast_all`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapeCrossing {
+    pub entity: Entity,
+    /// Distance the sphere traveled before the crossing
+    pub distance: f32,
+    /// Point on the surface of the collider
+    pub point: Vec3,
+    /// Outward surface normal of the collider at `point`
+    pub normal: Vec3,
+    /// Whether the sphere starts touching the collider here, rather than leaving it
+    pub entering: bool,
+}
+
+/// The closest hit of [`SdfSpatialQuery::cast_budgeted`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BudgetedCastHit {
+    pub entity: Entity,
+    /// Distance along the cast to the hit, or to the closest approach if the march was exhausted
+    pub distance: f32,
+    /// Surface normal of the collider at the hit or closest approach
+    pub normal: Vec3,
+    /// Whether the march ran out of iterations before touching the surface
+    pub exhausted: bool,
+}
+
+/// The first hit of [`SdfSpatialQuery::angular_cast`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AngularCastHit {
+    pub entity: Entity,
+    /// Angle the sphere turned before touching the collider, with the sign of the max angle
+    pub angle: f32,
+    /// Center of the sphere at the time of impact
+    pub center: Vec3,
+    /// Point on the surface of the collider
+    pub point: Vec3,
+    /// Surface normal of the collider at `point`
+    pub normal: Vec3,
+}
+
+/// The closest hit of [`SdfSpatialQuery::sphere_cast`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SphereCastHit {
+    pub entity: Entity,
+    /// Distance the sphere traveled before touching the collider
+    pub distance: f32,
+    /// Center of the sphere at the time of impact
+    pub center: Vec3,
+    /// Point on the surface of the collider
+    pub point: Vec3,
+    /// Surface normal of the collider at `point`
+    pub normal: Vec3,
+}
+
+/// The collider closest to a point, see [`SdfSpatialQuery::distance`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SceneDistance {
+    pub entity: Entity,
+    /// Signed distance to the surface of the collider, negative inside it
+    pub distance: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceSample {
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+struct RayCandidate<'a> {
+    entity: Entity,
+    position: Vec3,
+    rotation: Quat,
+    scale: f32,
+    sdf: ColliderSdf<'a>,
+}
+
+fn closest_ray_hit<'a>(
+    candidates: impl IntoIterator<Item = &'a RayCandidate<'a>>,
+    origin: Vec3,
+    direction: Dir3,
+    max_distance: f32,
+    solid: bool,
+) -> Option<RayHitData> {
+    closest_warm_ray_hit(
+        candidates.into_iter().map(|candidate| (candidate, None)),
+        origin,
+        direction,
+        max_distance,
+        solid,
+    )
+}
+
+/// Like [`closest_ray_hit`], but marches against candidates with a [`RayWarmStart`] skip through
+/// the space their previous ray found empty.
+fn closest_warm_ray_hit<'a, 'w>(
+    candidates: impl IntoIterator<Item = (&'a RayCandidate<'a>, Option<&'w mut RayWarmStart>)>,
+    origin: Vec3,
+    direction: Dir3,
+    max_distance: f32,
+    solid: bool,
+) -> Option<RayHitData> {
+    let mut closest: Option<RayHitData> = None;
+    for (candidate, warm) in candidates {
+        let inv_rot = candidate.rotation.inverse();
+        let local_origin = inv_rot * (origin - candidate.position) / candidate.scale;
+        let local_dir = Dir3::new_unchecked(inv_rot * *direction);
+        let local_max = closest.as_ref().map_or(max_distance, |hit| hit.distance) / candidate.scale;
+        let hit = match (&candidate.sdf, warm) {
+            (ColliderSdf::Asset(sdf), Some(warm)) if sdf.distance(local_origin) >= 0. => {
+                warm.ray_hit(sdf, local_origin, local_dir, local_max)
+            }
+            (sdf, _) => sdf.ray_hit(local_origin, local_dir, local_max, solid),
+        };
+        let Some(local_distance) = hit else {
+            continue;
+        };
+
+        let local_normal = candidate
+            .sdf
+            .gradient(local_origin + local_dir * local_distance);
+        closest = Some(RayHitData {
+            entity: candidate.entity,
+            distance: local_distance * candidate.scale,
+            normal: (candidate.rotation * local_normal).normalize_or(Vec3::Y),
+        });
+    }
+    closest
+}
+
+/// Points sampled by the last march against an SDF and their distance to its surface, each the
+/// center of a sphere the surface doesn't pass through.
+///
+/// Marches from inside these spheres can skip straight through them without evaluating the SDF,
+/// which saves most of the march for rays that start close to the previous one.
+#[derive(Default)]
+struct RayWarmStart {
+    spheres: Vec<(Vec3, f32)>,
+}
+
+impl RayWarmStart {
+    /// Marches a ray that starts outside the SDF, recording the spheres for the next ray.
+    fn ray_hit(
+        &mut self,
+        sdf: &impl LocalSdf,
+        local_origin: Vec3,
+        local_dir: Dir3,
+        max_distance: f32,
+    ) -> Option<f32> {
+        // Stay far enough from the surface that the march can't have reached it yet
+        let clearance = MIN_RAY_RADIUS + sdf.march_quality().epsilon;
+        let (skip, used) = self.skip(local_origin, *local_dir, clearance);
+        self.spheres.truncate(used);
+        if skip >= max_distance {
+            return None;
+        }
+
+        let recording = RecordingSdf {
+            sdf,
+            spheres: RefCell::new(&mut self.spheres),
+        };
+        let start = local_origin + local_dir * skip;
+        let (toi, _) = march_ray(&recording, start, *local_dir, max_distance - skip);
+        Some(skip + *toi?)
+    }
+
+    /// How far a ray can travel through the recorded spheres, in order, staying `clearance` away
+    /// from the surface, and how many of the spheres it passed through.
+    fn skip(&self, origin: Vec3, direction: Vec3, clearance: f32) -> (f32, usize) {
+        let mut skip = 0.;
+        for (i, &(center, distance)) in self.spheres.iter().enumerate() {
+            let radius = distance - clearance;
+            let point = origin + direction * skip;
+            if radius <= 0. || point.distance_squared(center) >= radius * radius {
+                return (skip, i);
+            }
+            // Where the ray leaves the sphere it's in
+            let to_center = center - origin;
+            let along = to_center.dot(direction);
+            let offset_squared = to_center.length_squared() - along * along;
+            skip = along + (radius * radius - offset_squared).max(0.).sqrt();
+        }
+        (skip, self.spheres.len())
+    }
+}
+
+/// Records every point a march evaluates as a sphere for [`RayWarmStart`].
+struct RecordingSdf<'a, S> {
+    sdf: &'a S,
+    spheres: RefCell<&'a mut Vec<(Vec3, f32)>>,
+}
+
+impl<S: LocalSdf> LocalSdf for RecordingSdf<'_, S> {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        let distance = self.sdf.distance(local_point);
+        self.spheres.borrow_mut().push((local_point, distance));
+        distance
+    }
+
+    fn gradient(&self, local_point: Vec3) -> Vec3 {
+        self.sdf.gradient(local_point)
+    }
+
+    fn record_march_iterations(&self, iterations: u32) {
+        self.sdf.record_march_iterations(iterations);
+    }
+
+    fn march_quality(&self) -> SdfMarchQuality {
+        self.sdf.march_quality()
+    }
+}
@@ -0,0 +1,168 @@
+use avian3d::prelude::*;
+use bevy::{
+    ecs::entity::EntityHashSet,
+    math::bounding::{Aabb3d, BoundingVolume},
+    platform::collections::HashMap,
+    prelude::*,
+};
+
+use crate::{SdfCollider, WorldOffset};
+
+/// Colliders covering more cells than this are checked by every query instead of being bucketed
+const MAX_CELLS_PER_COLLIDER: i32 = 64;
+
+/// Buckets SDF colliders into a uniform grid so spatial queries only visit colliders in the cells
+/// they pass through, for scenes with many small colliders.
+///
+/// Insert this resource to enable it, queries check every collider otherwise. The grid is rebuilt
+/// after every physics step, and follows changes of the [`WorldOffset`] in between.
+#[derive(Resource, Debug, Clone)]
+pub struct SdfQueryGrid {
+    /// Edge length of the grid cells, ideally a few times the size of a typical collider
+    pub cell_size: f32,
+    cells: HashMap<IVec3, Vec<Entity>>,
+    oversized: Vec<Entity>,
+    bounds: Option<Aabb3d>,
+    // The world offset the grid was built with
+    world_offset: Vec3,
+}
+
+impl SdfQueryGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            oversized: Vec::new(),
+            bounds: None,
+            world_offset: Vec3::ZERO,
+        }
+    }
+
+    fn cell(&self, point: Vec3) -> IVec3 {
+        (point / self.cell_size).floor().as_ivec3()
+    }
+
+    /// How far points at the current `world_offset` are from the same points in the grid.
+    fn shift(&self, world_offset: Vec3) -> Vec3 {
+        world_offset - self.world_offset
+    }
+
+    /// Colliders whose AABB may overlap `aabb`.
+    pub(crate) fn entities_in(&self, aabb: Aabb3d, world_offset: Vec3) -> EntityHashSet {
+        let mut entities = EntityHashSet::from_iter(self.oversized.iter().copied());
+        let Some(bounds) = self.bounds else {
+            return entities;
+        };
+        let aabb = aabb.translated_by(self.shift(world_offset));
+        let min = self.cell(Vec3::from(aabb.min).max(bounds.min.into()));
+        let max = self.cell(Vec3::from(aabb.max).min(bounds.max.into()));
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    if let Some(cell) = self.cells.get(&IVec3::new(x, y, z)) {
+                        entities.extend(cell.iter().copied());
+                    }
+                }
+            }
+        }
+        entities
+    }
+
+    /// Colliders whose AABB may be hit by a ray, found by walking the cells along it.
+    pub(crate) fn entities_along_ray(
+        &self,
+        origin: Vec3,
+        direction: Dir3,
+        max_distance: f32,
+        world_offset: Vec3,
+    ) -> EntityHashSet {
+        let mut entities = EntityHashSet::from_iter(self.oversized.iter().copied());
+        let Some(bounds) = self.bounds else {
+            return entities;
+        };
+        let origin = origin + self.shift(world_offset);
+        let Some((enter, exit)) = ray_span(origin, direction, max_distance, bounds) else {
+            return entities;
+        };
+
+        // Amanatides-Woo traversal from the cell where the ray enters the occupied bounds
+        let start = origin + direction * enter;
+        let mut cell = self.cell(start);
+        let end = self.cell(origin + direction * exit);
+        let step = direction.signum().as_ivec3();
+        let next_boundary = (cell.as_vec3() + step.max(IVec3::ZERO).as_vec3()) * self.cell_size;
+        // Axes the ray doesn't move along are never stepped on
+        let t_max = ((next_boundary - start) / *direction).abs();
+        let mut t_max = Vec3::select(t_max.is_nan_mask(), Vec3::INFINITY, t_max);
+        let t_delta = (self.cell_size / *direction).abs();
+        let max_steps = (end - cell).abs().element_sum() + 1;
+        for _ in 0..=max_steps {
+            if let Some(entities_in_cell) = self.cells.get(&cell) {
+                entities.extend(entities_in_cell.iter().copied());
+            }
+            if cell == end {
+                break;
+            }
+            let axis = if t_max.x < t_max.y && t_max.x < t_max.z {
+                0
+            } else if t_max.y < t_max.z {
+                1
+            } else {
+                2
+            };
+            cell[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
+        }
+        entities
+    }
+}
+
+/// The part of a ray inside an AABB, as distances along it.
+fn ray_span(origin: Vec3, direction: Dir3, max_distance: f32, aabb: Aabb3d) -> Option<(f32, f32)> {
+    let inv_dir = direction.recip();
+    let t1 = (Vec3::from(aabb.min) - origin) * inv_dir;
+    let t2 = (Vec3::from(aabb.max) - origin) * inv_dir;
+    let enter = t1.min(t2).max_element().max(0.);
+    let exit = t1.max(t2).min_element().min(max_distance);
+    (enter <= exit).then_some((enter, exit))
+}
+
+pub(crate) fn rebuild_query_grid(
+    mut grid: ResMut<SdfQueryGrid>,
+    colliders: Query<(Entity, &ColliderAabb), With<SdfCollider>>,
+    world_offset: Option<Res<WorldOffset>>,
+) {
+    let grid = &mut *grid;
+    grid.cells.values_mut().for_each(Vec::clear);
+    grid.oversized.clear();
+    grid.bounds = None;
+    grid.world_offset = world_offset.map_or(Vec3::ZERO, |offset| offset.0);
+
+    for (entity, aabb) in colliders.iter() {
+        if !aabb.min.is_finite() || !aabb.max.is_finite() {
+            grid.oversized.push(entity);
+            continue;
+        }
+        let cells = ((aabb.max - aabb.min) / grid.cell_size).floor() + 1.;
+        if cells.element_product() > MAX_CELLS_PER_COLLIDER as f32 {
+            grid.oversized.push(entity);
+            continue;
+        }
+        let min = grid.cell(aabb.min);
+        let max = grid.cell(aabb.max);
+
+        let aabb = Aabb3d::new((aabb.min + aabb.max) / 2., (aabb.max - aabb.min) / 2.);
+        grid.bounds = Some(grid.bounds.map_or(aabb, |bounds| bounds.merge(&aabb)));
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    grid.cells
+                        .entry(IVec3::new(x, y, z))
+                        .or_default()
+                        .push(entity);
+                }
+            }
+        }
+    }
+    grid.cells.retain(|_, entities| !entities.is_empty());
+}
@@ -0,0 +1,143 @@
+use avian3d::prelude::*;
+use bevy::{ecs::entity::EntityHashSet, prelude::*};
+use bevy_prototype_sdf::SdfProcessed;
+
+use crate::{SdfCollider, SdfColliderKind, SdfContext, SdfLodViewer};
+
+/// Limits how many colliders recompute their mass and bake their surface patches per frame after
+/// their SDF asset is processed, so loading a level with hundreds of SDF colliders doesn't cause a
+/// hitch.
+///
+/// AABBs and contacts follow the new asset right away, so bodies don't fall into geometry the
+/// broad phase doesn't know about yet. The rest waits for its turn in a queue, colliders closest
+/// to an awake dynamic body or an [`SdfLodViewer`] first. Until then they keep the mass derived
+/// from the previous version of their asset, and contacts aren't culled by baked patches. Without
+/// this resource every collider is refreshed right away.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdfRecomputeBudget {
+    pub colliders_per_frame: usize,
+}
+
+/// Colliders whose SDF asset was processed again, waiting for the [`SdfRecomputeBudget`] to
+/// recompute their mass and patches.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct SdfRecomputeQueue(pub EntityHashSet);
+
+/// Invalidates everything derived from an SDF asset once it is processed again, like after a
+/// hot reload or an edit through [`SdfDeformer`](crate::SdfDeformer).
+///
+/// Bodies touching the collider are woken up, and its AABB and contacts are refreshed during the
+/// next step. Changing the collider makes avian recompute its mass properties, unless an
+/// [`SdfRecomputeBudget`] queues the collider for that instead.
+pub(crate) fn invalidate_reloaded_colliders(
+    trigger: On<SdfProcessed>,
+    mut colliders: Query<(Entity, &mut SdfCollider)>,
+    collider_of: Query<&ColliderOf>,
+    collisions: Option<Collisions>,
+    budget: Option<Res<SdfRecomputeBudget>>,
+    mut queue: ResMut<SdfRecomputeQueue>,
+    mut commands: Commands,
+) {
+    let SdfProcessed(id) = trigger.event();
+    let id = AssetId::from(*id);
+    for (entity, mut collider) in colliders.iter_mut() {
+        let SdfColliderKind::Arbitrary(handle) = collider.collider() else {
+            continue;
+        };
+        if handle.id() != id {
+            continue;
+        }
+        if budget.is_some() {
+            queue.0.insert(entity);
+            collider.bypass_change_detection().reloaded = true;
+        } else {
+            collider.reloaded = true;
+        }
+
+        // Only missing if contacts are handled without avian's collision pipeline
+        if let Some(collisions) = &collisions {
+            wake_touching_bodies(entity, collisions, &collider_of, &mut commands);
+        }
+    }
+}
+
+pub(crate) fn wake_touching_bodies(
+    entity: Entity,
+    collisions: &Collisions,
+    collider_of: &Query<&ColliderOf>,
+    commands: &mut Commands,
+) {
+    for pair in collisions.collisions_with(entity) {
+        for other in [pair.collider1, pair.collider2] {
+            let body = collider_of.get(other).map_or(other, |c| c.body);
+            commands
+                .entity(body)
+                .try_remove::<Sleeping>()
+                .try_insert(TimeSleeping::default());
+        }
+    }
+}
+
+/// Recomputes the mass of the queued colliders closest to where things happen, up to the budget,
+/// by marking them as changed.
+pub(crate) fn recompute_queued_colliders(
+    budget: Option<Res<SdfRecomputeBudget>>,
+    mut queue: ResMut<SdfRecomputeQueue>,
+    mut colliders: Query<(&mut SdfCollider, &Position)>,
+    bodies: Query<(&RigidBody, &Position), Without<Sleeping>>,
+    viewers: Query<&GlobalTransform, With<SdfLodViewer>>,
+) {
+    if queue.0.is_empty() {
+        return;
+    }
+    // Colliders despawned while queued drop out, and everything is flushed without a budget
+    queue.0.retain(|&entity| colliders.contains(entity));
+    let limit = budget.map_or(usize::MAX, |budget| budget.colliders_per_frame);
+
+    let focus: Vec<Vec3> = bodies
+        .iter()
+        .filter(|(rb, _)| rb.is_dynamic())
+        .map(|(_, pos)| pos.0)
+        .chain(viewers.iter().map(GlobalTransform::translation))
+        .collect();
+    let mut queued: Vec<(f32, Entity)> = queue
+        .0
+        .iter()
+        .filter_map(|&entity| {
+            let (_, pos) = colliders.get(entity).ok()?;
+            let closest = focus
+                .iter()
+                .map(|focus| focus.distance_squared(pos.0))
+                .fold(f32::INFINITY, f32::min);
+            Some((closest, entity))
+        })
+        .collect();
+    queued.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    for &(_, entity) in queued.iter().take(limit) {
+        queue.0.remove(&entity);
+        if let Ok((mut collider, _)) = colliders.get_mut(entity) {
+            collider.set_changed();
+        }
+    }
+}
+
+pub(crate) fn refresh_reloaded_aabbs(
+    mut colliders: Query<(&SdfCollider, &Position, &Rotation, &mut ColliderAabb)>,
+    context: SdfContext,
+) {
+    for (collider, pos, rot, mut aabb) in colliders.iter_mut() {
+        if collider.reloaded {
+            *aabb = collider.world_aabb(Isometry3d::new(pos.0, rot.0), &context);
+        }
+    }
+}
+
+pub(crate) fn clear_reloaded(mut colliders: Query<&mut SdfCollider>) {
+    for mut collider in colliders.iter_mut() {
+        if collider.reloaded {
+            // Don't trigger another round of change detection for the flag itself
+            collider.bypass_change_detection().reloaded = false;
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{primitives::mean_curvature, SdfCollider, SdfContext};
+
+/// Damps the rotation of a body rolling over curved SDF surfaces.
+///
+/// Flat ground has no curvature and leaves the body untouched, while bumps slow it down in
+/// proportion to how sharply the surface curves at the contacts.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SdfRollingResistance {
+    /// Fraction of angular velocity lost per second, per unit of curvature
+    pub coefficient: f32,
+}
+
+impl SdfRollingResistance {
+    pub fn new(coefficient: f32) -> Self {
+        Self { coefficient }
+    }
+}
+
+pub(crate) fn apply_rolling_resistance(
+    collisions: Collisions,
+    mut bodies: Query<(Entity, &SdfRollingResistance, &mut AngularVelocity)>,
+    colliders: Query<(&Position, &Rotation, &SdfCollider)>,
+    context: SdfContext,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    for (entity, resistance, mut ang_vel) in bodies.iter_mut() {
+        let mut curvature = 0f32;
+        for pair in collisions.collisions_with(entity) {
+            let other = if pair.collider1 == entity {
+                pair.collider2
+            } else {
+                pair.collider1
+            };
+            let Ok((pos, rot, collider)) = colliders.get(other) else {
+                continue;
+            };
+            let Some(sdf) = collider.local_sdf(&context) else {
+                continue;
+            };
+
+            let inv_rot = rot.0.inverse();
+            for point in pair.manifolds.iter().flat_map(|m| m.points.iter()) {
+                let local_point = inv_rot * (point.point - pos.0) / collider.scale;
+                let local_curvature = mean_curvature(&sdf, local_point);
+                curvature = curvature.max(local_curvature.abs() / collider.scale);
+            }
+        }
+
+        if curvature > 0. {
+            ang_vel.0 *= (1. - resistance.coefficient * curvature * dt).max(0.);
+        }
+    }
+}
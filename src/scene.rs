@@ -0,0 +1,168 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{SdfCollider, SdfColliderKind};
+
+/// The asset path of an [`SdfCollider`] using an SDF asset, which lets it round-trip through scenes.
+///
+/// This is recorded automatically for colliders using a loaded asset, and when spawned from a
+/// scene the collider's handle is loaded from this path.
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug)]
+pub struct SdfAssetPath(pub String);
+
+pub(crate) fn record_sdf_asset_paths(
+    mut commands: Commands,
+    query: Query<(Entity, &SdfCollider), (Changed<SdfCollider>, Without<SdfAssetPath>)>,
+) {
+    for (entity, collider) in query.iter() {
+        let SdfColliderKind::Arbitrary(handle) = collider.collider() else {
+            continue;
+        };
+        let Some(path) = handle.path() else {
+            continue;
+        };
+        commands
+            .entity(entity)
+            .insert(SdfAssetPath(path.to_string()));
+    }
+}
+
+pub(crate) fn resolve_sdf_asset_paths(
+    server: Res<AssetServer>,
+    mut query: Query<(&SdfAssetPath, &mut SdfCollider), Changed<SdfAssetPath>>,
+) {
+    for (path, mut collider) in query.iter_mut() {
+        if let SdfColliderKind::Arbitrary(handle) = collider.collider() {
+            if handle.path().is_some_and(|p| p.to_string() == path.0) {
+                continue;
+            }
+        }
+        collider.collider = SdfColliderKind::Arbitrary(server.load(path.0.clone()));
+    }
+}
+
+/// Describes an [`SdfCollider`] declaratively, for scene and glTF pipelines.
+///
+/// Replaced by the collider it describes as soon as it's inserted.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug)]
+pub enum SdfColliderConstructor {
+    Sphere {
+        radius: f32,
+    },
+    Capsule {
+        radius: f32,
+        length: f32,
+    },
+    Ellipsoid {
+        half_size: Vec3,
+    },
+    /// An SDF asset loaded from this path
+    Asset {
+        path: String,
+    },
+}
+
+impl SdfColliderConstructor {
+    pub fn collider(&self, server: &AssetServer) -> SdfCollider {
+        match self {
+            &Self::Sphere { radius } => SdfCollider::sphere(radius),
+            &Self::Capsule { radius, length } => SdfCollider::capsule(radius, length),
+            &Self::Ellipsoid { half_size } => SdfCollider::ellipsoid(half_size),
+            Self::Asset { path } => SdfCollider::sdf(server.load(path.clone())),
+        }
+    }
+}
+
+/// Adds an [`SdfColliderConstructor`] to the named descendants of this entity, including ones
+/// spawned later like the nodes of a scene that is still loading.
+#[derive(Component, Debug, Clone, Default, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug)]
+pub struct SdfColliderConstructorHierarchy {
+    /// Used for descendants without an entry in `config`
+    pub default_constructor: Option<SdfColliderConstructor>,
+    /// Constructors by [`Name`], `None` skips descendants with that name
+    pub config: HashMap<String, Option<SdfColliderConstructor>>,
+}
+
+impl SdfColliderConstructorHierarchy {
+    pub fn new(default_constructor: Option<SdfColliderConstructor>) -> Self {
+        Self {
+            default_constructor,
+            config: HashMap::default(),
+        }
+    }
+
+    pub fn with_constructor_for_name(
+        mut self,
+        name: impl Into<String>,
+        constructor: Option<SdfColliderConstructor>,
+    ) -> Self {
+        self.config.insert(name.into(), constructor);
+        self
+    }
+
+    fn constructor_for(&self, name: &Name) -> Option<&SdfColliderConstructor> {
+        match self.config.get(name.as_str()) {
+            Some(constructor) => constructor.as_ref(),
+            None => self.default_constructor.as_ref(),
+        }
+    }
+}
+
+pub(crate) fn construct_sdf_colliders(
+    trigger: On<Add, SdfColliderConstructor>,
+    mut commands: Commands,
+    constructors: Query<&SdfColliderConstructor>,
+    server: Res<AssetServer>,
+) {
+    let Ok(constructor) = constructors.get(trigger.entity) else {
+        return;
+    };
+    commands
+        .entity(trigger.entity)
+        .insert(constructor.collider(&server))
+        .remove::<SdfColliderConstructor>();
+}
+
+pub(crate) fn construct_hierarchy_colliders(
+    mut commands: Commands,
+    new_hierarchies: Query<
+        (Entity, &SdfColliderConstructorHierarchy),
+        Added<SdfColliderConstructorHierarchy>,
+    >,
+    new_names: Query<Entity, Added<Name>>,
+    hierarchies: Query<&SdfColliderConstructorHierarchy>,
+    names: Query<&Name, Without<SdfCollider>>,
+    parents: Query<&ChildOf>,
+    children: Query<&Children>,
+) {
+    let mut construct = |entity: Entity, hierarchy: &SdfColliderConstructorHierarchy| {
+        let Ok(name) = names.get(entity) else {
+            return;
+        };
+        if let Some(constructor) = hierarchy.constructor_for(name) {
+            commands.entity(entity).insert(constructor.clone());
+        }
+    };
+
+    for (root, hierarchy) in new_hierarchies.iter() {
+        for entity in children.iter_descendants(root) {
+            construct(entity, hierarchy);
+        }
+    }
+
+    // Descendants spawned after the hierarchy, unless the hierarchy was handled above
+    for entity in new_names.iter() {
+        let Some((root, hierarchy)) = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| Some((ancestor, hierarchies.get(ancestor).ok()?)))
+        else {
+            continue;
+        };
+        if new_hierarchies.contains(root) {
+            continue;
+        }
+        construct(entity, hierarchy);
+    }
+}
@@ -0,0 +1,20 @@
+use std::{cell::RefCell, thread::LocalKey};
+
+/// A per-thread buffer reused across calls, declared with [`thread_local!`].
+pub(crate) type Scratch<T> = LocalKey<RefCell<Vec<T>>>;
+
+/// Runs `f` with the cleared buffer of this thread, keeping its allocation for the next call.
+///
+/// Nested calls on the same buffer get a fresh one instead of panicking.
+pub(crate) fn with_scratch<T, R>(
+    scratch: &'static Scratch<T>,
+    f: impl FnOnce(&mut Vec<T>) -> R,
+) -> R {
+    scratch.with(|buffer| match buffer.try_borrow_mut() {
+        Ok(mut buffer) => {
+            buffer.clear();
+            f(&mut buffer)
+        }
+        Err(_) => f(&mut Vec::new()),
+    })
+}
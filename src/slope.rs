@@ -0,0 +1,67 @@
+use avian3d::prelude::ContactManifold;
+use bevy::prelude::*;
+
+/// Scales the friction of contacts with an [`SdfCollider`](crate::SdfCollider) by how steep the
+/// surface is at the contact, so characters slide down slopes of the terrain.
+///
+/// Friction is unchanged on slopes up to `min_angle` from `up`, and falls off linearly to zero at
+/// `max_angle`. `up` is in the local space of the collider.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Debug)]
+pub struct SlopeFriction {
+    pub up: Dir3,
+    pub min_angle: f32,
+    pub max_angle: f32,
+}
+
+impl SlopeFriction {
+    /// Makes slopes steeper than `max_angle` frictionless.
+    pub fn new(max_angle: f32) -> Self {
+        Self {
+            up: Dir3::Y,
+            min_angle: max_angle,
+            max_angle,
+        }
+    }
+
+    /// Starts fading out the friction at `min_angle` instead of cutting it off at the max angle.
+    pub fn with_falloff(mut self, min_angle: f32) -> Self {
+        self.min_angle = min_angle;
+        self
+    }
+
+    pub fn with_up(mut self, up: Dir3) -> Self {
+        self.up = up;
+        self
+    }
+
+    fn scale(&self, rotation: Quat, surface_normal: Vec3) -> f32 {
+        let angle = (rotation * self.up).angle_between(surface_normal);
+        if angle >= self.max_angle {
+            return 0.;
+        }
+        if angle <= self.min_angle {
+            return 1.;
+        }
+        (self.max_angle - angle) / (self.max_angle - self.min_angle)
+    }
+}
+
+/// Sets the friction of every manifold to `friction` scaled by the slopes of the pair.
+pub(crate) fn apply_slope_friction(
+    contacts: &mut [ContactManifold],
+    friction: f32,
+    surface1: Option<(&SlopeFriction, Quat)>,
+    surface2: Option<(&SlopeFriction, Quat)>,
+) {
+    // Manifold normals point from the first collider to the second
+    for manifold in contacts.iter_mut() {
+        let scale1 = surface1.map_or(1., |(slope, rotation)| {
+            slope.scale(rotation, manifold.normal)
+        });
+        let scale2 = surface2.map_or(1., |(slope, rotation)| {
+            slope.scale(rotation, -manifold.normal)
+        });
+        manifold.friction = friction * scale1 * scale2;
+    }
+}
@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+
 use avian3d::{
     collision::collider::{BoundedShape, QueryCollider, QueryShapeCastHit, SingleContext},
     prelude::{AnyCollider, ColliderAabb, Rotation},
@@ -13,11 +15,20 @@ use bevy_prototype_sdf::{Sdf, Sdf3d};
 
 use crate::{
     adder::{Contact, ManifoldAdder, Manifolds},
-    collider::SdfColliderKind,
-    primitives::{march_edge, Collider, MarchResult, ScaledIsometry3d},
+    collider::{ColliderSdf, SdfColliderKind},
+    context::{SdfContext, StartPenetrating},
+    primitives::{
+        capsule_between, march_edge, march_exit, march_ray, sdf_sdf_contact, Collider, Ellipsoid,
+        LocalSdf, MarchResult, ScaledIsometry3d,
+    },
+    scratch::with_scratch,
     SdfCollider,
 };
 
+thread_local! {
+    static INTERSECTION_CONTACTS: RefCell<Vec<Contact>> = const { RefCell::new(Vec::new()) };
+}
+
 #[derive(Debug)]
 pub enum ColliderShape {
     Sphere(Sphere),
@@ -69,35 +80,16 @@ impl QueryCollider for SdfCollider {
     type Shape = ColliderShape;
 
     fn ray_hit(&self, ray: Ray, solid: bool, context: SingleContext<Self::Context>) -> f32 {
-        match &self.collider {
-            SdfColliderKind::Arbitrary(handle) => {
-                let Some(sdf) = context.get(handle.id()) else {
-                    return f32::INFINITY;
-                };
-                let res = march_edge(
-                    &sdf.1,
-                    ray.origin.into(),
-                    ray.direction.into(),
-                    0.001,
-                    ray.tmax,
-                );
-                let MarchResult::Hit(toi, _) = res else {
-                    return f32::INFINITY;
-                };
-                *toi
-            }
-            &SdfColliderKind::Sphere(Sphere { radius }) => {
-                let bray = Ray3d::new(ray.origin.into(), Dir3::new_unchecked(ray.direction.into()));
-                local_ray_distance_with_sphere(radius, bray, solid)
-                    .filter(|&distance| distance <= ray.tmax)
-                    .unwrap_or(f32::INFINITY)
-            }
-            SdfColliderKind::Capsule(capsule) => {
-                let bray = Ray3d::new(ray.origin.into(), Dir3::new_unchecked(ray.direction.into()));
-                local_ray_distance_with_capsule(capsule, bray, ray.tmax, solid)
-                    .unwrap_or(f32::INFINITY)
-            }
-        }
+        let Some(sdf) = self.local_sdf(&context) else {
+            return f32::INFINITY;
+        };
+        sdf.ray_hit(
+            ray.origin.into(),
+            Dir3::new_unchecked(ray.direction.into()),
+            ray.tmax,
+            solid,
+        )
+        .unwrap_or(f32::INFINITY)
     }
 
     fn ray_normal(
@@ -107,16 +99,8 @@ impl QueryCollider for SdfCollider {
         _: bool,
         context: SingleContext<Self::Context>,
     ) -> Vec3 {
-        match &self.collider {
-            SdfColliderKind::Arbitrary(handle) => {
-                let Some(sdf) = context.get(handle.id()) else {
-                    return Vec3::Y;
-                };
-                sdf.1.gradient(point)
-            }
-            SdfColliderKind::Sphere(s) => s.gradient(point),
-            SdfColliderKind::Capsule(c) => c.gradient(point),
-        }
+        self.local_sdf(&context)
+            .map_or(Vec3::Y, |sdf| sdf.gradient(point))
     }
 
     fn shape_cast(
@@ -128,60 +112,7 @@ impl QueryCollider for SdfCollider {
         range: (f32, f32),
         context: SingleContext<Self::Context>,
     ) -> Option<QueryShapeCastHit> {
-        match &self.collider {
-            SdfColliderKind::Arbitrary(handle) => {
-                let Some(sdf) = context.get(handle.id()) else {
-                    return None;
-                };
-                let start = local_origin + local_dir * range.0;
-                let res = march_edge(
-                    &sdf.1,
-                    start,
-                    local_dir.into(),
-                    shape.radius,
-                    range.1 - range.0,
-                );
-                let MarchResult::Hit(toi, distance) = res else {
-                    return None;
-                };
-                let pos = start + local_dir * *toi;
-                let gradient = sdf.1.gradient(pos);
-                Some(QueryShapeCastHit {
-                    distance: range.0 + *toi,
-                    point: pos - gradient * distance,
-                    normal: gradient,
-                })
-            }
-            SdfColliderKind::Sphere(s) => {
-                let sum = shape.radius + s.radius;
-                let bray = Ray3d::new(local_origin.into(), Dir3::new_unchecked(local_dir.into()));
-                local_ray_distance_with_sphere(sum, bray, true)
-                    .filter(|&distance| distance <= range.1)
-                    .map(|distance| {
-                        let normal = (local_origin + local_dir * distance).normalize_or(Vec3::Y);
-                        QueryShapeCastHit {
-                            distance,
-                            point: normal * s.radius,
-                            normal,
-                        }
-                    })
-            }
-            SdfColliderKind::Capsule(c) => {
-                let expanded = Capsule3d {
-                    radius: c.radius + shape.radius,
-                    half_length: c.half_length,
-                };
-                let bray = Ray3d::new(local_origin.into(), Dir3::new_unchecked(local_dir.into()));
-                local_ray_distance_with_capsule(&expanded, bray, range.1, true).map(|distance| {
-                    let normal = c.gradient(local_origin + local_dir * distance);
-                    QueryShapeCastHit {
-                        distance,
-                        point: normal * c.radius,
-                        normal,
-                    }
-                })
-            }
-        }
+        self.local_shape_cast(shape, local_origin, local_dir, range, &context)
     }
 
     fn shape_intersection(
@@ -191,98 +122,594 @@ impl QueryCollider for SdfCollider {
         local_origin: Vec3,
         context: SingleContext<Self::Context>,
     ) -> bool {
-        let mut contacts = Vec::<Contact>::new();
-        let manifolds = Manifolds(&mut contacts);
+        with_scratch(&INTERSECTION_CONTACTS, |contacts| {
+            self.local_shape_contacts(shape, *shape_rotation, local_origin, 0., &context, contacts);
+            contacts.iter().any(|c| c.penetration >= 0.)
+        })
+    }
+
+    fn closest_point(
+        &self,
+        point: Vec3,
+        solid: bool,
+        context: SingleContext<Self::Context>,
+    ) -> Vec3 {
+        let Some(sdf) = self.local_sdf(&context) else {
+            return point;
+        };
+        sdf.closest_point(point, solid)
+    }
+
+    fn contains_point(&self, point: Vec3, context: SingleContext<Self::Context>) -> bool {
+        self.local_sdf(&context)
+            .is_some_and(|sdf| sdf.distance(point) <= 0.)
+    }
+}
+
+impl SdfCollider {
+    /// Computes the contacts between this collider and a shape placed in its local space,
+    /// replacing the contents of `contacts`.
+    pub(crate) fn local_shape_contacts(
+        &self,
+        shape: &ColliderShape,
+        shape_rotation: Quat,
+        local_origin: Vec3,
+        pred_dist: f32,
+        context: &SdfContext,
+        contacts: &mut Vec<Contact>,
+    ) {
+        if let Some(placeholder) = context.placeholder(self) {
+            return self.with_shape(placeholder).local_shape_contacts(
+                shape,
+                shape_rotation,
+                local_origin,
+                pred_dist,
+                context,
+                contacts,
+            );
+        }
+
+        contacts.clear();
+        let manifolds = Manifolds::new(contacts);
         let iso1 = Isometry3d::default();
-        let iso2 = Isometry3d::new(local_origin, *shape_rotation);
+        let iso2 = Isometry3d::new(local_origin, shape_rotation);
         match &self.collider {
-            SdfColliderKind::Sphere(s1) => match shape {
-                ColliderShape::Sphere(s2) => {
-                    s1.get_collisions(iso1, s2, iso2, ManifoldAdder::normal(manifolds), 0.)
+            &SdfColliderKind::Sphere(mut s1) => {
+                s1.radius *= self.scale;
+                match shape {
+                    ColliderShape::Sphere(s2) => s1.get_collisions(
+                        iso1,
+                        s2,
+                        iso2,
+                        ManifoldAdder::normal(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Capsule(c2) => s1.get_collisions(
+                        iso1,
+                        c2,
+                        iso2,
+                        ManifoldAdder::normal(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Arbitrary(handle2) => {
+                        let Some(sdf2) = context.get(handle2.id()) else {
+                            return;
+                        };
+                        let scaled = ScaledIsometry3d {
+                            iso: iso2,
+                            scale: 1.,
+                        };
+                        s1.get_collisions(
+                            iso1,
+                            &sdf2.1,
+                            scaled,
+                            ManifoldAdder::normal(manifolds),
+                            pred_dist,
+                        )
+                    }
                 }
-                ColliderShape::Capsule(c2) => {
-                    s1.get_collisions(iso1, c2, iso2, ManifoldAdder::normal(manifolds), 0.)
+            }
+            &SdfColliderKind::Capsule(mut c1) => {
+                c1.radius *= self.scale;
+                c1.half_length *= self.scale;
+                match shape {
+                    ColliderShape::Sphere(s2) => s2.get_collisions(
+                        iso2,
+                        &c1,
+                        iso1,
+                        ManifoldAdder::flipped(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Capsule(c2) => c1.get_collisions(
+                        iso1,
+                        c2,
+                        iso2,
+                        ManifoldAdder::normal(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Arbitrary(handle2) => {
+                        let Some(sdf2) = context.get(handle2.id()) else {
+                            return;
+                        };
+                        let scaled = ScaledIsometry3d {
+                            iso: iso2,
+                            scale: 1.,
+                        };
+                        c1.get_collisions(
+                            iso1,
+                            &sdf2.1,
+                            scaled,
+                            ManifoldAdder::normal(manifolds),
+                            pred_dist,
+                        )
+                    }
                 }
-                ColliderShape::Arbitrary(handle2) => {
-                    let Some(sdf2) = context.get(handle2.id()) else {
-                        return false;
-                    };
-                    let scaled = ScaledIsometry3d {
-                        iso: iso2,
-                        scale: 1.,
-                    };
-                    s1.get_collisions(iso1, &sdf2.1, scaled, ManifoldAdder::normal(manifolds), 0.)
+            }
+            SdfColliderKind::Ellipsoid(e1) => {
+                let scaled1 = ScaledIsometry3d {
+                    iso: iso1,
+                    scale: self.scale,
+                };
+                match shape {
+                    ColliderShape::Sphere(s2) => s2.get_collisions(
+                        iso2,
+                        e1,
+                        scaled1,
+                        ManifoldAdder::flipped(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Capsule(c2) => c2.get_collisions(
+                        iso2,
+                        e1,
+                        scaled1,
+                        ManifoldAdder::flipped(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Arbitrary(handle2) => {
+                        let Some(sdf2) = context.get(handle2.id()) else {
+                            return;
+                        };
+                        let scaled2 = ScaledIsometry3d {
+                            iso: iso2,
+                            scale: 1.,
+                        };
+                        e1.get_collisions(
+                            scaled1,
+                            &sdf2.1,
+                            scaled2,
+                            ManifoldAdder::normal(manifolds),
+                            pred_dist,
+                        )
+                    }
                 }
-            },
-            SdfColliderKind::Capsule(c1) => match shape {
-                ColliderShape::Sphere(s2) => {
-                    s2.get_collisions(iso2, c1, iso1, ManifoldAdder::flipped(manifolds), 0.)
+            }
+            // Queries see the sphere at the body's position, not the tube behind it
+            &SdfColliderKind::SweptSphere(swept) => self
+                .with_shape(Sphere::new(swept.radius))
+                .local_shape_contacts(
+                    shape,
+                    shape_rotation,
+                    local_origin,
+                    pred_dist,
+                    context,
+                    manifolds.contacts,
+                ),
+            SdfColliderKind::Segment(_) => {
+                // Queried as a capsule, in a space where the capsule is centered and upright
+                let (capsule, capsule_iso) = self.line_capsule(iso1).unwrap();
+                let inv_iso = capsule_iso.inverse();
+                let contacts = manifolds.contacts;
+                self.with_shape(capsule).local_shape_contacts(
+                    shape,
+                    inv_iso.rotation * shape_rotation,
+                    inv_iso.transform_point(local_origin).into(),
+                    pred_dist,
+                    context,
+                    contacts,
+                );
+                let offset = Vec3::from(capsule_iso.translation);
+                for contact in contacts.iter_mut() {
+                    *contact = contact.transformed(capsule_iso);
+                    contact.anchor1 += offset;
+                    contact.local_point1 = capsule_iso.transform_point(contact.local_point1).into();
                 }
-                ColliderShape::Capsule(c2) => {
-                    c1.get_collisions(iso1, c2, iso2, ManifoldAdder::normal(manifolds), 0.)
+            }
+            SdfColliderKind::SphereCluster(cluster) => {
+                let scaled1 = ScaledIsometry3d {
+                    iso: iso1,
+                    scale: self.scale,
+                };
+                match shape {
+                    ColliderShape::Sphere(s2) => s2.get_collisions(
+                        iso2,
+                        cluster,
+                        scaled1,
+                        ManifoldAdder::flipped(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Capsule(c2) => c2.get_collisions(
+                        iso2,
+                        cluster,
+                        scaled1,
+                        ManifoldAdder::flipped(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Arbitrary(handle2) => {
+                        let Some(sdf2) = context.get(handle2.id()) else {
+                            return;
+                        };
+                        let scaled2 = ScaledIsometry3d {
+                            iso: iso2,
+                            scale: 1.,
+                        };
+                        cluster.get_collisions(
+                            scaled1,
+                            &sdf2.1,
+                            scaled2,
+                            ManifoldAdder::normal(manifolds),
+                            pred_dist,
+                        )
+                    }
                 }
-                ColliderShape::Arbitrary(handle2) => {
-                    let Some(sdf2) = context.get(handle2.id()) else {
-                        return false;
-                    };
-                    let scaled = ScaledIsometry3d {
-                        iso: iso2,
-                        scale: 1.,
-                    };
-                    c1.get_collisions(iso1, &sdf2.1, scaled, ManifoldAdder::normal(manifolds), 0.)
+            }
+            SdfColliderKind::Polyline(polyline) => {
+                let scaled1 = ScaledIsometry3d {
+                    iso: iso1,
+                    scale: self.scale,
+                };
+                match shape {
+                    ColliderShape::Sphere(s2) => s2.get_collisions(
+                        iso2,
+                        polyline,
+                        scaled1,
+                        ManifoldAdder::flipped(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Capsule(c2) => c2.get_collisions(
+                        iso2,
+                        polyline,
+                        scaled1,
+                        ManifoldAdder::flipped(manifolds),
+                        pred_dist,
+                    ),
+                    ColliderShape::Arbitrary(handle2) => {
+                        let Some(sdf2) = context.get(handle2.id()) else {
+                            return;
+                        };
+                        let scaled2 = ScaledIsometry3d {
+                            iso: iso2,
+                            scale: 1.,
+                        };
+                        polyline.get_collisions(
+                            scaled1,
+                            &sdf2.1,
+                            scaled2,
+                            ManifoldAdder::normal(manifolds),
+                            pred_dist,
+                        )
+                    }
                 }
-            },
-            SdfColliderKind::Arbitrary(handle) => {
-                let Some(sdf1) = context.get(handle.id()) else {
-                    return false;
+            }
+            SdfColliderKind::Arbitrary(_) | SdfColliderKind::Custom(_) => {
+                let Some(sdf1) = self.sdf_source(context) else {
+                    return;
                 };
+                let shelled1 = self.shelled(self.parameterized(sdf1, context));
                 let scaled1 = ScaledIsometry3d {
                     iso: iso1,
-                    scale: 1.,
+                    scale: self.scale,
                 };
                 match shape {
                     ColliderShape::Sphere(s2) => s2.get_collisions(
                         iso2,
-                        &sdf1.1,
+                        &shelled1,
                         scaled1,
                         ManifoldAdder::flipped(manifolds),
-                        0.,
+                        pred_dist,
                     ),
                     ColliderShape::Capsule(c2) => c2.get_collisions(
                         iso2,
-                        &sdf1.1,
+                        &shelled1,
                         scaled1,
                         ManifoldAdder::flipped(manifolds),
-                        0.,
+                        pred_dist,
                     ),
                     ColliderShape::Arbitrary(handle2) => {
                         let Some(sdf2) = context.get(handle2.id()) else {
-                            return false;
+                            return;
+                        };
+                        let scaled2 = ScaledIsometry3d {
+                            iso: iso2,
+                            scale: 1.,
                         };
-                        _ = (sdf1, sdf2);
-                        todo!();
+                        sdf_sdf_contact(
+                            &shelled1,
+                            scaled1,
+                            &sdf2.1,
+                            scaled2,
+                            ManifoldAdder::normal(manifolds),
+                            pred_dist,
+                        )
                     }
                 }
             }
         }
+    }
 
-        contacts.iter().any(|c| c.penetration >= 0.)
+    /// Sweeps a sphere placed in this collider's local space, before its scale is applied.
+    pub(crate) fn local_shape_cast(
+        &self,
+        shape: &Sphere,
+        local_origin: Vec3,
+        local_dir: Dir3,
+        range: (f32, f32),
+        context: &SdfContext,
+    ) -> Option<QueryShapeCastHit> {
+        let scale = self.scale;
+        let hit = self.unscaled_shape_cast(
+            &Sphere::new(shape.radius / scale),
+            local_origin / scale,
+            local_dir,
+            (range.0 / scale, range.1 / scale),
+            context,
+        )?;
+        Some(QueryShapeCastHit {
+            distance: hit.distance * scale,
+            point: hit.point * scale,
+            normal: hit.normal,
+        })
     }
 
-    fn closest_point(
+    fn unscaled_shape_cast(
         &self,
-        point: Vec3,
+        shape: &Sphere,
+        local_origin: Vec3,
+        local_dir: Dir3,
+        range: (f32, f32),
+        context: &SdfContext,
+    ) -> Option<QueryShapeCastHit> {
+        let sdf = self.local_sdf(context)?;
+        let start = local_origin + local_dir * range.0;
+        let start_distance = sdf.distance(start);
+        if start_distance < shape.radius {
+            return match context.query_config.start_penetrating {
+                StartPenetrating::Ignore => None,
+                StartPenetrating::ReportZero => Some(QueryShapeCastHit {
+                    distance: range.0,
+                    point: start,
+                    normal: -*local_dir,
+                }),
+                StartPenetrating::ReportWithDepenetrationNormal => {
+                    let normal = sdf.gradient(start).normalize_or(-*local_dir);
+                    Some(QueryShapeCastHit {
+                        distance: range.0,
+                        point: start - normal * start_distance,
+                        normal,
+                    })
+                }
+            };
+        }
+
+        match &sdf {
+            ColliderSdf::Asset(sdf) => march_shape_cast(sdf, shape, local_origin, local_dir, range),
+            ColliderSdf::Ellipsoid(e) => march_shape_cast(e, shape, local_origin, local_dir, range),
+            ColliderSdf::Cluster(c) => march_shape_cast(c, shape, local_origin, local_dir, range),
+            ColliderSdf::Line(l) => march_shape_cast(l, shape, local_origin, local_dir, range),
+            ColliderSdf::Polyline(p) => march_shape_cast(p, shape, local_origin, local_dir, range),
+            ColliderSdf::Sphere(s) => {
+                let sum = shape.radius + s.radius;
+                let bray = Ray3d::new(local_origin.into(), Dir3::new_unchecked(local_dir.into()));
+                local_ray_distance_with_sphere(sum, bray, true)
+                    .filter(|&distance| distance <= range.1)
+                    .map(|distance| {
+                        let normal = (local_origin + local_dir * distance).normalize_or(Vec3::Y);
+                        QueryShapeCastHit {
+                            distance,
+                            point: normal * s.radius,
+                            normal,
+                        }
+                    })
+            }
+            ColliderSdf::Capsule(c) => {
+                let expanded = Capsule3d {
+                    radius: c.radius + shape.radius,
+                    half_length: c.half_length,
+                };
+                let bray = Ray3d::new(local_origin.into(), Dir3::new_unchecked(local_dir.into()));
+                local_ray_distance_with_capsule(&expanded, bray, range.1, true).map(|distance| {
+                    let normal = c.gradient(local_origin + local_dir * distance);
+                    QueryShapeCastHit {
+                        distance,
+                        point: normal * c.radius,
+                        normal,
+                    }
+                })
+            }
+        }
+    }
+}
+
+fn march_shape_cast(
+    sdf: &impl LocalSdf,
+    shape: &Sphere,
+    local_origin: Vec3,
+    local_dir: Dir3,
+    range: (f32, f32),
+) -> Option<QueryShapeCastHit> {
+    let start = local_origin + local_dir * range.0;
+    let res = march_edge(
+        sdf,
+        start,
+        local_dir.into(),
+        shape.radius,
+        range.1 - range.0,
+    );
+    let MarchResult::Hit(toi, distance) = res else {
+        return None;
+    };
+    let pos = start + local_dir * *toi;
+    let gradient = sdf.gradient(pos);
+    Some(QueryShapeCastHit {
+        distance: range.0 + *toi,
+        point: pos - gradient * distance,
+        normal: gradient,
+    })
+}
+
+/// A ray hit with everything the march found along the way.
+#[derive(Clone, Copy, Debug)]
+pub struct RayHitDetails {
+    pub distance: f32,
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Whether the ray started inside the collider
+    pub inside: bool,
+    /// Number of SDF evaluations used by the march, zero for analytic shapes
+    pub iterations: u32,
+}
+
+impl ColliderSdf<'_> {
+    pub(crate) fn ray_hit_detailed(
+        &self,
+        local_origin: Vec3,
+        local_dir: Dir3,
+        max_distance: f32,
         solid: bool,
-        context: SingleContext<Self::Context>,
-    ) -> Vec3 {
-        _ = (point, solid, context);
-        todo!()
+    ) -> Option<RayHitDetails> {
+        let inside = self.distance(local_origin) < 0.;
+        let (distance, iterations) = match self {
+            Self::Asset(sdf) if !inside => {
+                let (toi, iterations) =
+                    march_ray(sdf, local_origin, local_dir.into(), max_distance);
+                (*toi?, iterations)
+            }
+            _ => (
+                self.ray_hit(local_origin, local_dir, max_distance, solid)?,
+                0,
+            ),
+        };
+
+        let point = local_origin + local_dir * distance;
+        Some(RayHitDetails {
+            distance,
+            point,
+            normal: self.gradient(point).normalize_or(Vec3::Y),
+            inside,
+            iterations,
+        })
     }
 
-    fn contains_point(&self, point: Vec3, context: SingleContext<Self::Context>) -> bool {
-        _ = (point, context);
-        todo!()
+    pub(crate) fn closest_point(&self, local_point: Vec3, solid: bool) -> Vec3 {
+        let distance = self.distance(local_point);
+        if solid && distance <= 0. {
+            return local_point;
+        }
+        local_point - self.gradient(local_point).normalize_or(Vec3::Y) * distance
     }
+
+    pub(crate) fn ray_hit(
+        &self,
+        local_origin: Vec3,
+        local_dir: Dir3,
+        max_distance: f32,
+        solid: bool,
+    ) -> Option<f32> {
+        match self {
+            Self::Asset(sdf) => {
+                if sdf.distance(local_origin) < 0. {
+                    if solid {
+                        return Some(0.);
+                    }
+                    return march_exit(sdf, local_origin, local_dir.into(), max_distance)
+                        .map(|toi| *toi);
+                }
+
+                march_ray(sdf, local_origin, local_dir.into(), max_distance)
+                    .0
+                    .map(|toi| *toi)
+            }
+            &Self::Sphere(Sphere { radius }) => {
+                local_ray_distance_with_sphere(radius, Ray3d::new(local_origin, local_dir), solid)
+                    .filter(|&distance| distance <= max_distance)
+            }
+            Self::Cluster(cluster) => cluster
+                .centers()
+                .filter_map(|center| {
+                    local_ray_distance_with_sphere(
+                        cluster.radius,
+                        Ray3d::new(local_origin - center, local_dir),
+                        solid,
+                    )
+                })
+                .filter(|&distance| distance <= max_distance)
+                .min_by(f32::total_cmp),
+            Self::Capsule(capsule) => local_ray_distance_with_capsule(
+                capsule,
+                Ray3d::new(local_origin, local_dir),
+                max_distance,
+                solid,
+            ),
+            Self::Line(line) => {
+                // Cast against the capsule around the line, in a space where it's upright
+                let (capsule, center, rotation) =
+                    capsule_between(line.start, line.end, line.radius);
+                let inv_rotation = rotation.inverse();
+                local_ray_distance_with_capsule(
+                    &capsule,
+                    Ray3d::new(
+                        inv_rotation * (local_origin - center),
+                        inv_rotation * local_dir,
+                    ),
+                    max_distance,
+                    solid,
+                )
+            }
+            Self::Polyline(polyline) => {
+                // Only segments whose bounds the ray passes through before the closest hit
+                let padding = Vec3::splat(polyline.radius);
+                let mut closest: Option<f32> = None;
+                polyline.visit(|node| {
+                    let reach = closest.unwrap_or(max_distance);
+                    let min = node.min - padding;
+                    let max = node.max + padding;
+                    if !ray_aabb_entry(local_origin, local_dir, min, max)
+                        .is_some_and(|entry| entry <= reach)
+                    {
+                        return false;
+                    }
+                    for index in node.leaf_segments().into_iter().flatten() {
+                        let hit = Self::Line(polyline.line(index)).ray_hit(
+                            local_origin,
+                            local_dir,
+                            reach,
+                            solid,
+                        );
+                        if let Some(distance) = hit.filter(|&d| d < closest.unwrap_or(f32::MAX)) {
+                            closest = Some(distance);
+                        }
+                    }
+                    true
+                });
+                closest
+            }
+            Self::Ellipsoid(ellipsoid) => local_ray_distance_with_ellipsoid(
+                ellipsoid,
+                Ray3d::new(local_origin, local_dir),
+                solid,
+            )
+            .filter(|&distance| distance <= max_distance),
+        }
+    }
+}
+
+/// Distance along a ray to where it enters an AABB, zero if it starts inside.
+fn ray_aabb_entry(origin: Vec3, dir: Dir3, min: Vec3, max: Vec3) -> Option<f32> {
+    let inv_dir = dir.recip();
+    let t1 = (min - origin) * inv_dir;
+    let t2 = (max - origin) * inv_dir;
+    let entry = t1.min(t2).max_element().max(0.);
+    let exit = t1.max(t2).min_element();
+    (entry <= exit).then_some(entry)
 }
 
 // Use the version from bevy if it ever lands.
@@ -321,6 +748,37 @@ fn local_ray_distance_with_sphere(radius: f32, ray: Ray3d, solid: bool) -> Optio
     }
 }
 
+#[inline]
+fn local_ray_distance_with_ellipsoid(
+    ellipsoid: &Ellipsoid,
+    ray: Ray3d,
+    solid: bool,
+) -> Option<f32> {
+    // Scaling space so the ellipsoid becomes a unit sphere keeps the distance along the ray the same
+    let origin = ray.origin / ellipsoid.half_size;
+    let direction = *ray.direction / ellipsoid.half_size;
+
+    let a = direction.length_squared();
+    let b = origin.dot(direction);
+    let c = origin.length_squared() - 1.;
+    if c > 0. && b > 0. {
+        return None;
+    }
+
+    let d = b * b - a * c;
+    if d < 0. {
+        return None;
+    }
+    if c <= 0. {
+        return if solid {
+            Some(0.)
+        } else {
+            Some((-b + d.sqrt()) / a)
+        };
+    }
+    Some((-b - d.sqrt()) / a)
+}
+
 // Use the version from bevy if it ever lands.
 // See: https://github.com/bevyengine/bevy/pull/15724
 #[inline]
@@ -0,0 +1,61 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::{primitives::capsule_between, SdfCollider, SdfColliderKind};
+
+/// A sphere stretched into a tube along a line, which the narrow phase treats as a capsule from
+/// `start` to `end`.
+///
+/// Offsets are relative to the body's position and don't rotate with it. For bodies with a
+/// [`LinearVelocity`] the tube is updated every step to cover the motion of the previous step,
+/// so fast projectiles can't tunnel through thin SDF geometry. Spatial queries only see the
+/// sphere at the body's position.
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq)]
+#[reflect(Default, Debug)]
+#[type_path(sdf_peck)]
+pub struct SweptSphere {
+    pub radius: f32,
+    pub start: Vec3,
+    pub end: Vec3,
+}
+
+impl SweptSphere {
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            start: Vec3::ZERO,
+            end: Vec3::ZERO,
+        }
+    }
+
+    /// The capsule covering the tube, with the offset of its center and its rotation.
+    pub(crate) fn capsule(&self) -> (Capsule3d, Vec3, Quat) {
+        capsule_between(self.start, self.end, self.radius)
+    }
+}
+
+impl From<SweptSphere> for SdfColliderKind {
+    fn from(swept: SweptSphere) -> Self {
+        Self::SweptSphere(swept)
+    }
+}
+
+pub(crate) fn update_swept_spheres(
+    time: Res<Time>,
+    mut colliders: Query<(&mut SdfCollider, &LinearVelocity)>,
+) {
+    let dt = time.delta_secs();
+    for (mut collider, velocity) in colliders.iter_mut() {
+        let SdfColliderKind::SweptSphere(swept) = collider.collider else {
+            continue;
+        };
+        let start = -velocity.0 * dt / collider.scale;
+        if swept.start != start || swept.end != Vec3::ZERO {
+            collider.collider = SdfColliderKind::SweptSphere(SweptSphere {
+                start,
+                end: Vec3::ZERO,
+                ..swept
+            });
+        }
+    }
+}
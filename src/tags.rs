@@ -0,0 +1,140 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_prototype_sdf::{dim3::Dim3, ExecutableSdf3d, ExecutableSdfs, Sdf3d};
+
+use crate::{SdfCollider, SdfColliderKind};
+
+const TAG_TOLERANCE: f32 = 0.001;
+
+/// Tags parts of an SDF asset collider, like "lava" or "ice", with the CSG subtrees they were
+/// built from.
+///
+/// At every contact, the subtree whose distance matches the full SDF is the one that formed the
+/// surface there, and [`SdfSurfaceTagContact`] is triggered when a body first touches it.
+#[derive(Component, Debug, Default, Clone)]
+pub struct SdfSurfaceTags {
+    tags: Vec<(String, Handle<Sdf3d>)>,
+    touching: Vec<(Entity, usize)>,
+}
+
+impl SdfSurfaceTags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tags the surface formed by `subtree`, which should be a subtree of the collider's SDF in
+    /// the same local space.
+    pub fn with_tag(mut self, tag: impl Into<String>, subtree: Handle<Sdf3d>) -> Self {
+        self.tags.push((tag.into(), subtree));
+        self
+    }
+
+    /// The tag of the surface at a world space point on the collider, if it's tagged.
+    pub(crate) fn tag_at(
+        &self,
+        pos: &Position,
+        rot: &Rotation,
+        collider: &SdfCollider,
+        point: Vec3,
+        sdfs: &ExecutableSdfs<Dim3>,
+    ) -> Option<&str> {
+        let SdfColliderKind::Arbitrary(handle) = collider.collider() else {
+            return None;
+        };
+        let (_, sdf) = sdfs.get(handle.id())?;
+        let subtrees = self
+            .tags
+            .iter()
+            .map(|(_, subtree)| sdfs.get(subtree.id()).map(|(_, sdf)| sdf))
+            .collect::<Vec<_>>();
+
+        let local_point = rot.0.inverse() * (point - pos.0) / collider.scale;
+        let tag = dominant_tag(&subtrees, local_point, sdf.distance(local_point))?;
+        Some(&self.tags[tag].0)
+    }
+}
+
+// The subtree whose distance matches the full SDF's `distance` best formed the surface
+fn dominant_tag(
+    subtrees: &[Option<ExecutableSdf3d>],
+    local_point: Vec3,
+    distance: f32,
+) -> Option<usize> {
+    subtrees
+        .iter()
+        .enumerate()
+        .filter_map(|(i, subtree)| {
+            Some((
+                i,
+                (subtree.as_ref()?.distance(local_point) - distance).abs(),
+            ))
+        })
+        .filter(|&(_, error)| error <= TAG_TOLERANCE)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+/// Triggered when `other` starts touching a surface of `entity` tagged with [`SdfSurfaceTags`].
+#[derive(Event, Debug, Clone)]
+pub struct SdfSurfaceTagContact {
+    pub entity: Entity,
+    pub other: Entity,
+    pub tag: String,
+}
+
+pub(crate) fn trigger_surface_tag_contacts(
+    mut commands: Commands,
+    collisions: Collisions,
+    mut query: Query<(
+        Entity,
+        &Position,
+        &Rotation,
+        &SdfCollider,
+        &mut SdfSurfaceTags,
+    )>,
+    sdfs: ExecutableSdfs<Dim3>,
+) {
+    for (entity, pos, rot, collider, mut tags) in query.iter_mut() {
+        let SdfColliderKind::Arbitrary(handle) = collider.collider() else {
+            continue;
+        };
+        let Some((_, sdf)) = sdfs.get(handle.id()) else {
+            continue;
+        };
+        let subtrees = tags
+            .tags
+            .iter()
+            .map(|(_, subtree)| sdfs.get(subtree.id()).map(|(_, sdf)| sdf))
+            .collect::<Vec<_>>();
+
+        let inv_rot = rot.0.inverse();
+        let mut touching = Vec::new();
+        for pair in collisions.collisions_with(entity) {
+            let other = if pair.collider1 == entity {
+                pair.collider2
+            } else {
+                pair.collider1
+            };
+            for point in pair.manifolds.iter().flat_map(|m| m.points.iter()) {
+                let local_point = inv_rot * (point.point - pos.0) / collider.scale;
+                let distance = sdf.distance(local_point);
+                if let Some(tag) = dominant_tag(&subtrees, local_point, distance) {
+                    if !touching.contains(&(other, tag)) {
+                        touching.push((other, tag));
+                    }
+                }
+            }
+        }
+
+        for &(other, tag) in touching.iter() {
+            if !tags.touching.contains(&(other, tag)) {
+                commands.trigger(SdfSurfaceTagContact {
+                    entity,
+                    other,
+                    tag: tags.tags[tag].0.clone(),
+                });
+            }
+        }
+        tags.touching = touching;
+    }
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_prototype_sdf::Sdf3d;
+use common::{headless_app, load_sdf, reload_sdf, step};
+use sdf_peck::{SdfCollider, SdfSpatialQuery, SdfSurfaceAnchor};
+
+#[test]
+fn anchor_follows_reloaded_surface() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    let raised = load_sdf(&mut app, "raised_terrain.sdf3d");
+    let ground = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sdf(terrain.clone()),
+            Transform::from_xyz(0., 0., 2.),
+        ))
+        .id();
+    step(&mut app, 2);
+
+    let anchor = app
+        .world_mut()
+        .run_system_once(move |query: SdfSpatialQuery| {
+            query.surface_anchor(ground, Vec3::new(0.5, 0.7, 2.))
+        })
+        .unwrap()
+        .expect("no anchor");
+    assert_eq!(anchor.collider, ground);
+    assert!(anchor.local_anchor.y.abs() < 0.01, "{anchor:?}");
+    assert!((anchor.local_anchor.x - 0.5).abs() < 0.01, "{anchor:?}");
+    assert!(anchor.local_normal.y > 0.99);
+
+    let rope = app.world_mut().spawn(anchor).id();
+    let raised_sdf = app
+        .world()
+        .resource::<Assets<Sdf3d>>()
+        .get(raised.id())
+        .unwrap()
+        .clone();
+    reload_sdf(&mut app, &terrain, raised_sdf);
+
+    let anchor = app.world().get::<SdfSurfaceAnchor>(rope).unwrap();
+    assert!((anchor.local_anchor.y - 1.).abs() < 0.01, "{anchor:?}");
+    assert!((anchor.local_anchor.x - 0.5).abs() < 0.01, "{anchor:?}");
+}
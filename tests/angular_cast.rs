@@ -0,0 +1,50 @@
+mod common;
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, step};
+use sdf_peck::{AngularCastHit, SdfCollider, SdfSpatialQuery};
+
+fn swing(app: &mut App, max_angle: f32) -> Option<AngularCastHit> {
+    app.world_mut()
+        .run_system_once(move |query: SdfSpatialQuery| {
+            query.angular_cast(
+                &Sphere::new(0.1),
+                Vec3::new(2., 1., 0.),
+                Vec3::ZERO,
+                Dir3::Y,
+                max_angle,
+                &SpatialQueryFilter::DEFAULT,
+            )
+        })
+        .unwrap()
+}
+
+#[test]
+fn swings_stop_at_the_first_collider() {
+    let mut app = headless_app();
+    // A post a quarter turn counterclockwise from the start of the swing
+    let post = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sphere(0.5),
+            Transform::from_xyz(0., 1., -2.),
+        ))
+        .id();
+    step(&mut app, 2);
+
+    // The centers are 0.6 apart where the chord of the remaining turn is that long
+    let expected = FRAC_PI_2 - 2. * (0.15f32).asin();
+    let hit = swing(&mut app, PI).unwrap();
+    assert_eq!(hit.entity, post);
+    assert!((hit.angle - expected).abs() < 0.005, "{hit:?}");
+    assert!((hit.center.distance(Vec3::new(0., 1., -2.)) - 0.6).abs() < 0.01);
+    assert!((hit.point.distance(Vec3::new(0., 1., -2.)) - 0.5).abs() < 0.01);
+
+    assert!(swing(&mut app, 1.).is_none());
+    // Swinging the other way misses the post until it comes around again
+    assert!(swing(&mut app, -PI).is_none());
+}
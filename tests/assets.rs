@@ -0,0 +1,173 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, step};
+use sdf_peck::{SdfCollider, SdfSpatialQuery};
+
+const ASSETS: [&str; 4] = [
+    "sphere_stage.sdf3d",
+    "csg_subtract.sdf3d",
+    "thin_shell.sdf3d",
+    "terrain.sdf3d",
+];
+
+fn run_matrix(path: &str) {
+    let mut app = headless_app();
+    let sdf = load_sdf(&mut app, path);
+
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(sdf),
+        Transform::default(),
+    ));
+    let bodies = [
+        SdfCollider::sphere(0.3),
+        SdfCollider::capsule(0.2, 0.8),
+        SdfCollider::sphere(0.05),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, collider)| {
+        app.world_mut()
+            .spawn((
+                RigidBody::Dynamic,
+                collider,
+                Transform::from_xyz(i as f32 * 0.7 - 0.7, 1.5, 0.2),
+            ))
+            .id()
+    })
+    .collect::<Vec<_>>();
+
+    step(&mut app, 120);
+
+    for body in bodies {
+        let pos = app.world().get::<Position>(body).unwrap();
+        assert!(pos.is_finite(), "{path}: body {body} ended at {pos:?}");
+    }
+
+    let results = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            let directions = [
+                Dir3::X,
+                Dir3::NEG_X,
+                Dir3::Y,
+                Dir3::NEG_Y,
+                Dir3::Z,
+                Dir3::NEG_Z,
+            ];
+            let rays = directions.map(|dir| (Vec3::ZERO - *dir * 20., dir));
+            let ray_hits = query.cast_rays(&rays, 40., true, &SpatialQueryFilter::DEFAULT);
+            let sweeps = directions.map(|dir| {
+                query.shape_hits(
+                    &Sphere::new(0.25),
+                    Vec3::ZERO - *dir * 20.,
+                    dir,
+                    4,
+                    &ShapeCastConfig::from_max_distance(40.),
+                    &SpatialQueryFilter::DEFAULT,
+                )
+            });
+            let thickness = query.solid_thickness(
+                Vec3::new(-20., 0.1, 0.),
+                Vec3::new(20., 0.1, 0.),
+                &SpatialQueryFilter::DEFAULT,
+            );
+            (ray_hits, sweeps, thickness)
+        })
+        .unwrap();
+
+    let (ray_hits, sweeps, thickness) = results;
+    for hit in ray_hits.iter().flatten() {
+        assert!(
+            hit.distance.is_finite() && hit.distance >= 0.,
+            "{path}: {hit:?}"
+        );
+        assert!(hit.normal.is_normalized(), "{path}: {hit:?}");
+    }
+    for hit in sweeps.iter().flatten() {
+        assert!(
+            hit.distance.is_finite() && hit.distance >= 0.,
+            "{path}: {hit:?}"
+        );
+    }
+    assert!(
+        (0. ..=40.).contains(&thickness),
+        "{path}: thickness {thickness}"
+    );
+}
+
+#[test]
+fn sphere_stage() {
+    run_matrix(ASSETS[0]);
+}
+
+#[test]
+fn csg_subtract() {
+    run_matrix(ASSETS[1]);
+}
+
+#[test]
+fn thin_shell() {
+    run_matrix(ASSETS[2]);
+}
+
+#[test]
+fn terrain() {
+    run_matrix(ASSETS[3]);
+}
+
+#[test]
+fn batched_rays_match_single_casts() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, ASSETS[3]);
+    let subtract = load_sdf(&mut app, ASSETS[1]);
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::default(),
+    ));
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(subtract),
+        Transform::from_xyz(0.5, 1., 8.),
+    ));
+    step(&mut app, 2);
+
+    // A view cone of neighbouring rays, grazing the terrain and crossing the cut in the sphere
+    let (batched, single) = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            let origin = Vec3::new(0., 2., 0.);
+            let rays = (0..200)
+                .map(|i| {
+                    let yaw = (i % 20) as f32 * 0.02 - 0.2;
+                    let pitch = -0.05 - (i / 20) as f32 * 0.03;
+                    (origin, Dir3::new(Vec3::new(yaw, pitch, 1.)).unwrap())
+                })
+                .collect::<Vec<_>>();
+            let filter = SpatialQueryFilter::DEFAULT;
+            let batched = query.cast_rays(&rays, 60., true, &filter);
+            let single = rays
+                .iter()
+                .map(|&(origin, dir)| query.ray_hits(origin, dir, 60., 1, true, &filter).pop())
+                .collect::<Vec<_>>();
+            (batched, single)
+        })
+        .unwrap();
+
+    for (i, (batched, single)) in batched.iter().zip(&single).enumerate() {
+        match (batched, single) {
+            (Some(batched), Some(single)) => {
+                assert_eq!(batched.entity, single.entity, "ray {i}");
+                assert!(
+                    (batched.distance - single.distance).abs() < 0.01,
+                    "ray {i}: {batched:?} {single:?}"
+                );
+            }
+            (None, None) => {}
+            _ => panic!("ray {i}: {batched:?} {single:?}"),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, step};
+use sdf_peck::{BudgetedCastHit, SdfCollider, SdfSpatialQuery};
+
+fn grazing_cast(app: &mut App, max_iterations: u32) -> Option<BudgetedCastHit> {
+    app.world_mut()
+        .run_system_once(move |query: SdfSpatialQuery| {
+            // Descends 1m over 20m, so every march step only gets slightly closer to the ground
+            let direction = Dir3::new(Vec3::new(20., -1., 0.)).unwrap();
+            query.cast_budgeted(
+                Vec3::Y * 0.5,
+                direction,
+                0.,
+                200.,
+                max_iterations,
+                &SpatialQueryFilter::DEFAULT,
+            )
+        })
+        .unwrap()
+}
+
+#[test]
+fn exhausted_budget_reports_closest_approach() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    let floor = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sdf(terrain),
+            Transform::default(),
+        ))
+        .id();
+    step(&mut app, 2);
+
+    let converged = grazing_cast(&mut app, u32::MAX).expect("no hit");
+    assert_eq!(converged.entity, floor);
+    assert!(!converged.exhausted);
+    // Where the ray meets the curved ground of the terrain sphere
+    assert!((converged.distance - 11.284).abs() < 0.05, "{converged:?}");
+
+    let partial = grazing_cast(&mut app, 8).expect("no partial hit");
+    assert_eq!(partial.entity, floor);
+    assert!(partial.exhausted);
+    assert!(partial.distance > 0. && partial.distance < converged.distance);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use std::f32::consts::FRAC_PI_2;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, step};
+use sdf_peck::SdfCollider;
+
+#[test]
+fn swept_sphere_does_not_tunnel_through_thin_plate() {
+    let mut app = headless_app();
+    app.insert_resource(Gravity(Vec3::ZERO));
+
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::ellipsoid(Vec3::new(5., 0.02, 5.)),
+        Transform::default(),
+    ));
+    let bullet = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::sphere(0.1),
+            SweptCcd::default(),
+            LinearVelocity(Vec3::NEG_Y * 500.),
+            Transform::from_xyz(0., 5., 0.),
+        ))
+        .id();
+
+    step(&mut app, 10);
+
+    let pos = app.world().get::<Position>(bullet).unwrap();
+    assert!(pos.y > 0., "bullet tunneled to {pos:?}");
+}
+
+#[test]
+fn swept_capsule_stops_at_thin_wall() {
+    // Upright capsules stop with their side against the wall, capsules lying along their motion
+    // with their end against it
+    for (rotation, stop) in [
+        (Quat::IDENTITY, 0.12),
+        (Quat::from_rotation_z(FRAC_PI_2), 1.12),
+    ] {
+        let mut app = headless_app();
+        app.insert_resource(Gravity(Vec3::ZERO));
+
+        app.world_mut().spawn((
+            RigidBody::Static,
+            SdfCollider::ellipsoid(Vec3::new(0.02, 5., 5.)),
+            Transform::default(),
+        ));
+        let capsule = app
+            .world_mut()
+            .spawn((
+                RigidBody::Dynamic,
+                SdfCollider::capsule(0.1, 2.),
+                SweptCcd::default(),
+                LinearVelocity(Vec3::NEG_X * 500.),
+                Transform::from_xyz(5., 0., 0.).with_rotation(rotation),
+            ))
+            .id();
+
+        step(&mut app, 10);
+
+        let pos = app.world().get::<Position>(capsule).unwrap();
+        assert!(
+            (pos.x - stop).abs() < 0.1,
+            "capsule should stop at x = {stop}: {pos:?}"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use std::f32::consts::PI;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, load_sdf, spawn_ball, spawn_floor, step};
+use sdf_peck::SdfCollider;
+
+#[test]
+fn offset_child_colliders_rest_on_the_floor() {
+    let mut app = headless_app();
+    spawn_floor(&mut app, 5.);
+    // A vehicle whose only colliders are four wheels below the corners of the body
+    let wheel_offsets = [
+        Vec3::new(1., -0.5, 1.),
+        Vec3::new(-1., -0.5, 1.),
+        Vec3::new(1., -0.5, -1.),
+        Vec3::new(-1., -0.5, -1.),
+    ];
+    let vehicle = app
+        .world_mut()
+        .spawn((RigidBody::Dynamic, Transform::from_xyz(0., 2., 0.)))
+        .id();
+    let wheels = wheel_offsets.map(|offset| {
+        app.world_mut()
+            .spawn((
+                SdfCollider::sphere(0.3),
+                Transform::from_translation(offset),
+                ChildOf(vehicle),
+            ))
+            .id()
+    });
+
+    step(&mut app, 240);
+
+    // The wheels rest on the floor, with the body half a unit above them
+    let pos = app.world().get::<Position>(vehicle).unwrap();
+    assert!((pos.y - 1.).abs() < 0.05, "vehicle didn't settle: {pos:?}");
+    let rot = app.world().get::<Rotation>(vehicle).unwrap();
+    assert!(rot.angle_between(Quat::IDENTITY) < 0.05, "{rot:?}");
+    for (wheel, offset) in wheels.into_iter().zip(wheel_offsets) {
+        let wheel_pos = app.world().get::<Position>(wheel).unwrap();
+        assert!(
+            wheel_pos.abs_diff_eq(pos.0 + offset, 0.05),
+            "wheel isn't at its offset: {wheel_pos:?}"
+        );
+    }
+
+    // Mass adds up over the wheels, centered between them
+    let wheel_mass = 4. / 3. * PI * 0.3_f32.powi(3);
+    let mass = app.world().get::<ComputedMass>(vehicle).unwrap();
+    assert!((mass.value() - wheel_mass * 4.).abs() < 1e-3, "{mass:?}");
+    let com = app.world().get::<ComputedCenterOfMass>(vehicle).unwrap();
+    assert!(com.abs_diff_eq(Vec3::new(0., -0.5, 0.), 1e-3), "{com:?}");
+}
+
+#[test]
+fn child_colliders_inherit_the_scale_of_their_body() {
+    let mut app = headless_app();
+    spawn_floor(&mut app, 5.);
+    // Scaled up with the body to a radius of 0.5, a whole unit below it
+    let body = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            Transform::from_xyz(0., 3., 0.).with_scale(Vec3::splat(2.)),
+        ))
+        .id();
+    let child = app
+        .world_mut()
+        .spawn((
+            SdfCollider::sphere(0.25),
+            Transform::from_xyz(0., -0.5, 0.),
+            ChildOf(body),
+        ))
+        .id();
+
+    step(&mut app, 240);
+
+    let collider = app.world().get::<SdfCollider>(child).unwrap();
+    assert_eq!(collider.uniform_scale(), 2.);
+    let pos = app.world().get::<Position>(body).unwrap();
+    assert!((pos.y - 1.7).abs() < 0.05, "body didn't settle: {pos:?}");
+}
+
+#[test]
+fn fast_kinematic_body_carries_bodies_on_its_child_collider() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+
+    // The surface of the platform is a unit below its body
+    let platform = app
+        .world_mut()
+        .spawn((
+            RigidBody::Kinematic,
+            LinearVelocity(Vec3::Y * 6.),
+            Transform::default(),
+        ))
+        .id();
+    app.world_mut().spawn((
+        SdfCollider::sdf(terrain),
+        Transform::from_xyz(0., -1., 0.),
+        ChildOf(platform),
+    ));
+    let ball = spawn_ball(&mut app, Vec3::new(0., -0.69, 0.)).id();
+
+    for _ in 0..60 {
+        step(&mut app, 1);
+        let platform = app.world().get::<Position>(platform).unwrap().y;
+        let ball = app.world().get::<Position>(ball).unwrap().y;
+        assert!(
+            ball - platform > -0.75,
+            "ball sank into the platform: {ball} vs {platform}"
+        );
+    }
+}
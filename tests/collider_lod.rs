@@ -0,0 +1,42 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, spawn_ball, step};
+use sdf_peck::{SdfCollider, SdfColliderLod, SdfSpatialQuery};
+
+fn floor_hit_distance(app: &mut App) -> f32 {
+    app.world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            let rays = [(Vec3::Y * 1000., Dir3::NEG_Y)];
+            query
+                .cast_rays(&rays, 2000., true, &SpatialQueryFilter::DEFAULT)
+                .into_iter()
+                .flatten()
+                .next()
+                .map_or(f32::INFINITY, |hit| hit.distance)
+        })
+        .unwrap()
+}
+
+#[test]
+fn distant_colliders_act_as_bounding_spheres() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        SdfColliderLod::new(2.),
+        Transform::default(),
+    ));
+    step(&mut app, 2);
+
+    // Nothing is near, so the ray hits the bounding sphere of the floor
+    assert!(floor_hit_distance(&mut app) < 999.);
+
+    spawn_ball(&mut app, Vec3::new(1., 0.3, 1.));
+    step(&mut app, 2);
+
+    let distance = floor_hit_distance(&mut app);
+    assert!((distance - 1000.).abs() < 0.01, "hit at {distance}");
+}
@@ -0,0 +1,25 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, spawn_ball, spawn_floor, step, BALL_REST_HEIGHT};
+use sdf_peck::SdfCollider;
+
+#[test]
+fn growing_sphere_rises_off_the_floor() {
+    let mut app = headless_app();
+    spawn_floor(&mut app, 5.);
+    let balloon = spawn_ball(&mut app, Vec3::Y * BALL_REST_HEIGHT).id();
+    step(&mut app, 32);
+
+    app.world_mut()
+        .get_mut::<SdfCollider>(balloon)
+        .unwrap()
+        .set_sphere_radius(0.6);
+    step(&mut app, 64);
+
+    let pos = app.world().get::<Position>(balloon).unwrap();
+    assert!((pos.y - 0.8).abs() < 0.05, "balloon didn't grow: {pos:?}");
+    let aabb = app.world().get::<ColliderAabb>(balloon).unwrap();
+    assert!((aabb.max.y - aabb.min.y - 1.2).abs() < 0.05, "{aabb:?}");
+}
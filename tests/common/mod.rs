@@ -0,0 +1,122 @@
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use avian3d::prelude::*;
+use bevy::{asset::AssetPlugin, prelude::*, time::TimeUpdateStrategy};
+use bevy_prototype_sdf::{Sdf3d, SdfPlugin, SdfProcessed};
+use sdf_peck::{SdfCollider, SdfCollisionPlugin};
+
+pub const TIMESTEP: f64 = 1. / 64.;
+
+#[derive(Resource, Default)]
+struct ProcessedSdfs(Vec<AssetId<Sdf3d>>);
+
+pub fn headless_app() -> App {
+    headless_app_with(SdfCollisionPlugin::<()>::default())
+}
+
+pub fn headless_app_with(plugin: SdfCollisionPlugin) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: "tests/assets".into(),
+            ..default()
+        },
+        TransformPlugin,
+        SdfPlugin,
+        PhysicsPlugins::default(),
+        plugin,
+    ))
+    .insert_resource(Time::<Fixed>::from_seconds(TIMESTEP))
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        TIMESTEP,
+    )))
+    .init_resource::<ProcessedSdfs>()
+    .add_observer(
+        |trigger: On<SdfProcessed>, mut processed: ResMut<ProcessedSdfs>| {
+            processed.0.push(AssetId::from(trigger.event().0));
+        },
+    );
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// Spawns a static floor at the origin, an ellipsoid 0.4 thick with its top at y = 0.2 that
+/// reaches `half_width` out in X and Z.
+pub fn spawn_floor(app: &mut App, half_width: f32) -> EntityWorldMut<'_> {
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::ellipsoid(Vec3::new(half_width, 0.2, half_width)),
+        Transform::default(),
+    ))
+}
+
+/// Spawns a dynamic ball with a radius of 0.3, which rests at [`BALL_REST_HEIGHT`] on a floor.
+pub fn spawn_ball(app: &mut App, position: Vec3) -> EntityWorldMut<'_> {
+    app.world_mut().spawn((
+        RigidBody::Dynamic,
+        SdfCollider::sphere(0.3),
+        Transform::from_translation(position),
+    ))
+}
+
+/// Height of a ball from [`spawn_ball`] resting on the middle of a floor from [`spawn_floor`].
+pub const BALL_REST_HEIGHT: f32 = 0.5;
+
+/// Asserts that a ball from [`spawn_ball`] came to rest on a floor from [`spawn_floor`].
+pub fn assert_resting(app: &App, ball: Entity, what: &str) {
+    let pos = app.world().get::<Position>(ball).unwrap();
+    assert!(
+        (pos.y - BALL_REST_HEIGHT).abs() < 0.05,
+        "{what} isn't resting on the floor: {pos:?}"
+    );
+}
+
+/// Loads an SDF from `tests/assets` and steps the app until it has been processed.
+pub fn load_sdf(app: &mut App, path: &str) -> Handle<Sdf3d> {
+    let handle = app.world().resource::<AssetServer>().load(path.to_owned());
+    for _ in 0..1000 {
+        app.update();
+        if app
+            .world()
+            .resource::<ProcessedSdfs>()
+            .0
+            .contains(&handle.id())
+        {
+            return handle;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    panic!("Timed out loading {path}");
+}
+
+pub fn step(app: &mut App, steps: usize) {
+    for _ in 0..steps {
+        app.update();
+    }
+}
+
+/// Replaces the contents of a loaded SDF asset, like a hot reload, and steps the app until it has
+/// been processed again.
+pub fn reload_sdf(app: &mut App, handle: &Handle<Sdf3d>, sdf: Sdf3d) {
+    app.world_mut().resource_mut::<ProcessedSdfs>().0.clear();
+    *app.world_mut()
+        .resource_mut::<Assets<Sdf3d>>()
+        .get_mut(handle.id())
+        .unwrap() = sdf;
+    for _ in 0..1000 {
+        app.update();
+        if app
+            .world()
+            .resource::<ProcessedSdfs>()
+            .0
+            .contains(&handle.id())
+        {
+            return;
+        }
+    }
+    panic!("Timed out reprocessing {:?}", handle.id());
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, load_sdf, spawn_ball, spawn_floor, step};
+use sdf_peck::{SdfCollider, SdfContactCompliance};
+
+#[test]
+fn bodies_sink_into_compliant_surfaces() {
+    let mut app = headless_app();
+    spawn_floor(&mut app, 3.).insert(SdfContactCompliance::new(0.2, 200.));
+    let body = spawn_ball(&mut app, Vec3::new(0., 1., 0.)).id();
+
+    step(&mut app, 256);
+
+    // The spring holds up gravity after sinking 9.81 / 200 into the surface
+    let pos = app.world().get::<Position>(body).unwrap();
+    assert!(pos.y > 0.4 && pos.y < 0.48, "{pos:?}");
+}
+
+#[test]
+fn bodies_sink_into_compliant_terrain() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        SdfContactCompliance::new(0.2, 200.),
+        Transform::default(),
+    ));
+    let body = spawn_ball(&mut app, Vec3::new(0., 1., 0.)).id();
+
+    step(&mut app, 256);
+
+    // Like on the floor, but the surface of the terrain is at y = 0
+    let pos = app.world().get::<Position>(body).unwrap();
+    assert!(pos.y > 0.2 && pos.y < 0.28, "{pos:?}");
+}
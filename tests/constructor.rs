@@ -0,0 +1,46 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{headless_app, step};
+use sdf_peck::{SdfCollider, SdfColliderConstructor, SdfColliderConstructorHierarchy};
+
+#[test]
+fn constructors_resolve_into_colliders() {
+    let mut app = headless_app();
+
+    let root = app
+        .world_mut()
+        .spawn((
+            SdfColliderConstructorHierarchy::new(Some(SdfColliderConstructor::Sphere {
+                radius: 0.5,
+            }))
+            .with_constructor_for_name("Visual", None),
+            Transform::default(),
+        ))
+        .id();
+    let direct = app
+        .world_mut()
+        .spawn(SdfColliderConstructor::Capsule {
+            radius: 0.2,
+            length: 1.,
+        })
+        .id();
+    step(&mut app, 1);
+
+    // Spawned after the hierarchy, like nodes of a scene that was still loading
+    let body = app
+        .world_mut()
+        .spawn((Name::new("Body"), ChildOf(root)))
+        .id();
+    let visual = app
+        .world_mut()
+        .spawn((Name::new("Visual"), ChildOf(root)))
+        .id();
+    step(&mut app, 2);
+
+    let world = app.world();
+    assert!(world.get::<SdfColliderConstructor>(direct).is_none());
+    assert!(world.get::<SdfCollider>(direct).is_some());
+    assert!(world.get::<SdfCollider>(body).is_some());
+    assert!(world.get::<SdfCollider>(visual).is_none());
+}
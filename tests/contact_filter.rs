@@ -0,0 +1,52 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{assert_resting, headless_app_with, load_sdf, spawn_ball, spawn_floor, step};
+use sdf_peck::{Contact, SdfCollider, SdfCollisionPlugin};
+
+/// Drops the contacts far from the origin, and makes the others push straight up or down.
+fn filtered_app() -> App {
+    let plugin = SdfCollisionPlugin::<()>::default()
+        .with_contact_filter(|_, _, contact: &mut Contact| contact.point.x.abs() < 2.)
+        .with_contact_filter(|_, _, contact: &mut Contact| {
+            contact.normal = contact.normal.y.signum() * Vec3::Y;
+            true
+        });
+    headless_app_with(plugin)
+}
+
+#[test]
+fn contact_filters_drop_and_modify_contacts() {
+    let mut app = filtered_app();
+    spawn_floor(&mut app, 5.);
+    let kept = spawn_ball(&mut app, Vec3::new(1., 2., 0.)).id();
+    let dropped = spawn_ball(&mut app, Vec3::new(3., 2., 0.)).id();
+
+    step(&mut app, 128);
+
+    assert_resting(&app, kept, "ball with kept contacts");
+    let dropped = app.world().get::<Position>(dropped).unwrap();
+    assert!(dropped.y < -0.5, "{dropped:?}");
+}
+
+#[test]
+fn contact_filters_apply_to_terrain_contacts() {
+    let mut app = filtered_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::default(),
+    ));
+    let kept = spawn_ball(&mut app, Vec3::new(1., 2., 0.)).id();
+    let dropped = spawn_ball(&mut app, Vec3::new(3., 2., 0.)).id();
+
+    step(&mut app, 128);
+
+    // The surface of the terrain is at y = 0, so the ball rests a radius above it
+    let kept = app.world().get::<Position>(kept).unwrap();
+    assert!((kept.y - 0.3).abs() < 0.05, "{kept:?}");
+    let dropped = app.world().get::<Position>(dropped).unwrap();
+    assert!(dropped.y < -0.5, "{dropped:?}");
+}
@@ -0,0 +1,30 @@
+use bevy::math::{primitives::Sphere, Isometry3d, Vec3};
+use sdf_peck::core::{sphere_contacts, Ellipsoid, ScaledIsometry3d};
+
+#[test]
+fn contacts_without_an_app() {
+    let sphere_iso = Isometry3d::from_translation(Vec3::Y * 1.4);
+    let ground = Ellipsoid::new(Vec3::new(10., 1., 10.));
+    let contacts = sphere_contacts(
+        Sphere::new(0.5),
+        sphere_iso,
+        &ground,
+        ScaledIsometry3d::new(Isometry3d::IDENTITY, 1.),
+        0.,
+    );
+    assert_eq!(contacts.len(), 1);
+    assert!((contacts[0].penetration - 0.1).abs() < 1e-3);
+    assert!(contacts[0].normal.abs_diff_eq(Vec3::NEG_Y, 1e-3));
+
+    // The same ground built at half size and scaled up
+    let half_ground = Ellipsoid::new(Vec3::new(5., 0.5, 5.));
+    let scaled = sphere_contacts(
+        Sphere::new(0.5),
+        sphere_iso,
+        &half_ground,
+        ScaledIsometry3d::new(Isometry3d::IDENTITY, 2.),
+        0.,
+    );
+    assert_eq!(scaled.len(), 1);
+    assert!((scaled[0].penetration - contacts[0].penetration).abs() < 1e-4);
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, math::bounding::Aabb3d, prelude::*};
+use common::{headless_app, step};
+use sdf_peck::{SdfBackend, SdfCollider, SdfSpatialQuery};
+
+/// Ground with gentle bumps, using the estimated gradient.
+struct BumpyGround;
+
+impl SdfBackend for BumpyGround {
+    fn distance(&self, local_point: Vec3) -> f32 {
+        local_point.y - 0.1 * local_point.x.sin() * local_point.z.sin()
+    }
+
+    fn aabb(&self) -> Aabb3d {
+        Aabb3d::new(Vec3::new(0., -10., 0.), Vec3::new(50., 10.1, 50.))
+    }
+}
+
+#[test]
+fn custom_sdfs_collide_and_are_queried() {
+    let mut app = headless_app();
+    let ground = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::custom(BumpyGround),
+            Transform::default(),
+        ))
+        .id();
+    let ball = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::sphere(0.5),
+            Transform::from_xyz(0., 2., 0.),
+        ))
+        .id();
+    let capsule = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::capsule(0.25, 1.),
+            Transform::from_xyz(5., 2., 0.),
+        ))
+        .id();
+    step(&mut app, 128);
+
+    let aabb = app.world().get::<ColliderAabb>(ground).unwrap();
+    assert!(aabb.max.y < 0.5 && aabb.min.y < -20., "{aabb:?}");
+    let ball = app.world().get::<Position>(ball).unwrap();
+    assert!((ball.y - 0.5).abs() < 0.05, "{ball:?}");
+    // Standing or fallen over, the capsule rests on the ground
+    let capsule = app.world().get::<Position>(capsule).unwrap();
+    assert!(capsule.y > 0.15 && capsule.y < 0.85, "{capsule:?}");
+
+    let hit = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            query.cast_ray_detailed(
+                Vec3::new(2., 5., 0.),
+                Dir3::NEG_Y,
+                10.,
+                true,
+                &SpatialQueryFilter::DEFAULT,
+            )
+        })
+        .unwrap();
+    let (entity, hit) = hit.unwrap();
+    assert_eq!(entity, ground);
+    assert!((hit.distance - 5.).abs() < 0.01, "{hit:?}");
+    assert!(hit.normal.abs_diff_eq(Vec3::Y, 0.05), "{hit:?}");
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use bevy::prelude::*;
+use common::{headless_app, spawn_ball, spawn_floor, step};
+use sdf_peck::SdfImpactEvent;
+
+#[derive(Resource, Default)]
+struct Impacts(Vec<SdfImpactEvent>);
+
+#[test]
+fn falling_body_reports_impact_speed() {
+    let mut app = headless_app();
+    app.init_resource::<Impacts>().add_observer(
+        |trigger: On<SdfImpactEvent>, mut impacts: ResMut<Impacts>| {
+            impacts.0.push(trigger.event().clone());
+        },
+    );
+    let ground = spawn_floor(&mut app, 3.).id();
+    let falling = spawn_ball(&mut app, Vec3::new(0., 2., 0.)).id();
+
+    step(&mut app, 120);
+
+    // Falling 1.5m before touching the ground
+    let impacts = &app.world().resource::<Impacts>().0;
+    let impact = impacts.first().expect("no impact");
+    assert!(
+        [impact.entity1, impact.entity2] == [ground, falling]
+            || [impact.entity1, impact.entity2] == [falling, ground]
+    );
+    assert!(
+        (impact.speed - (2. * 9.81 * 1.5f32).sqrt()).abs() < 0.5,
+        "{impact:?}"
+    );
+    assert!(impact.normal.y.abs() > 0.99);
+    assert!((impact.point.y - 0.2).abs() < 0.1);
+    assert_eq!(impact.tag, None);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, step};
+use sdf_peck::{
+    SdfCollider, SdfInteriorEnter, SdfInteriorExit, SdfInteriorTracker, SdfInteriorVolume,
+};
+
+#[derive(Resource, Default)]
+struct Crossings(Vec<(bool, Entity, Entity)>);
+
+#[test]
+fn tracked_center_enters_and_exits_volume() {
+    let mut app = headless_app();
+    app.init_resource::<Crossings>()
+        .add_observer(
+            |trigger: On<SdfInteriorEnter>, mut crossings: ResMut<Crossings>| {
+                let event = trigger.event();
+                crossings.0.push((true, event.volume, event.entity));
+            },
+        )
+        .add_observer(
+            |trigger: On<SdfInteriorExit>, mut crossings: ResMut<Crossings>| {
+                let event = trigger.event();
+                crossings.0.push((false, event.volume, event.entity));
+            },
+        );
+    let water = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            Sensor,
+            SdfCollider::sphere(2.),
+            SdfInteriorVolume::default(),
+            Transform::default(),
+        ))
+        .id();
+    let diver = app
+        .world_mut()
+        .spawn((
+            RigidBody::Kinematic,
+            SdfCollider::sphere(0.5),
+            SdfInteriorTracker,
+            LinearVelocity(Vec3::X * 4.),
+            Transform::from_xyz(-4., 0., 0.),
+        ))
+        .id();
+
+    // The diver's collider touches the water half a meter before its center enters it
+    step(&mut app, 28);
+    assert!(app.world().resource::<Crossings>().0.is_empty());
+
+    step(&mut app, 8);
+    assert_eq!(
+        app.world().resource::<Crossings>().0,
+        [(true, water, diver)]
+    );
+    assert!(app
+        .world()
+        .get::<SdfInteriorVolume>(water)
+        .unwrap()
+        .contains(diver));
+
+    step(&mut app, 64);
+    assert_eq!(
+        app.world().resource::<Crossings>().0,
+        [(true, water, diver), (false, water, diver)]
+    );
+}
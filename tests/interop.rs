@@ -0,0 +1,38 @@
+#![cfg(feature = "parry")]
+
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, load_sdf, step};
+use sdf_peck::{SdfCollider, SdfInterop, SdfInteropProxy};
+
+#[test]
+fn parry_colliders_land_on_sdf_colliders() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::from_xyz(0., -1., 0.),
+    ));
+    let crate_body = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            Collider::cuboid(1., 1., 1.),
+            SdfInterop::default(),
+            Transform::from_xyz(0., 1., 0.),
+        ))
+        .id();
+    step(&mut app, 192);
+
+    let mut proxies = app
+        .world_mut()
+        .query_filtered::<&ChildOf, With<SdfInteropProxy>>();
+    assert!(proxies
+        .iter(app.world())
+        .any(|child_of| child_of.parent() == crate_body));
+    let pos = app.world().get::<Position>(crate_body).unwrap();
+    assert!((pos.y + 0.5).abs() < 0.06, "{pos:?}");
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, load_sdf, step};
+use sdf_peck::{SdfCollider, SdfMassProperties};
+
+const ROCK: SdfMassProperties = SdfMassProperties {
+    volume: 2.,
+    unit_principal_angular_inertia: Vec3::new(0.4, 0.5, 0.6),
+    center_of_mass: Vec3::new(0., 0.5, 0.),
+};
+
+#[test]
+fn precomputed_mass_properties_replace_the_estimate() {
+    let mut app = headless_app();
+    let rock = load_sdf(&mut app, "sphere_stage.sdf3d");
+    let body = |app: &mut App, offset: f32| {
+        app.world_mut()
+            .spawn((
+                RigidBody::Dynamic,
+                GravityScale(0.),
+                SdfCollider::sdf(rock.clone()),
+                Transform::from_xyz(offset, 100., 0.),
+            ))
+            .id()
+    };
+    let estimated = body(&mut app, 0.);
+    let precomputed = body(&mut app, 20.);
+    app.world_mut()
+        .entity_mut(precomputed)
+        .insert((ROCK, ColliderDensity(3.)));
+    let overridden = body(&mut app, 40.);
+    app.world_mut()
+        .entity_mut(overridden)
+        .insert((ROCK, Mass(10.)));
+    step(&mut app, 2);
+
+    let mass = |app: &App, entity| app.world().get::<ComputedMass>(entity).unwrap().value();
+    assert!((mass(&app, precomputed) - 6.).abs() < 1e-4);
+    assert!((mass(&app, overridden) - 10.).abs() < 1e-4);
+    let center = app
+        .world()
+        .get::<ComputedCenterOfMass>(precomputed)
+        .unwrap();
+    assert!(
+        center.abs_diff_eq(Vec3::new(0., 0.5, 0.), 1e-4),
+        "{center:?}"
+    );
+
+    // Properties added later are picked up like any other change to the collider
+    app.world_mut().entity_mut(estimated).insert(ROCK);
+    step(&mut app, 2);
+    assert!((mass(&app, estimated) - 2.).abs() < 1e-4);
+}
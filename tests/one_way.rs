@@ -0,0 +1,43 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{assert_resting, headless_app, load_sdf, spawn_ball, spawn_floor, step};
+use sdf_peck::{OneWaySurface, SdfCollider};
+
+#[test]
+fn bodies_pass_one_way_platforms_from_below_only() {
+    let mut app = headless_app();
+    spawn_floor(&mut app, 3.).insert(OneWaySurface::new(Dir3::Y));
+    let falling = spawn_ball(&mut app, Vec3::new(0.5, 2., 0.)).id();
+    let jumping = spawn_ball(&mut app, Vec3::new(-0.5, -1., 0.))
+        .insert(LinearVelocity(Vec3::Y * 8.))
+        .id();
+
+    step(&mut app, 240);
+
+    assert_resting(&app, falling, "falling body");
+    assert_resting(&app, jumping, "jumping body that passed through");
+}
+
+#[test]
+fn bodies_pass_one_way_sdf_slabs_from_below_only() {
+    let mut app = headless_app();
+    // Just as thick as the floor, with its top at y = 0.2 too
+    let slab = load_sdf(&mut app, "slab.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(slab),
+        OneWaySurface::new(Dir3::Y),
+        Transform::default(),
+    ));
+    let falling = spawn_ball(&mut app, Vec3::new(0.5, 2., 0.)).id();
+    let jumping = spawn_ball(&mut app, Vec3::new(-0.5, -1., 0.))
+        .insert(LinearVelocity(Vec3::Y * 8.))
+        .id();
+
+    step(&mut app, 240);
+
+    assert_resting(&app, falling, "falling body");
+    assert_resting(&app, jumping, "jumping body that passed through");
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, spawn_ball, step};
+use sdf_peck::{SdfCollider, SdfSpatialQuery};
+
+#[test]
+fn polylines_collide_and_are_hit_by_rays() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::default(),
+    ));
+    // A long zigzag of thin rails that things can rest on, made of many short segments
+    let rail_points =
+        (0..=64).map(|i| Vec3::new(i as f32 * 0.25 - 8., 0., if i % 2 == 0 { -1. } else { 1. }));
+    let rail = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::polyline(rail_points, 0.05),
+            Transform::from_xyz(0., 2., 5.),
+        ))
+        .id();
+    let ball = spawn_ball(&mut app, Vec3::new(0., 4., 5.)).id();
+    let pipe = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::polyline(
+                [
+                    Vec3::new(-1., 0., 0.),
+                    Vec3::new(0., 0., 1.),
+                    Vec3::new(1., 0., 0.),
+                ],
+                0.1,
+            ),
+            Transform::from_xyz(0., 1., -5.),
+        ))
+        .id();
+
+    step(&mut app, 128);
+
+    let ball = app.world().get::<Position>(ball).unwrap();
+    assert!(
+        ball.y > 2. && ball.y < 2.35,
+        "ball fell past the rails: {ball:?}"
+    );
+    let pipe = app.world().get::<Position>(pipe).unwrap();
+    assert!(
+        (pipe.y - 0.1).abs() < 0.05,
+        "pipe didn't land on the terrain: {pipe:?}"
+    );
+
+    let hit = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            query.cast_rays(
+                // Halfway along the first segment, where it crosses the middle
+                &[(Vec3::new(-7.875, 5., 5.), Dir3::NEG_Y)],
+                10.,
+                true,
+                &SpatialQueryFilter::DEFAULT,
+            )[0]
+        })
+        .unwrap()
+        .expect("ray missed the rail");
+    assert_eq!(hit.entity, rail);
+    assert!((hit.distance - 2.95).abs() < 0.01, "{hit:?}");
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, spawn_ball, step};
+use sdf_peck::{SdfCollider, SdfPredictionDistance};
+
+fn speculative_contacts(prediction: SdfPredictionDistance) -> usize {
+    let mut app = headless_app();
+    app.insert_resource(prediction);
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    let terrain = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sdf(terrain),
+            Transform::default(),
+        ))
+        .id();
+    // Floats 0.2 above the terrain, which curves down to about -0.45 30m from the origin
+    let ball = spawn_ball(&mut app, Vec3::new(30., 0.05, 0.))
+        .insert(GravityScale(0.))
+        .id();
+
+    step(&mut app, 4);
+
+    app.world_mut()
+        .run_system_once(move |collisions: Collisions| {
+            collisions
+                .get(terrain, ball)
+                .map_or(0, |pair| pair.manifolds.len())
+        })
+        .unwrap()
+}
+
+#[test]
+fn sdf_pairs_use_the_configured_prediction_distance() {
+    assert_eq!(speculative_contacts(SdfPredictionDistance::default()), 0);
+    let far = SdfPredictionDistance {
+        sdf: Some(0.5),
+        primitives: None,
+    };
+    assert!(speculative_contacts(far) > 0);
+    let primitives_only = SdfPredictionDistance {
+        sdf: None,
+        primitives: Some(0.5),
+    };
+    assert_eq!(speculative_contacts(primitives_only), 0);
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, step};
+use sdf_peck::{ColliderShape, SdfCollider, SdfQueryGrid, SdfSpatialQuery, WorldOffset};
+
+fn query(app: &mut App) -> (Vec<Option<f32>>, Vec<Entity>) {
+    app.world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            let rays = [
+                (Vec3::new(-20., 0.5, 0.), Dir3::X),
+                (Vec3::new(4.5, 10., 0.), Dir3::NEG_Y),
+                (
+                    Vec3::new(7., 3., 0.),
+                    Dir3::new(Vec3::new(-1., -1., 0.)).unwrap(),
+                ),
+                (Vec3::new(0., 0.5, 5.), Dir3::Z),
+            ];
+            let distances = query
+                .cast_rays(&rays, 100., true, &SpatialQueryFilter::DEFAULT)
+                .into_iter()
+                .map(|hit| hit.map(|hit| hit.distance))
+                .collect();
+            let overlapping = query
+                .shape_contacts(
+                    &ColliderShape::Sphere(Sphere::new(0.5)),
+                    Vec3::new(6., 0.5, 0.),
+                    Quat::IDENTITY,
+                    &SpatialQueryFilter::DEFAULT,
+                )
+                .into_iter()
+                .map(|(entity, _)| entity)
+                .collect();
+            (distances, overlapping)
+        })
+        .unwrap()
+}
+
+#[test]
+fn grid_matches_unfiltered_queries() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::from_xyz(0., -1., 0.),
+    ));
+    for i in 0..10 {
+        app.world_mut().spawn((
+            RigidBody::Static,
+            SdfCollider::sphere(0.4),
+            Transform::from_xyz(i as f32 * 1.5, 0.5, 0.),
+        ));
+    }
+    step(&mut app, 2);
+    let expected = query(&mut app);
+
+    app.insert_resource(SdfQueryGrid::new(2.));
+    step(&mut app, 2);
+    let (distances, overlapping) = query(&mut app);
+
+    assert_eq!(overlapping, expected.1);
+    assert_eq!(overlapping.len(), 1);
+    for (distance, expected) in distances.iter().zip(&expected.0) {
+        match (distance, expected) {
+            (Some(distance), Some(expected)) => assert!((distance - expected).abs() < 1e-4),
+            _ => assert_eq!(distance, expected),
+        }
+    }
+}
+
+#[test]
+fn grid_follows_shifts_of_the_world_offset() {
+    let mut app = headless_app();
+    app.insert_resource(SdfQueryGrid::new(2.))
+        .insert_resource(WorldOffset::default());
+    let sphere = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sphere(0.4),
+            Transform::from_xyz(10., 0.5, 0.),
+        ))
+        .id();
+    step(&mut app, 2);
+
+    // Shift the origin to the sphere, without stepping so the grid isn't rebuilt
+    let shift = Vec3::new(10., 0., 0.);
+    app.world_mut().resource_mut::<WorldOffset>().0 += shift;
+    let world = app.world_mut();
+    let mut positions = world.query::<&mut Position>();
+    for mut pos in positions.iter_mut(world) {
+        pos.0 -= shift;
+    }
+
+    let hit = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            query.cast_rays(
+                &[(Vec3::new(0., 5., 0.), Dir3::NEG_Y)],
+                10.,
+                true,
+                &SpatialQueryFilter::DEFAULT,
+            )[0]
+        })
+        .unwrap()
+        .expect("the ray should hit the sphere at the new origin");
+    assert_eq!(hit.entity, sphere);
+    assert!((hit.distance - 4.1).abs() < 0.01, "{hit:?}");
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, spawn_ball, spawn_floor, step};
+use sdf_peck::{SdfCollider, SdfQueryOnly, SdfSpatialQuery};
+
+#[test]
+fn query_only_colliders_are_hit_by_queries_but_not_bodies() {
+    let mut app = headless_app();
+    let hitbox = spawn_floor(&mut app, 3.).insert(SdfQueryOnly).id();
+    let body = spawn_ball(&mut app, Vec3::new(0., 2., 0.)).id();
+
+    step(&mut app, 64);
+
+    let pos = app.world().get::<Position>(body).unwrap();
+    assert!(
+        pos.y < -0.5,
+        "body landed on a query-only collider: {pos:?}"
+    );
+    let hit = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            query.cast_rays(
+                &[(Vec3::new(2., 5., 0.), Dir3::NEG_Y)],
+                10.,
+                true,
+                &SpatialQueryFilter::DEFAULT,
+            )[0]
+        })
+        .unwrap()
+        .expect("ray missed the query-only collider");
+    assert_eq!(hit.entity, hitbox);
+}
+
+#[test]
+fn query_only_slabs_are_hit_by_queries_but_not_bodies() {
+    let mut app = headless_app();
+    let slab = load_sdf(&mut app, "slab.sdf3d");
+    let volume = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sdf(slab),
+            SdfQueryOnly,
+            Transform::default(),
+        ))
+        .id();
+    let body = spawn_ball(&mut app, Vec3::new(0., 2., 0.)).id();
+
+    step(&mut app, 64);
+
+    let pos = app.world().get::<Position>(body).unwrap();
+    assert!(pos.y < -0.5, "body landed on a query-only slab: {pos:?}");
+    let hit = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            query.cast_rays(
+                &[(Vec3::new(2., 5., 0.), Dir3::NEG_Y)],
+                10.,
+                true,
+                &SpatialQueryFilter::DEFAULT,
+            )[0]
+        })
+        .unwrap()
+        .expect("ray missed the query-only slab");
+    assert_eq!(hit.entity, volume);
+    assert!((hit.distance - 4.8).abs() < 0.01, "{hit:?}");
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use avian3d::prelude::*;
+use bevy::{asset::AssetPlugin, ecs::system::RunSystemOnce, prelude::*, time::TimeUpdateStrategy};
+use bevy_prototype_sdf::SdfPlugin;
+use sdf_peck::{SdfCollider, SdfQueryPlugin, SdfSpatialQuery};
+
+const TIMESTEP: f64 = 1. / 64.;
+
+/// An app with SDF spatial queries, but no solver, narrow phase or SDF contacts.
+fn query_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: "tests/assets".into(),
+            ..default()
+        },
+        TransformPlugin,
+        SdfPlugin,
+        PhysicsSchedulePlugin::default(),
+        PhysicsTransformPlugin::default(),
+        ColliderHierarchyPlugin,
+        ColliderTransformPlugin::default(),
+        SdfQueryPlugin::default(),
+    ))
+    .insert_resource(Time::<Fixed>::from_seconds(TIMESTEP))
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        TIMESTEP,
+    )));
+    app.finish();
+    app.cleanup();
+    app
+}
+
+#[test]
+fn raycasts_without_the_collision_plugin() {
+    let mut app = query_app();
+    let target = app
+        .world_mut()
+        .spawn((
+            SdfCollider::ellipsoid(Vec3::new(3., 0.2, 3.)),
+            Transform::from_xyz(0., 1., 0.),
+        ))
+        .id();
+    for _ in 0..4 {
+        app.update();
+    }
+
+    let (sdf_hit, spatial_hit) = app
+        .world_mut()
+        .run_system_once(
+            |sdf_query: SdfSpatialQuery, spatial_query: SpatialQuery<SdfCollider>| {
+                let filter = SpatialQueryFilter::DEFAULT;
+                (
+                    sdf_query.cast_rays(
+                        &[(Vec3::new(1., 5., 0.), Dir3::NEG_Y)],
+                        10.,
+                        true,
+                        &filter,
+                    )[0],
+                    spatial_query.cast_ray(Vec3::new(1., 5., 0.), Dir3::NEG_Y, 10., true, &filter),
+                )
+            },
+        )
+        .unwrap();
+
+    // The top of the ellipsoid is a little below y = 1.2 a unit from its center
+    for hit in [sdf_hit, spatial_hit] {
+        let hit = hit.expect("the ray should hit the collider");
+        assert_eq!(hit.entity, target);
+        assert!((hit.distance - 3.8).abs() < 0.02, "{hit:?}");
+        assert!(hit.normal.y > 0.9, "{hit:?}");
+    }
+}
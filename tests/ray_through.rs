@@ -0,0 +1,49 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, step};
+use sdf_peck::{RayPassHit, SdfCollider, SdfSpatialQuery};
+
+fn cast_through(app: &mut App, origin: Vec3) -> Option<RayPassHit> {
+    app.world_mut()
+        .run_system_once(move |query: SdfSpatialQuery| {
+            query.cast_ray_through(origin, Dir3::NEG_Y, 20., &SpatialQueryFilter::DEFAULT)
+        })
+        .unwrap()
+}
+
+#[test]
+fn rays_report_where_they_leave_colliders() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::from_xyz(0., -1., 0.),
+    ));
+    let glass = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::ellipsoid(Vec3::new(2., 0.2, 2.)),
+            Transform::from_xyz(0., 3., 0.),
+        ))
+        .id();
+    step(&mut app, 2);
+
+    let hit = cast_through(&mut app, Vec3::new(0., 5., 0.)).unwrap();
+    assert_eq!(hit.entity, glass);
+    assert!((hit.entry - 1.8).abs() < 0.01, "{hit:?}");
+    let (exit, exit_normal) = hit.exit.unwrap();
+    assert!((exit - 2.2).abs() < 0.01, "{hit:?}");
+    assert!(exit_normal.abs_diff_eq(Vec3::NEG_Y, 0.01), "{hit:?}");
+    assert!((hit.thickness(20.) - 0.4).abs() < 0.02, "{hit:?}");
+
+    // The terrain is far thicker than the rest of the ray
+    let hit = cast_through(&mut app, Vec3::new(5., 5., 0.)).unwrap();
+    assert_ne!(hit.entity, glass);
+    assert!((hit.entry - 6.).abs() < 0.05, "{hit:?}");
+    assert!(hit.exit.is_none(), "{hit:?}");
+    assert!((hit.thickness(20.) - 14.).abs() < 0.05, "{hit:?}");
+}
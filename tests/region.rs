@@ -0,0 +1,50 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{math::bounding::Aabb3d, prelude::*};
+use common::{headless_app, load_sdf, step};
+use sdf_peck::SdfCollider;
+
+#[test]
+fn only_the_region_of_a_collider_is_solid() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    let region = Aabb3d::new(Vec3::ZERO, Vec3::splat(5.));
+    let ground = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sdf(terrain).with_region(Some(region)),
+            Transform::from_xyz(0., -1., 0.),
+        ))
+        .id();
+    let ball = |app: &mut App, x: f32| {
+        app.world_mut()
+            .spawn((
+                RigidBody::Dynamic,
+                SdfCollider::sphere(0.5),
+                Transform::from_xyz(x, 1., 0.),
+            ))
+            .id()
+    };
+    let inside = ball(&mut app, 0.);
+    let outside = ball(&mut app, 30.);
+    step(&mut app, 128);
+
+    let aabb = app.world().get::<ColliderAabb>(ground).unwrap();
+    assert!(aabb.max.x < 6. && aabb.min.x > -6., "{aabb:?}");
+    let inside = app.world().get::<Position>(inside).unwrap();
+    assert!((inside.y + 0.5).abs() < 0.05, "{inside:?}");
+    let outside = app.world().get::<Position>(outside).unwrap();
+    assert!(outside.y < -5., "{outside:?}");
+
+    // Moving the region makes the terrain solid around the other ball instead
+    app.world_mut()
+        .get_mut::<SdfCollider>(ground)
+        .unwrap()
+        .set_region(Some(Aabb3d::new(Vec3::new(30., 0., 0.), Vec3::splat(5.))));
+    let ball = ball(&mut app, 30.);
+    step(&mut app, 128);
+    let ball = app.world().get::<Position>(ball).unwrap();
+    assert!((ball.y + 0.95).abs() < 0.05, "{ball:?}");
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy_prototype_sdf::Sdf3d;
+use common::{headless_app, load_sdf, reload_sdf, step};
+use sdf_peck::{SdfCollider, SdfRecomputeBudget};
+
+#[test]
+fn resting_body_resettles_after_hot_reload() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    let raised = load_sdf(&mut app, "raised_terrain.sdf3d");
+
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain.clone()),
+        Transform::default(),
+    ));
+    let ball = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::sphere(0.5),
+            Transform::from_xyz(0., 2., 0.),
+        ))
+        .id();
+
+    step(&mut app, 300);
+    let pos = app.world().get::<Position>(ball).unwrap();
+    assert!((pos.y - 0.5).abs() < 0.05, "ball didn't settle: {pos:?}");
+
+    let raised_sdf = app
+        .world()
+        .resource::<Assets<Sdf3d>>()
+        .get(raised.id())
+        .unwrap()
+        .clone();
+    reload_sdf(&mut app, &terrain, raised_sdf);
+    step(&mut app, 300);
+
+    let pos = app.world().get::<Position>(ball).unwrap();
+    assert!(
+        (pos.y - 1.5).abs() < 0.05,
+        "ball didn't resettle on the reloaded surface: {pos:?}"
+    );
+    let velocity = app.world().get::<LinearVelocity>(ball).unwrap();
+    assert!(
+        velocity.length() < 0.1,
+        "ball is still moving: {velocity:?}"
+    );
+}
+
+/// Colliders marked as changed, which makes avian recompute their mass, in order.
+#[derive(Resource, Default)]
+struct ChangedColliders(Vec<Entity>);
+
+#[test]
+fn budget_recomputes_colliders_nearest_bodies_first() {
+    let mut app = headless_app();
+    app.insert_resource(SdfRecomputeBudget {
+        colliders_per_frame: 1,
+    })
+    .init_resource::<ChangedColliders>()
+    .add_systems(
+        PostUpdate,
+        |colliders: Query<Entity, Changed<SdfCollider>>, mut changed: ResMut<ChangedColliders>| {
+            changed.0.extend(colliders.iter());
+        },
+    );
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    let raised = load_sdf(&mut app, "raised_terrain.sdf3d");
+
+    let colliders: Vec<Entity> = [0., 20., 40.]
+        .into_iter()
+        .map(|x| {
+            app.world_mut()
+                .spawn((
+                    RigidBody::Static,
+                    SdfCollider::sdf(terrain.clone()),
+                    Transform::from_xyz(x, 0., 0.),
+                ))
+                .id()
+        })
+        .collect();
+    let ball = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::sphere(0.5),
+            Transform::from_xyz(40., 2., 0.),
+        ))
+        .id();
+    step(&mut app, 10);
+    app.world_mut().resource_mut::<ChangedColliders>().0.clear();
+
+    let raised_sdf = app
+        .world()
+        .resource::<Assets<Sdf3d>>()
+        .get(raised.id())
+        .unwrap()
+        .clone();
+    reload_sdf(&mut app, &terrain, raised_sdf);
+    step(&mut app, 1);
+
+    // Every AABB covers the raised surface right away
+    for &entity in &colliders {
+        let aabb = app.world().get::<ColliderAabb>(entity).unwrap();
+        assert!(aabb.max.y > 0.5, "AABB wasn't refreshed: {aabb:?}");
+    }
+
+    // Only the mass waits, one collider per frame, starting under the ball
+    step(&mut app, 4);
+    let changed = &app.world().resource::<ChangedColliders>().0;
+    let changed: Vec<Entity> = changed.iter().copied().filter(|&e| e != ball).collect();
+    assert_eq!(changed, [colliders[2], colliders[1], colliders[0]]);
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, step};
+use sdf_peck::{SdfCollider, SdfSpatialQuery};
+
+#[test]
+fn scene_distance_finds_nearest_collider() {
+    let mut app = headless_app();
+    let near = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sphere(1.),
+            Transform::from_xyz(3., 0., 0.),
+        ))
+        .id();
+    let far = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sphere(2.),
+            Transform::from_xyz(-10., 0., 0.),
+        ))
+        .id();
+    step(&mut app, 2);
+
+    let (outside, inside, out_of_range) = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            let filter = SpatialQueryFilter::DEFAULT;
+            (
+                query.distance(Vec3::ZERO, 100., &filter),
+                query.distance(Vec3::new(-10.5, 0., 0.), 100., &filter),
+                query.distance(Vec3::new(0., 50., 0.), 5., &filter),
+            )
+        })
+        .unwrap();
+
+    let outside = outside.unwrap();
+    assert_eq!(outside.entity, near);
+    assert!((outside.distance - 2.).abs() < 1e-4, "{outside:?}");
+    let inside = inside.unwrap();
+    assert_eq!(inside.entity, far);
+    assert!((inside.distance + 1.5).abs() < 1e-4, "{inside:?}");
+    assert!(out_of_range.is_none());
+}
+
+#[test]
+fn avoidance_pushes_agents_away_from_nearby_colliders() {
+    let mut app = headless_app();
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sphere(1.),
+        Transform::default(),
+    ));
+    step(&mut app, 2);
+
+    let agents = [
+        Vec3::new(1.5, 0., 0.),
+        Vec3::new(0., 1.2, 0.),
+        Vec3::new(0., 0., 5.),
+    ];
+    let repulsion = app
+        .world_mut()
+        .run_system_once(move |query: SdfSpatialQuery| {
+            query.avoidance(&agents, 2., &SpatialQueryFilter::DEFAULT)
+        })
+        .unwrap();
+
+    assert_eq!(repulsion.len(), 3);
+    assert!(repulsion[0].normalize().abs_diff_eq(Vec3::X, 1e-4));
+    assert!(repulsion[1].normalize().abs_diff_eq(Vec3::Y, 1e-4));
+    // Closer agents are pushed harder
+    assert!(repulsion[1].length() > repulsion[0].length());
+    assert_eq!(repulsion[2], Vec3::ZERO);
+}
+
+#[test]
+fn escape_points_out_of_the_deepest_collider() {
+    let mut app = headless_app();
+    let ellipsoid = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::ellipsoid(Vec3::new(4., 1., 4.)),
+            Transform::from_scale(Vec3::splat(2.)),
+        ))
+        .id();
+    step(&mut app, 2);
+
+    let (inside, outside) = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            let filter = SpatialQueryFilter::DEFAULT;
+            (
+                query.escape(Vec3::new(0., 1.5, 0.), &filter),
+                query.escape(Vec3::new(0., 3., 0.), &filter),
+            )
+        })
+        .unwrap();
+
+    let inside = inside.unwrap();
+    assert_eq!(inside.entity, ellipsoid);
+    assert!((inside.depth - 0.5).abs() < 0.05, "{inside:?}");
+    assert!(inside.direction.abs_diff_eq(Vec3::Y, 1e-3));
+    assert!(outside.is_none());
+}
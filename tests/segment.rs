@@ -0,0 +1,63 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, spawn_ball, step};
+use sdf_peck::{SdfCollider, SdfSpatialQuery};
+
+#[test]
+fn segments_collide_and_are_hit_by_rays() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::default(),
+    ));
+    let wire = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::segment(Vec3::new(-2., 0., 0.), Vec3::new(2., 0., 0.)),
+            Transform::from_xyz(0., 1., 5.),
+        ))
+        .id();
+    let ball = spawn_ball(&mut app, Vec3::new(0., 3., 5.)).id();
+    let rod = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::segment(Vec3::new(-1., 0., 0.), Vec3::new(1., 0., 0.)),
+            Transform::from_xyz(0., 1., -5.),
+        ))
+        .id();
+
+    step(&mut app, 128);
+
+    let ball = app.world().get::<Position>(ball).unwrap();
+    assert!(
+        (ball.y - 1.3).abs() < 0.05,
+        "ball fell past the wire: {ball:?}"
+    );
+    // The terrain curves down slightly 5m away from the origin
+    let rod = app.world().get::<Position>(rod).unwrap();
+    assert!(
+        rod.y.abs() < 0.05,
+        "rod didn't land on the terrain: {rod:?}"
+    );
+
+    let hit = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            query.cast_rays(
+                &[(Vec3::new(1.5, 3., 5.), Dir3::NEG_Y)],
+                10.,
+                true,
+                &SpatialQueryFilter::DEFAULT,
+            )[0]
+        })
+        .unwrap()
+        .expect("ray missed the wire");
+    assert_eq!(hit.entity, wire);
+    assert!((hit.distance - 2.).abs() < 0.01, "{hit:?}");
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, load_sdf, step};
+use sdf_peck::{SdfCollider, SdfSpatialQuery};
+
+#[test]
+fn casts_report_every_surface_they_cross() {
+    let mut app = headless_app();
+    // A hollow sphere with a wall 0.02 thick, crossed twice on each side
+    let shell = load_sdf(&mut app, "thin_shell.sdf3d");
+    let bubble = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sdf(shell),
+            Transform::default(),
+        ))
+        .id();
+    let pillar = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sphere(0.5),
+            Transform::from_xyz(0., -6., 0.),
+        ))
+        .id();
+    step(&mut app, 2);
+
+    let crossings = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            query.shape_cast_all(
+                &Sphere::new(0.1),
+                Vec3::new(0., 5., 0.),
+                Dir3::NEG_Y,
+                u32::MAX,
+                &ShapeCastConfig::from_max_distance(20.),
+                &SpatialQueryFilter::DEFAULT,
+            )
+        })
+        .unwrap();
+
+    let expected = [
+        (bubble, 1.9, true),
+        (bubble, 2.12, false),
+        (bubble, 7.88, true),
+        (bubble, 8.1, false),
+        (pillar, 10.4, true),
+        (pillar, 11.6, false),
+    ];
+    assert_eq!(crossings.len(), expected.len(), "{crossings:?}");
+    for (crossing, (entity, distance, entering)) in crossings.iter().zip(expected) {
+        assert_eq!(crossing.entity, entity, "{crossing:?}");
+        assert_eq!(crossing.entering, entering, "{crossing:?}");
+        assert!((crossing.distance - distance).abs() < 0.01, "{crossing:?}");
+        // Surfaces face against the cast where it enters them, and along it where it leaves
+        let normal = if entering { Vec3::Y } else { Vec3::NEG_Y };
+        assert!(crossing.normal.abs_diff_eq(normal, 0.01), "{crossing:?}");
+    }
+}
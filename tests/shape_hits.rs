@@ -0,0 +1,55 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use common::{headless_app, step};
+use sdf_peck::{SdfCollider, SdfSpatialQuery};
+
+#[test]
+fn casts_against_scaled_colliders() {
+    let mut app = headless_app();
+    // Scaled up to 2 wide and 1 tall, so its top is at y = 1
+    let ellipsoid = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::ellipsoid(Vec3::new(1., 0.5, 1.)),
+            Transform::from_xyz(3., 0., 0.).with_scale(Vec3::splat(2.)),
+        ))
+        .id();
+    step(&mut app, 2);
+
+    let (hits, sphere_hit) = app
+        .world_mut()
+        .run_system_once(|query: SdfSpatialQuery| {
+            let filter = SpatialQueryFilter::DEFAULT;
+            (
+                query.shape_hits(
+                    &Sphere::new(0.25),
+                    Vec3::new(3., 5., 0.),
+                    Dir3::NEG_Y,
+                    1,
+                    &ShapeCastConfig::from_max_distance(10.),
+                    &filter,
+                ),
+                query.sphere_cast(Vec3::new(3., 5., 0.), Dir3::NEG_Y, 0.25, 10., &filter),
+            )
+        })
+        .unwrap();
+
+    let hit = hits.first().expect("the cast should hit the ellipsoid");
+    assert_eq!(hit.entity, ellipsoid);
+    assert!((hit.distance - 3.75).abs() < 0.01, "{hit:?}");
+    assert!(
+        hit.point1.abs_diff_eq(Vec3::new(3., 1., 0.), 0.01),
+        "{hit:?}"
+    );
+    assert!(hit.normal1.abs_diff_eq(Vec3::Y, 0.01), "{hit:?}");
+
+    let sphere_hit = sphere_hit.expect("the sphere cast should hit the ellipsoid");
+    assert!((sphere_hit.distance - 3.75).abs() < 0.01, "{sphere_hit:?}");
+    assert!(
+        sphere_hit.center.abs_diff_eq(Vec3::new(3., 1.25, 0.), 0.01),
+        "{sphere_hit:?}"
+    );
+}
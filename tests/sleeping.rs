@@ -0,0 +1,44 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, load_sdf, spawn_ball, step};
+use sdf_peck::{ContactStabilization, SdfCollider};
+
+/// How many of the balls resting at the bottom of a bowl fall asleep.
+fn sleeping_balls(quantum: Option<f32>) -> usize {
+    let mut app = headless_app();
+    app.insert_resource(ContactStabilization {
+        quantum,
+        ..default()
+    });
+    // The inside of a sphere with a radius of 7
+    let bowl = load_sdf(&mut app, "sphere_stage.sdf3d");
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(bowl),
+        Transform::default(),
+    ));
+    let balls: Vec<_> = (0..8)
+        .map(|i| {
+            let x = (i % 4) as f32 - 1.5;
+            let z = (i / 4) as f32 - 0.5;
+            spawn_ball(&mut app, Vec3::new(x, -6., z)).id()
+        })
+        .collect();
+
+    step(&mut app, 640);
+
+    balls
+        .into_iter()
+        .filter(|&ball| app.world().get::<Sleeping>(ball).is_some())
+        .count()
+}
+
+#[test]
+fn quantized_contacts_let_resting_bodies_sleep() {
+    let slept = sleeping_balls(None);
+    assert_eq!(slept, 0, "{slept} balls slept without quantization");
+    let slept = sleeping_balls(Some(0.005));
+    assert_eq!(slept, 8, "only {slept} balls slept with quantization");
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, load_sdf, spawn_ball, spawn_floor, step, BALL_REST_HEIGHT};
+use sdf_peck::{SdfCollider, SlopeFriction};
+
+/// How far a body that can't roll moves along a 20 degree ramp in a second.
+///
+/// The ramp is the floor, or the terrain asset when `terrain` is set.
+fn slide_distance(slope: Option<SlopeFriction>, terrain: bool) -> f32 {
+    let mut app = headless_app();
+    let tilt = Quat::from_rotation_z(20f32.to_radians());
+    // The terrain's surface is at the origin, the floor's 0.2 above it
+    let (mut ramp, rest_height) = if terrain {
+        let sdf = load_sdf(&mut app, "terrain.sdf3d");
+        let ramp = app
+            .world_mut()
+            .spawn((RigidBody::Static, SdfCollider::sdf(sdf)));
+        (ramp, BALL_REST_HEIGHT - 0.2)
+    } else {
+        (spawn_floor(&mut app, 4.), BALL_REST_HEIGHT)
+    };
+    ramp.insert(Transform::from_rotation(tilt));
+    if let Some(slope) = slope {
+        ramp.insert(slope);
+    }
+    let start = tilt * Vec3::Y * rest_height;
+    let body = spawn_ball(&mut app, start)
+        .insert(LockedAxes::ROTATION_LOCKED)
+        .id();
+
+    step(&mut app, 64);
+    app.world().get::<Position>(body).unwrap().distance(start)
+}
+
+#[test]
+fn steep_slopes_lose_their_friction() {
+    for terrain in [false, true] {
+        // The default friction holds the body on a slope this gentle
+        let held = slide_distance(None, terrain);
+        assert!(held < 0.1, "body slid {held} without slope friction");
+        let held = slide_distance(Some(SlopeFriction::new(30f32.to_radians())), terrain);
+        assert!(held < 0.1, "body slid {held} below the max angle");
+
+        let slid = slide_distance(Some(SlopeFriction::new(15f32.to_radians())), terrain);
+        assert!(slid > 0.5, "body only slid {slid} above the max angle");
+    }
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, load_sdf, spawn_ball, step};
+use sdf_peck::SdfCollider;
+
+#[test]
+fn sphere_rests_on_sdf_floor() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::default(),
+    ));
+    let ball = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::sphere(0.4),
+            Transform::from_xyz(0., 3., 0.),
+        ))
+        .id();
+
+    step(&mut app, 300);
+
+    let pos = app.world().get::<Position>(ball).unwrap();
+    assert!((pos.y - 0.4).abs() < 0.03, "ball rests at {pos:?}");
+    let velocity = app.world().get::<LinearVelocity>(ball).unwrap();
+    assert!(
+        velocity.length() < 0.1,
+        "ball is still moving: {velocity:?}"
+    );
+}
+
+#[test]
+fn fast_capsule_does_not_tunnel_into_sdf_floor() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::default(),
+    ));
+    let capsule = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::capsule(0.3, 1.),
+            LinearVelocity(Vec3::NEG_Y * 40.),
+            Transform::from_xyz(0., 4., 0.).with_rotation(Quat::from_rotation_z(0.4)),
+        ))
+        .id();
+
+    for _ in 0..120 {
+        step(&mut app, 1);
+        let pos = app.world().get::<Position>(capsule).unwrap();
+        assert!(pos.y > 0., "capsule tunneled to {pos:?}");
+    }
+
+    // Resting on its side
+    let pos = app.world().get::<Position>(capsule).unwrap();
+    assert!((pos.y - 0.3).abs() < 0.05, "capsule rests at {pos:?}");
+}
+
+#[test]
+fn capsule_spawned_inside_sdf_floor_climbs_out_gently() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(terrain),
+        Transform::default(),
+    ));
+    // The whole capsule is two units below the surface
+    let capsule = app
+        .world_mut()
+        .spawn((
+            RigidBody::Dynamic,
+            SdfCollider::capsule(0.3, 1.),
+            Transform::from_xyz(0., -2., 0.),
+        ))
+        .id();
+
+    for _ in 0..300 {
+        step(&mut app, 1);
+        let velocity = app.world().get::<LinearVelocity>(capsule).unwrap();
+        assert!(velocity.length() < 8., "capsule was launched: {velocity:?}");
+    }
+
+    // Standing or fallen over, the capsule rests on the surface
+    let pos = app.world().get::<Position>(capsule).unwrap();
+    assert!(pos.y > 0.2 && pos.y < 0.85, "capsule is at {pos:?}");
+}
+
+#[test]
+fn scaled_container_keeps_bodies_inside() {
+    let mut app = headless_app();
+    let stage = load_sdf(&mut app, "sphere_stage.sdf3d");
+
+    // An inverted sphere with a radius of 7, scaled down to 3.5
+    const RADIUS: f32 = 3.5;
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::sdf(stage),
+        Transform::from_scale(Vec3::splat(0.5)),
+    ));
+    let bodies = (0..8)
+        .map(|i| {
+            let direction = Quat::from_rotation_y(i as f32 * 0.8) * Vec3::new(1., 0.6, 0.);
+            app.world_mut()
+                .spawn((
+                    RigidBody::Dynamic,
+                    if i % 2 == 0 {
+                        SdfCollider::sphere(0.25)
+                    } else {
+                        SdfCollider::capsule(0.2, 0.5)
+                    },
+                    LinearVelocity(direction.normalize() * 15.),
+                    Transform::from_translation(direction * 0.5),
+                ))
+                .id()
+        })
+        .collect::<Vec<_>>();
+
+    for _ in 0..240 {
+        step(&mut app, 1);
+        for &body in bodies.iter() {
+            let pos = app.world().get::<Position>(body).unwrap();
+            assert!(pos.length() < RADIUS, "body escaped to {pos:?}");
+        }
+    }
+}
+
+#[test]
+fn fast_kinematic_sdf_platform_carries_bodies() {
+    let mut app = headless_app();
+    let terrain = load_sdf(&mut app, "terrain.sdf3d");
+
+    let platform = app
+        .world_mut()
+        .spawn((
+            RigidBody::Kinematic,
+            SdfCollider::sdf(terrain),
+            LinearVelocity(Vec3::Y * 6.),
+            Transform::default(),
+        ))
+        .id();
+    let ball = spawn_ball(&mut app, Vec3::new(0., 0.31, 0.)).id();
+
+    for _ in 0..60 {
+        step(&mut app, 1);
+        let platform = app.world().get::<Position>(platform).unwrap().y;
+        let ball = app.world().get::<Position>(ball).unwrap().y;
+        assert!(
+            ball - platform > 0.25,
+            "ball sank into the platform: {ball} vs {platform}"
+        );
+    }
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::{headless_app, step, TIMESTEP};
+use sdf_peck::SdfCollider;
+
+#[derive(Resource, Default)]
+struct Started(bool);
+
+/// Fires a bullet that moves a full meter per step through a plate 4cm thick.
+fn fire_through_plate(bullet: SdfCollider) -> bool {
+    let mut app = headless_app();
+    app.insert_resource(Gravity(Vec3::ZERO))
+        .init_resource::<Started>()
+        .add_observer(|_: On<CollisionStart>, mut started: ResMut<Started>| {
+            started.0 = true;
+        });
+
+    app.world_mut().spawn((
+        RigidBody::Static,
+        SdfCollider::ellipsoid(Vec3::new(5., 0.02, 5.)),
+        Transform::default(),
+    ));
+    app.world_mut().spawn((
+        RigidBody::Dynamic,
+        bullet,
+        CollisionEventsEnabled,
+        SpeculativeMargin(0.),
+        LinearVelocity(Vec3::NEG_Y / TIMESTEP as f32),
+        Transform::from_xyz(0., 0.5, 0.),
+    ));
+
+    step(&mut app, 4);
+    app.world().resource::<Started>().0
+}
+
+#[test]
+fn swept_sphere_hits_what_it_passed_through() {
+    assert!(!fire_through_plate(SdfCollider::sphere(0.05)));
+    assert!(fire_through_plate(SdfCollider::swept_sphere(0.05)));
+}
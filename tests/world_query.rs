@@ -0,0 +1,42 @@
+mod common;
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use common::headless_app;
+use sdf_peck::{SdfCollider, SdfWorldQuery};
+
+#[test]
+fn world_queries_run_without_stepping_physics() {
+    let mut app = headless_app();
+    let sphere = app
+        .world_mut()
+        .spawn((
+            RigidBody::Static,
+            SdfCollider::sphere(0.5),
+            Position::from_xyz(0., 0., 3.),
+            Rotation::default(),
+        ))
+        .id();
+    let mut queries = SdfWorldQuery::new(app.world_mut());
+
+    let cast = |queries: &mut SdfWorldQuery, world: &World| {
+        queries.get(world).cast_rays(
+            &[(Vec3::ZERO, Dir3::Z)],
+            10.,
+            true,
+            &SpatialQueryFilter::DEFAULT,
+        )[0]
+    };
+
+    let hit = cast(&mut queries, app.world()).expect("the ray should hit the sphere");
+    assert_eq!(hit.entity, sphere);
+    assert!((hit.distance - 2.5).abs() < 0.01, "{hit:?}");
+
+    // Moves between queries are seen without running the schedule
+    app.world_mut().get_mut::<Position>(sphere).unwrap().0 = Vec3::new(0., 0., 6.);
+    let hit = cast(&mut queries, app.world()).expect("the ray should hit the moved sphere");
+    assert!((hit.distance - 5.5).abs() < 0.01, "{hit:?}");
+
+    app.world_mut().get_mut::<Position>(sphere).unwrap().0 = Vec3::new(0., 5., 6.);
+    assert!(cast(&mut queries, app.world()).is_none());
+}